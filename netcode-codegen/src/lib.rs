@@ -0,0 +1,673 @@
+use netcode::{
+    wire_type, FieldKind, FieldSchema, MessageLayout, MessageSchema, NetSchema, Quantization,
+    WireFormat, WireType,
+};
+use std::fmt::Write;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "yield",
+];
+
+/// Generates Rust structs implementing `netcode::NetMessage` for every message
+/// in `schema`, sorted by name so the output is stable between runs.
+pub fn generate(schema: &NetSchema) -> Result<String, String> {
+    let mut names: Vec<&String> = schema.messages.keys().collect();
+    names.sort();
+
+    let mut out = String::new();
+    out.push_str("// @generated by netcode-codegen. Do not edit by hand.\n\n");
+    out.push_str("#[allow(unused_imports)]\n");
+    out.push_str("use netcode::wire::{write_f32, write_f64, Cursor, WireFormat, WireType};\n");
+    out.push_str("#[allow(unused_imports)]\n");
+    out.push_str("use netcode::{CodecError, CodecResult, NetMessage, Quantization};\n");
+
+    for name in names {
+        let message = &schema.messages[name];
+        let mut fields: Vec<&FieldSchema> = message.fields.iter().collect();
+        fields.sort_by_key(|field| field.number);
+        for field in &fields {
+            let field = match &field.map {
+                Some(map) => &map.value,
+                None => *field,
+            };
+            if field.kind == FieldKind::Message {
+                match field.type_name.as_deref() {
+                    Some(type_name) if schema.messages.contains_key(type_name) => {}
+                    other => {
+                        return Err(format!(
+                            "Unknown message type for {}.{}: {other:?}",
+                            message.name, field.name
+                        ))
+                    }
+                }
+            }
+        }
+        write_quantization_consts(&mut out, message, &fields);
+        write_oneof_enums(&mut out, message, &fields);
+        out.push('\n');
+        write_struct(&mut out, message, &fields);
+        out.push('\n');
+        write_impl(&mut out, message, &fields);
+    }
+    Ok(out)
+}
+
+fn write_quantization_consts(out: &mut String, message: &MessageSchema, fields: &[&FieldSchema]) {
+    for field in fields {
+        let Some(quantization) = field.quantization else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "\nconst {}: Quantization = Quantization::{};",
+            quantization_const(message, field),
+            quantization_literal(quantization)
+        );
+    }
+}
+
+fn quantization_literal(quantization: Quantization) -> String {
+    match quantization {
+        Quantization::Range { min, max, bits } => {
+            format!("Range {{\n    min: {min:?},\n    max: {max:?},\n    bits: {bits},\n}}")
+        }
+        Quantization::FixedPoint { scale, bits } => {
+            format!("FixedPoint {{\n    scale: {scale:?},\n    bits: {bits},\n}}")
+        }
+    }
+}
+
+fn quantization_const(message: &MessageSchema, field: &FieldSchema) -> String {
+    format!(
+        "{}_{}_QUANTIZATION",
+        screaming_snake_case(&message.name),
+        screaming_snake_case(&field.name)
+    )
+}
+
+fn screaming_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (index, ch) in name.chars().enumerate() {
+        if ch.is_ascii_uppercase() && index > 0 && !out.ends_with('_') {
+            out.push('_');
+        }
+        out.push(ch.to_ascii_uppercase());
+    }
+    out
+}
+
+fn write_oneof_enums(out: &mut String, message: &MessageSchema, fields: &[&FieldSchema]) {
+    for oneof in &message.oneofs {
+        out.push_str("\n#[derive(Debug, Clone, PartialEq)]\n");
+        let _ = writeln!(out, "pub enum {} {{", oneof_type(message, &oneof.name));
+        for field in fields {
+            if field.oneof.as_ref() == Some(&oneof.name) {
+                let _ = writeln!(
+                    out,
+                    "    {}({}),",
+                    pascal_case(&field.name),
+                    field_type(field)
+                );
+            }
+        }
+        out.push_str("}\n");
+    }
+}
+
+fn write_struct(out: &mut String, message: &MessageSchema, fields: &[&FieldSchema]) {
+    out.push_str("#[derive(Debug, Clone, Default, PartialEq)]\n");
+    if fields.is_empty() {
+        let _ = writeln!(out, "pub struct {} {{}}", message.name);
+        return;
+    }
+    let _ = writeln!(out, "pub struct {} {{", message.name);
+    let mut oneofs: Vec<&str> = Vec::new();
+    for field in fields {
+        // Members of a oneof share one field holding the enum, placed where
+        // the first member would be.
+        if let Some(oneof) = field.oneof.as_deref() {
+            if !oneofs.contains(&oneof) {
+                oneofs.push(oneof);
+                let _ = writeln!(
+                    out,
+                    "    pub {}: Option<{}>,",
+                    ident(oneof),
+                    oneof_type(message, oneof)
+                );
+            }
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "    pub {}: Option<{}>,",
+            field_ident(field),
+            field_type(field)
+        );
+    }
+    out.push_str("}\n");
+}
+
+fn write_impl(out: &mut String, message: &MessageSchema, fields: &[&FieldSchema]) {
+    let _ = writeln!(out, "impl NetMessage for {} {{", message.name);
+    let _ = writeln!(out, "    const NAME: &'static str = \"{}\";", message.name);
+    out.push('\n');
+
+    if message.layout == MessageLayout::Bitmap {
+        write_bitmap_encode(out, message);
+        out.push('\n');
+        write_bitmap_decode(out, message);
+        out.push_str("}\n");
+        return;
+    }
+
+    out.push_str(
+        "    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {\n",
+    );
+    if fields.is_empty() {
+        out.push_str("        format.write_u16(buffer, 0);\n");
+    } else {
+        out.push_str("        let mut count = 0u16;\n");
+        let mut oneofs: Vec<&str> = Vec::new();
+        for field in fields {
+            let place = match field.oneof.as_deref() {
+                Some(oneof) if oneofs.contains(&oneof) => continue,
+                Some(oneof) => {
+                    oneofs.push(oneof);
+                    ident(oneof)
+                }
+                None => field_ident(field),
+            };
+            let _ = writeln!(
+                out,
+                "        if self.{place}.is_some() {{\n            count += 1;\n        }}"
+            );
+        }
+        out.push_str("        format.write_u16(buffer, count);\n");
+    }
+    let typed = message.layout == MessageLayout::Typed;
+    for field in fields {
+        let _ = writeln!(out, "        if let {} {{", field_binding(message, field));
+        if !typed {
+            let _ = writeln!(
+                out,
+                "            format.write_u16(buffer, {});",
+                field.number
+            );
+            write_encode_value(out, message, field, "            ");
+        } else if is_wrapped(field) {
+            let _ = writeln!(
+                out,
+                "            format.write_key(buffer, {}, WireType::Len);",
+                field.number
+            );
+            out.push_str("            let mut nested = Vec::new();\n");
+            out.push_str("            {\n");
+            out.push_str("                let buffer = &mut nested;\n");
+            write_encode_value(out, message, field, "                ");
+            out.push_str("            }\n");
+            out.push_str("            format.write_bytes(buffer, &nested);\n");
+        } else {
+            let _ = writeln!(
+                out,
+                "            format.write_key(buffer, {}, {});",
+                field.number,
+                wire_type_expr(field)
+            );
+            write_encode_value(out, message, field, "            ");
+        }
+        out.push_str("        }\n");
+    }
+    out.push_str("        Ok(())\n");
+    out.push_str("    }\n\n");
+
+    out.push_str("    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {\n");
+    if typed {
+        write_typed_decode(out, message, fields);
+        return;
+    }
+    if fields.is_empty() {
+        out.push_str("        if cursor.read_u16()? > 0 {\n");
+        out.push_str(
+            "            return Err(CodecError::UnknownFieldNumber(cursor.read_u16()?));\n",
+        );
+        out.push_str("        }\n");
+        out.push_str("        Ok(Self {})\n");
+        out.push_str("    }\n");
+        out.push_str("}\n");
+        return;
+    }
+    out.push_str("        let field_count = cursor.read_u16()?;\n");
+    out.push_str("        let mut message = Self::default();\n");
+    out.push_str("        for _ in 0..field_count {\n");
+    out.push_str("            match cursor.read_u16()? {\n");
+    for field in fields {
+        if field.is_repeated || field.map.is_some() || field.oneof.is_some() {
+            let _ = writeln!(out, "                {} => {{", field.number);
+            write_decode_value(out, message, field, "                    ");
+            out.push_str("                }\n");
+        } else {
+            let _ = writeln!(
+                out,
+                "                {} => message.{} = Some({}),",
+                field.number,
+                field_ident(field),
+                decode_expr(message, field, true)
+            );
+        }
+    }
+    out.push_str("                number => return Err(CodecError::UnknownFieldNumber(number)),\n");
+    out.push_str("            }\n");
+    out.push_str("        }\n");
+    out.push_str("        Ok(message)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+}
+
+// Unknown fields are skipped by wire type, like `Codec::read_message`.
+fn write_typed_decode(out: &mut String, message: &MessageSchema, fields: &[&FieldSchema]) {
+    if fields.is_empty() {
+        out.push_str("        for _ in 0..cursor.read_u16()? {\n");
+        out.push_str("            let (_, wire_type) = cursor.read_key()?;\n");
+        out.push_str("            cursor.skip(wire_type)?;\n");
+        out.push_str("        }\n");
+        out.push_str("        Ok(Self {})\n");
+        out.push_str("    }\n");
+        out.push_str("}\n");
+        return;
+    }
+    out.push_str("        let format = cursor.format();\n");
+    out.push_str("        let field_count = cursor.read_u16()?;\n");
+    out.push_str("        let mut message = Self::default();\n");
+    out.push_str("        for _ in 0..field_count {\n");
+    out.push_str("            match cursor.read_key()? {\n");
+    for field in fields {
+        let _ = writeln!(out, "                ({}, wire_type) => {{", field.number);
+        let _ = writeln!(
+            out,
+            "                    if wire_type != {} {{",
+            wire_type_expr(field)
+        );
+        let _ = writeln!(
+            out,
+            "                        return Err(CodecError::WireTypeMismatch(\"{}\".to_string()));",
+            field.name
+        );
+        out.push_str("                    }\n");
+        if is_wrapped(field) {
+            out.push_str(
+                "                    let cursor = &mut Cursor::with_format(cursor.read_bytes()?, format);\n",
+            );
+            write_decode_value(out, message, field, "                    ");
+            out.push_str("                    if !cursor.is_empty() {\n");
+            out.push_str(
+                "                        return Err(CodecError::TrailingBytes(cursor.remaining()));\n",
+            );
+            out.push_str("                    }\n");
+        } else {
+            write_decode_value(out, message, field, "                    ");
+        }
+        out.push_str("                }\n");
+    }
+    out.push_str("                (_, wire_type) => cursor.skip(wire_type)?,\n");
+    out.push_str("            }\n");
+    out.push_str("        }\n");
+    out.push_str("        Ok(message)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+}
+
+/// The wire type of `field` as an expression over `format`, which only
+/// matters for integers.
+fn wire_type_expr(field: &FieldSchema) -> String {
+    let fixed = wire_type(field, WireFormat::Fixed);
+    if fixed == wire_type(field, WireFormat::Compact) {
+        return format!("WireType::{fixed:?}");
+    }
+    match field.kind {
+        FieldKind::Int64 | FieldKind::UInt64 => "format.int64_wire_type()".to_string(),
+        _ => "format.int32_wire_type()".to_string(),
+    }
+}
+
+/// Matches the codec: `Len` values other than strings and bytes get a
+/// length prefix of their own.
+fn is_wrapped(field: &FieldSchema) -> bool {
+    wire_type(field, WireFormat::Fixed) == WireType::Len
+        && (field.is_repeated || !matches!(field.kind, FieldKind::String | FieldKind::Bytes))
+}
+
+// Presence bits follow schema declaration order, not field numbers, to match
+// `Codec::write_message`.
+fn write_bitmap_encode(out: &mut String, message: &MessageSchema) {
+    let fields = &message.fields;
+    if fields.is_empty() {
+        out.push_str(
+            "    fn encode(&self, _buffer: &mut Vec<u8>, _format: WireFormat) -> CodecResult<()> {\n",
+        );
+        out.push_str("        Ok(())\n");
+        out.push_str("    }\n");
+        return;
+    }
+    out.push_str(
+        "    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {\n",
+    );
+    let _ = writeln!(
+        out,
+        "        let mut presence = [0u8; {}];",
+        fields.len().div_ceil(8)
+    );
+    for (index, field) in fields.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "        if {} {{\n            presence[{}] |= {:#04x};\n        }}",
+            is_set_expr(message, field),
+            index / 8,
+            1u8 << (index % 8)
+        );
+    }
+    out.push_str("        buffer.extend_from_slice(&presence);\n");
+    for field in fields {
+        let _ = writeln!(out, "        if let {} {{", field_binding(message, field));
+        write_encode_value(out, message, field, "            ");
+        out.push_str("        }\n");
+    }
+    out.push_str("        Ok(())\n");
+    out.push_str("    }\n");
+}
+
+fn write_bitmap_decode(out: &mut String, message: &MessageSchema) {
+    let fields = &message.fields;
+    if fields.is_empty() {
+        out.push_str("    fn decode(_cursor: &mut Cursor) -> CodecResult<Self> {\n");
+        out.push_str("        Ok(Self {})\n");
+        out.push_str("    }\n");
+        return;
+    }
+    let presence_len = fields.len().div_ceil(8);
+    out.push_str("    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {\n");
+    let _ = writeln!(
+        out,
+        "        let presence = cursor.read_slice({presence_len})?;"
+    );
+    let used_bits = fields.len() % 8;
+    if used_bits > 0 {
+        let _ = writeln!(
+            out,
+            "        if presence[{}] & {:#04x} != 0 {{",
+            presence_len - 1,
+            !((1u8 << used_bits) - 1)
+        );
+        out.push_str("            return Err(CodecError::InvalidPresenceBitmap);\n");
+        out.push_str("        }\n");
+    }
+    out.push_str("        let mut message = Self::default();\n");
+    for (index, field) in fields.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "        if presence[{}] & {:#04x} != 0 {{",
+            index / 8,
+            1u8 << (index % 8)
+        );
+        write_decode_value(out, message, field, "            ");
+        out.push_str("        }\n");
+    }
+    out.push_str("        Ok(message)\n");
+    out.push_str("    }\n");
+}
+
+/// The `if let` pattern binding `value` to a set field.
+fn field_binding(message: &MessageSchema, field: &FieldSchema) -> String {
+    match field.oneof.as_deref() {
+        Some(oneof) => format!(
+            "Some({}::{}(value)) = &self.{}",
+            oneof_type(message, oneof),
+            pascal_case(&field.name),
+            ident(oneof)
+        ),
+        None => format!("Some(value) = &self.{}", field_ident(field)),
+    }
+}
+
+fn is_set_expr(message: &MessageSchema, field: &FieldSchema) -> String {
+    match field.oneof.as_deref() {
+        Some(oneof) => format!(
+            "matches!(self.{}, Some({}::{}(_)))",
+            ident(oneof),
+            oneof_type(message, oneof),
+            pascal_case(&field.name)
+        ),
+        None => format!("self.{}.is_some()", field_ident(field)),
+    }
+}
+
+fn write_encode_value(
+    out: &mut String,
+    message: &MessageSchema,
+    field: &FieldSchema,
+    indent: &str,
+) {
+    if field.is_repeated {
+        write_encode_len(out, field, "at most 65535 items", indent);
+        let _ = writeln!(out, "{indent}for value in value {{");
+        let _ = writeln!(out, "{indent}    {}", encode_expr(message, field, "value"));
+        let _ = writeln!(out, "{indent}}}");
+    } else if let Some(map) = &field.map {
+        write_encode_len(out, field, "at most 65535 map entries", indent);
+        let _ = writeln!(out, "{indent}for (key, value) in value {{");
+        let _ = writeln!(out, "{indent}    {}", encode_expr(message, &map.key, "key"));
+        let _ = writeln!(
+            out,
+            "{indent}    {}",
+            encode_expr(message, &map.value, "value")
+        );
+        let _ = writeln!(out, "{indent}}}");
+    } else {
+        let _ = writeln!(out, "{indent}{}", encode_expr(message, field, "value"));
+    }
+}
+
+/// Writes the u16 count of a list or map, rejecting longer ones like the
+/// codec does. Laid out the way rustfmt would, so generated files stay
+/// formatted.
+fn write_encode_len(out: &mut String, field: &FieldSchema, expected: &str, indent: &str) {
+    let err = format!(
+        "CodecError::invalid_value(\"{}\", \"{expected}\")",
+        field.name
+    );
+    let map_err = format!("{indent}    .map_err(|_| {err})?;");
+    if map_err.len() < 100 {
+        let _ = writeln!(out, "{indent}let len = u16::try_from(value.len())");
+        let _ = writeln!(out, "{map_err}");
+    } else {
+        let _ = writeln!(
+            out,
+            "{indent}let len = u16::try_from(value.len()).map_err(|_| {{"
+        );
+        let _ = writeln!(out, "{indent}    {err}");
+        let _ = writeln!(out, "{indent}}})?;");
+    }
+    let _ = writeln!(out, "{indent}format.write_u16(buffer, len);");
+}
+
+/// Writes the statements decoding `field` and storing it in `message`.
+fn write_decode_value(
+    out: &mut String,
+    message: &MessageSchema,
+    field: &FieldSchema,
+    indent: &str,
+) {
+    let value = if field.is_repeated || field.map.is_some() {
+        let _ = writeln!(out, "{indent}let count = cursor.read_u16()? as usize;");
+        let _ = writeln!(out, "{indent}let mut values = Vec::with_capacity(count);");
+        let _ = writeln!(out, "{indent}for _ in 0..count {{");
+        match &field.map {
+            Some(map) => {
+                let _ = writeln!(
+                    out,
+                    "{indent}    let key = {};",
+                    decode_expr(message, &map.key, false)
+                );
+                let _ = writeln!(
+                    out,
+                    "{indent}    values.push((key, {}));",
+                    decode_expr(message, &map.value, false)
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "{indent}    values.push({});",
+                    decode_expr(message, field, false)
+                );
+            }
+        }
+        let _ = writeln!(out, "{indent}}}");
+        "values".to_string()
+    } else {
+        decode_expr(message, field, true)
+    };
+    match field.oneof.as_deref() {
+        Some(oneof) => {
+            let enum_name = oneof_type(message, oneof);
+            let variant = pascal_case(&field.name);
+            let place = ident(oneof);
+            let _ = writeln!(
+                out,
+                "{indent}if !matches!(message.{place}, None | Some({enum_name}::{variant}(_))) {{"
+            );
+            let _ = writeln!(
+                out,
+                "{indent}    return Err(CodecError::OneofConflict(\"{oneof}\".to_string()));"
+            );
+            let _ = writeln!(out, "{indent}}}");
+            // Oneof members are never repeated, so `value` is a decode
+            // expression; bind it to keep the assignment on one line.
+            let _ = writeln!(out, "{indent}let value = {value};");
+            let _ = writeln!(
+                out,
+                "{indent}message.{place} = Some({enum_name}::{variant}(value));"
+            );
+        }
+        None => {
+            let _ = writeln!(
+                out,
+                "{indent}message.{} = Some({value});",
+                field_ident(field)
+            );
+        }
+    }
+}
+
+fn oneof_type(message: &MessageSchema, oneof: &str) -> String {
+    format!("{}{}", message.name, pascal_case(oneof))
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn ident(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn field_ident(field: &FieldSchema) -> String {
+    ident(&field.name)
+}
+
+fn scalar_type(field: &FieldSchema) -> String {
+    match field.kind {
+        FieldKind::Int32 | FieldKind::Enum => "i32".to_string(),
+        FieldKind::UInt32 => "u32".to_string(),
+        FieldKind::Int64 => "i64".to_string(),
+        FieldKind::UInt64 => "u64".to_string(),
+        FieldKind::Float => "f32".to_string(),
+        FieldKind::Double => "f64".to_string(),
+        FieldKind::Bool => "bool".to_string(),
+        FieldKind::String => "String".to_string(),
+        FieldKind::Bytes => "Vec<u8>".to_string(),
+        FieldKind::Message => field.type_name.clone().unwrap_or_default(),
+        FieldKind::Map => match &field.map {
+            Some(map) => format!(
+                "Vec<({}, {})>",
+                scalar_type(&map.key),
+                scalar_type(&map.value)
+            ),
+            None => "Vec<()>".to_string(),
+        },
+    }
+}
+
+fn field_type(field: &FieldSchema) -> String {
+    let scalar = scalar_type(field);
+    if field.is_repeated {
+        format!("Vec<{scalar}>")
+    } else if field.kind == FieldKind::Message {
+        format!("Box<{scalar}>")
+    } else {
+        scalar
+    }
+}
+
+fn encode_expr(message: &MessageSchema, field: &FieldSchema, var: &str) -> String {
+    if field.quantization.is_some() {
+        return format!(
+            "{}.write(buffer, *{var});",
+            quantization_const(message, field)
+        );
+    }
+    match field.kind {
+        FieldKind::Int32 | FieldKind::Enum => format!("format.write_i32(buffer, *{var});"),
+        FieldKind::UInt32 => format!("format.write_u32(buffer, *{var});"),
+        FieldKind::Int64 => format!("format.write_i64(buffer, *{var});"),
+        FieldKind::UInt64 => format!("format.write_u64(buffer, *{var});"),
+        FieldKind::Float => format!("write_f32(buffer, *{var});"),
+        FieldKind::Double => format!("write_f64(buffer, *{var});"),
+        FieldKind::Bool => format!("buffer.push(*{var} as u8);"),
+        FieldKind::String => format!("format.write_bytes(buffer, {var}.as_bytes());"),
+        FieldKind::Bytes => format!("format.write_bytes(buffer, {var});"),
+        FieldKind::Message => format!("{var}.encode(buffer, format)?;"),
+        // Entries are written by `write_encode_value`.
+        FieldKind::Map => unreachable!("map fields are encoded entry by entry"),
+    }
+}
+
+fn decode_expr(message: &MessageSchema, field: &FieldSchema, boxed: bool) -> String {
+    if field.quantization.is_some() {
+        return format!("{}.read(cursor)?", quantization_const(message, field));
+    }
+    match field.kind {
+        FieldKind::Int32 | FieldKind::Enum => "cursor.read_i32()?".to_string(),
+        FieldKind::UInt32 => "cursor.read_u32()?".to_string(),
+        FieldKind::Int64 => "cursor.read_i64()?".to_string(),
+        FieldKind::UInt64 => "cursor.read_u64()?".to_string(),
+        FieldKind::Float => "cursor.read_f32()?".to_string(),
+        FieldKind::Double => "cursor.read_f64()?".to_string(),
+        FieldKind::Bool => "cursor.read_u8()? == 1".to_string(),
+        FieldKind::String => "cursor.read_str()?.to_string()".to_string(),
+        FieldKind::Bytes => "cursor.read_bytes()?.to_vec()".to_string(),
+        FieldKind::Message => {
+            let type_name = scalar_type(field);
+            if boxed {
+                format!("Box::new({type_name}::decode(cursor)?)")
+            } else {
+                format!("{type_name}::decode(cursor)?")
+            }
+        }
+        FieldKind::Map => unreachable!("map fields are decoded entry by entry"),
+    }
+}
@@ -0,0 +1,50 @@
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: netcode-codegen [net_schema.json] [output.rs]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() > 2 || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let schema_path = args
+        .first()
+        .map(String::as_str)
+        .unwrap_or("netcode/schema/gen/net_schema.json");
+
+    let raw_json = match std::fs::read_to_string(schema_path) {
+        Ok(raw_json) => raw_json,
+        Err(err) => {
+            eprintln!("Failed to read {schema_path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let schema = match netcode::validate_json(&raw_json) {
+        Ok(schema) => schema,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("Invalid {schema_path}: {err}");
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    let source = match netcode_codegen::generate(&schema) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.get(1) {
+        Some(output_path) => {
+            if let Err(err) = std::fs::write(output_path, source) {
+                eprintln!("Failed to write {output_path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{source}"),
+    }
+    ExitCode::SUCCESS
+}
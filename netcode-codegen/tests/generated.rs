@@ -0,0 +1,767 @@
+// @generated by netcode-codegen. Do not edit by hand.
+
+#[allow(unused_imports)]
+use netcode::wire::{write_f32, write_f64, Cursor, WireFormat, WireType};
+#[allow(unused_imports)]
+use netcode::{CodecError, CodecResult, NetMessage, Quantization};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Area {
+    pub name: Option<String>,
+    pub data: Option<Vec<u8>>,
+    pub objects: Option<Vec<ServerDebugObject>>,
+}
+
+impl NetMessage for Area {
+    const NAME: &'static str = "Area";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        let mut count = 0u16;
+        if self.name.is_some() {
+            count += 1;
+        }
+        if self.data.is_some() {
+            count += 1;
+        }
+        if self.objects.is_some() {
+            count += 1;
+        }
+        format.write_u16(buffer, count);
+        if let Some(value) = &self.name {
+            format.write_u16(buffer, 1);
+            format.write_bytes(buffer, value.as_bytes());
+        }
+        if let Some(value) = &self.data {
+            format.write_u16(buffer, 2);
+            format.write_bytes(buffer, value);
+        }
+        if let Some(value) = &self.objects {
+            format.write_u16(buffer, 3);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("objects", "at most 65535 items"))?;
+            format.write_u16(buffer, len);
+            for value in value {
+                value.encode(buffer, format)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        let field_count = cursor.read_u16()?;
+        let mut message = Self::default();
+        for _ in 0..field_count {
+            match cursor.read_u16()? {
+                1 => message.name = Some(cursor.read_str()?.to_string()),
+                2 => message.data = Some(cursor.read_bytes()?.to_vec()),
+                3 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        values.push(ServerDebugObject::decode(cursor)?);
+                    }
+                    message.objects = Some(values);
+                }
+                number => return Err(CodecError::UnknownFieldNumber(number)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Empty {}
+
+impl NetMessage for Empty {
+    const NAME: &'static str = "Empty";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        format.write_u16(buffer, 0);
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        if cursor.read_u16()? > 0 {
+            return Err(CodecError::UnknownFieldNumber(cursor.read_u16()?));
+        }
+        Ok(Self {})
+    }
+}
+
+const ENTITY_ANGLE_QUANTIZATION: Quantization = Quantization::FixedPoint {
+    scale: 100.0,
+    bits: 16,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityTarget {
+    TargetId(u32),
+    TargetArea(Box<Area>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entity {
+    pub id: Option<u32>,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub name: Option<String>,
+    pub dead: Option<bool>,
+    pub r#type: Option<i32>,
+    pub effects: Option<Vec<u32>>,
+    pub area: Option<Box<Area>>,
+    pub level: Option<i32>,
+    pub angle: Option<f32>,
+    pub stats: Option<Box<Stats>>,
+    pub owner: Option<u64>,
+    pub created_at: Option<i64>,
+    pub mass: Option<f64>,
+    pub target: Option<EntityTarget>,
+    pub scores: Option<Vec<(String, i32)>>,
+    pub loadout: Option<Vec<(u32, Stats)>>,
+}
+
+impl NetMessage for Entity {
+    const NAME: &'static str = "Entity";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        let mut count = 0u16;
+        if self.id.is_some() {
+            count += 1;
+        }
+        if self.x.is_some() {
+            count += 1;
+        }
+        if self.y.is_some() {
+            count += 1;
+        }
+        if self.name.is_some() {
+            count += 1;
+        }
+        if self.dead.is_some() {
+            count += 1;
+        }
+        if self.r#type.is_some() {
+            count += 1;
+        }
+        if self.effects.is_some() {
+            count += 1;
+        }
+        if self.area.is_some() {
+            count += 1;
+        }
+        if self.level.is_some() {
+            count += 1;
+        }
+        if self.angle.is_some() {
+            count += 1;
+        }
+        if self.stats.is_some() {
+            count += 1;
+        }
+        if self.owner.is_some() {
+            count += 1;
+        }
+        if self.created_at.is_some() {
+            count += 1;
+        }
+        if self.mass.is_some() {
+            count += 1;
+        }
+        if self.target.is_some() {
+            count += 1;
+        }
+        if self.scores.is_some() {
+            count += 1;
+        }
+        if self.loadout.is_some() {
+            count += 1;
+        }
+        format.write_u16(buffer, count);
+        if let Some(value) = &self.id {
+            format.write_u16(buffer, 1);
+            format.write_u32(buffer, *value);
+        }
+        if let Some(value) = &self.x {
+            format.write_u16(buffer, 2);
+            write_f32(buffer, *value);
+        }
+        if let Some(value) = &self.y {
+            format.write_u16(buffer, 3);
+            write_f32(buffer, *value);
+        }
+        if let Some(value) = &self.name {
+            format.write_u16(buffer, 4);
+            format.write_bytes(buffer, value.as_bytes());
+        }
+        if let Some(value) = &self.dead {
+            format.write_u16(buffer, 5);
+            buffer.push(*value as u8);
+        }
+        if let Some(value) = &self.r#type {
+            format.write_u16(buffer, 6);
+            format.write_i32(buffer, *value);
+        }
+        if let Some(value) = &self.effects {
+            format.write_u16(buffer, 7);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("effects", "at most 65535 items"))?;
+            format.write_u16(buffer, len);
+            for value in value {
+                format.write_u32(buffer, *value);
+            }
+        }
+        if let Some(value) = &self.area {
+            format.write_u16(buffer, 8);
+            value.encode(buffer, format)?;
+        }
+        if let Some(value) = &self.level {
+            format.write_u16(buffer, 9);
+            format.write_i32(buffer, *value);
+        }
+        if let Some(value) = &self.angle {
+            format.write_u16(buffer, 10);
+            ENTITY_ANGLE_QUANTIZATION.write(buffer, *value);
+        }
+        if let Some(value) = &self.stats {
+            format.write_u16(buffer, 11);
+            value.encode(buffer, format)?;
+        }
+        if let Some(value) = &self.owner {
+            format.write_u16(buffer, 12);
+            format.write_u64(buffer, *value);
+        }
+        if let Some(value) = &self.created_at {
+            format.write_u16(buffer, 13);
+            format.write_i64(buffer, *value);
+        }
+        if let Some(value) = &self.mass {
+            format.write_u16(buffer, 14);
+            write_f64(buffer, *value);
+        }
+        if let Some(EntityTarget::TargetId(value)) = &self.target {
+            format.write_u16(buffer, 15);
+            format.write_u32(buffer, *value);
+        }
+        if let Some(EntityTarget::TargetArea(value)) = &self.target {
+            format.write_u16(buffer, 16);
+            value.encode(buffer, format)?;
+        }
+        if let Some(value) = &self.scores {
+            format.write_u16(buffer, 17);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("scores", "at most 65535 map entries"))?;
+            format.write_u16(buffer, len);
+            for (key, value) in value {
+                format.write_bytes(buffer, key.as_bytes());
+                format.write_i32(buffer, *value);
+            }
+        }
+        if let Some(value) = &self.loadout {
+            format.write_u16(buffer, 18);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("loadout", "at most 65535 map entries"))?;
+            format.write_u16(buffer, len);
+            for (key, value) in value {
+                format.write_u32(buffer, *key);
+                value.encode(buffer, format)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        let field_count = cursor.read_u16()?;
+        let mut message = Self::default();
+        for _ in 0..field_count {
+            match cursor.read_u16()? {
+                1 => message.id = Some(cursor.read_u32()?),
+                2 => message.x = Some(cursor.read_f32()?),
+                3 => message.y = Some(cursor.read_f32()?),
+                4 => message.name = Some(cursor.read_str()?.to_string()),
+                5 => message.dead = Some(cursor.read_u8()? == 1),
+                6 => message.r#type = Some(cursor.read_i32()?),
+                7 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        values.push(cursor.read_u32()?);
+                    }
+                    message.effects = Some(values);
+                }
+                8 => message.area = Some(Box::new(Area::decode(cursor)?)),
+                9 => message.level = Some(cursor.read_i32()?),
+                10 => message.angle = Some(ENTITY_ANGLE_QUANTIZATION.read(cursor)?),
+                11 => message.stats = Some(Box::new(Stats::decode(cursor)?)),
+                12 => message.owner = Some(cursor.read_u64()?),
+                13 => message.created_at = Some(cursor.read_i64()?),
+                14 => message.mass = Some(cursor.read_f64()?),
+                15 => {
+                    if !matches!(message.target, None | Some(EntityTarget::TargetId(_))) {
+                        return Err(CodecError::OneofConflict("target".to_string()));
+                    }
+                    let value = cursor.read_u32()?;
+                    message.target = Some(EntityTarget::TargetId(value));
+                }
+                16 => {
+                    if !matches!(message.target, None | Some(EntityTarget::TargetArea(_))) {
+                        return Err(CodecError::OneofConflict("target".to_string()));
+                    }
+                    let value = Box::new(Area::decode(cursor)?);
+                    message.target = Some(EntityTarget::TargetArea(value));
+                }
+                17 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let key = cursor.read_str()?.to_string();
+                        values.push((key, cursor.read_i32()?));
+                    }
+                    message.scores = Some(values);
+                }
+                18 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let key = cursor.read_u32()?;
+                        values.push((key, Stats::decode(cursor)?));
+                    }
+                    message.loadout = Some(values);
+                }
+                number => return Err(CodecError::UnknownFieldNumber(number)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+const EVENT_ANGLE_QUANTIZATION: Quantization = Quantization::FixedPoint {
+    scale: 100.0,
+    bits: 16,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventChoice {
+    Pick(u32),
+    PickName(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    pub id: Option<u32>,
+    pub at: Option<i64>,
+    pub label: Option<String>,
+    pub area: Option<Box<Area>>,
+    pub tags: Option<Vec<u32>>,
+    pub angle: Option<f32>,
+    pub counts: Option<Vec<(String, u32)>>,
+    pub ok: Option<bool>,
+    pub mass: Option<f64>,
+    pub choice: Option<EventChoice>,
+}
+
+impl NetMessage for Event {
+    const NAME: &'static str = "Event";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        let mut count = 0u16;
+        if self.id.is_some() {
+            count += 1;
+        }
+        if self.at.is_some() {
+            count += 1;
+        }
+        if self.label.is_some() {
+            count += 1;
+        }
+        if self.area.is_some() {
+            count += 1;
+        }
+        if self.tags.is_some() {
+            count += 1;
+        }
+        if self.angle.is_some() {
+            count += 1;
+        }
+        if self.counts.is_some() {
+            count += 1;
+        }
+        if self.ok.is_some() {
+            count += 1;
+        }
+        if self.mass.is_some() {
+            count += 1;
+        }
+        if self.choice.is_some() {
+            count += 1;
+        }
+        format.write_u16(buffer, count);
+        if let Some(value) = &self.id {
+            format.write_key(buffer, 1, format.int32_wire_type());
+            format.write_u32(buffer, *value);
+        }
+        if let Some(value) = &self.at {
+            format.write_key(buffer, 2, format.int64_wire_type());
+            format.write_i64(buffer, *value);
+        }
+        if let Some(value) = &self.label {
+            format.write_key(buffer, 3, WireType::Len);
+            format.write_bytes(buffer, value.as_bytes());
+        }
+        if let Some(value) = &self.area {
+            format.write_key(buffer, 4, WireType::Len);
+            let mut nested = Vec::new();
+            {
+                let buffer = &mut nested;
+                value.encode(buffer, format)?;
+            }
+            format.write_bytes(buffer, &nested);
+        }
+        if let Some(value) = &self.tags {
+            format.write_key(buffer, 5, WireType::Len);
+            let mut nested = Vec::new();
+            {
+                let buffer = &mut nested;
+                let len = u16::try_from(value.len())
+                    .map_err(|_| CodecError::invalid_value("tags", "at most 65535 items"))?;
+                format.write_u16(buffer, len);
+                for value in value {
+                    format.write_u32(buffer, *value);
+                }
+            }
+            format.write_bytes(buffer, &nested);
+        }
+        if let Some(value) = &self.angle {
+            format.write_key(buffer, 6, WireType::Len);
+            let mut nested = Vec::new();
+            {
+                let buffer = &mut nested;
+                EVENT_ANGLE_QUANTIZATION.write(buffer, *value);
+            }
+            format.write_bytes(buffer, &nested);
+        }
+        if let Some(value) = &self.counts {
+            format.write_key(buffer, 7, WireType::Len);
+            let mut nested = Vec::new();
+            {
+                let buffer = &mut nested;
+                let len = u16::try_from(value.len()).map_err(|_| {
+                    CodecError::invalid_value("counts", "at most 65535 map entries")
+                })?;
+                format.write_u16(buffer, len);
+                for (key, value) in value {
+                    format.write_bytes(buffer, key.as_bytes());
+                    format.write_u32(buffer, *value);
+                }
+            }
+            format.write_bytes(buffer, &nested);
+        }
+        if let Some(value) = &self.ok {
+            format.write_key(buffer, 8, WireType::Varint);
+            buffer.push(*value as u8);
+        }
+        if let Some(value) = &self.mass {
+            format.write_key(buffer, 9, WireType::Fixed64);
+            write_f64(buffer, *value);
+        }
+        if let Some(EventChoice::Pick(value)) = &self.choice {
+            format.write_key(buffer, 10, format.int32_wire_type());
+            format.write_u32(buffer, *value);
+        }
+        if let Some(EventChoice::PickName(value)) = &self.choice {
+            format.write_key(buffer, 11, WireType::Len);
+            format.write_bytes(buffer, value.as_bytes());
+        }
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        let format = cursor.format();
+        let field_count = cursor.read_u16()?;
+        let mut message = Self::default();
+        for _ in 0..field_count {
+            match cursor.read_key()? {
+                (1, wire_type) => {
+                    if wire_type != format.int32_wire_type() {
+                        return Err(CodecError::WireTypeMismatch("id".to_string()));
+                    }
+                    message.id = Some(cursor.read_u32()?);
+                }
+                (2, wire_type) => {
+                    if wire_type != format.int64_wire_type() {
+                        return Err(CodecError::WireTypeMismatch("at".to_string()));
+                    }
+                    message.at = Some(cursor.read_i64()?);
+                }
+                (3, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("label".to_string()));
+                    }
+                    message.label = Some(cursor.read_str()?.to_string());
+                }
+                (4, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("area".to_string()));
+                    }
+                    let cursor = &mut Cursor::with_format(cursor.read_bytes()?, format);
+                    message.area = Some(Box::new(Area::decode(cursor)?));
+                    if !cursor.is_empty() {
+                        return Err(CodecError::TrailingBytes(cursor.remaining()));
+                    }
+                }
+                (5, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("tags".to_string()));
+                    }
+                    let cursor = &mut Cursor::with_format(cursor.read_bytes()?, format);
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        values.push(cursor.read_u32()?);
+                    }
+                    message.tags = Some(values);
+                    if !cursor.is_empty() {
+                        return Err(CodecError::TrailingBytes(cursor.remaining()));
+                    }
+                }
+                (6, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("angle".to_string()));
+                    }
+                    let cursor = &mut Cursor::with_format(cursor.read_bytes()?, format);
+                    message.angle = Some(EVENT_ANGLE_QUANTIZATION.read(cursor)?);
+                    if !cursor.is_empty() {
+                        return Err(CodecError::TrailingBytes(cursor.remaining()));
+                    }
+                }
+                (7, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("counts".to_string()));
+                    }
+                    let cursor = &mut Cursor::with_format(cursor.read_bytes()?, format);
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let key = cursor.read_str()?.to_string();
+                        values.push((key, cursor.read_u32()?));
+                    }
+                    message.counts = Some(values);
+                    if !cursor.is_empty() {
+                        return Err(CodecError::TrailingBytes(cursor.remaining()));
+                    }
+                }
+                (8, wire_type) => {
+                    if wire_type != WireType::Varint {
+                        return Err(CodecError::WireTypeMismatch("ok".to_string()));
+                    }
+                    message.ok = Some(cursor.read_u8()? == 1);
+                }
+                (9, wire_type) => {
+                    if wire_type != WireType::Fixed64 {
+                        return Err(CodecError::WireTypeMismatch("mass".to_string()));
+                    }
+                    message.mass = Some(cursor.read_f64()?);
+                }
+                (10, wire_type) => {
+                    if wire_type != format.int32_wire_type() {
+                        return Err(CodecError::WireTypeMismatch("pick".to_string()));
+                    }
+                    if !matches!(message.choice, None | Some(EventChoice::Pick(_))) {
+                        return Err(CodecError::OneofConflict("choice".to_string()));
+                    }
+                    let value = cursor.read_u32()?;
+                    message.choice = Some(EventChoice::Pick(value));
+                }
+                (11, wire_type) => {
+                    if wire_type != WireType::Len {
+                        return Err(CodecError::WireTypeMismatch("pick_name".to_string()));
+                    }
+                    if !matches!(message.choice, None | Some(EventChoice::PickName(_))) {
+                        return Err(CodecError::OneofConflict("choice".to_string()));
+                    }
+                    let value = cursor.read_str()?.to_string();
+                    message.choice = Some(EventChoice::PickName(value));
+                }
+                (_, wire_type) => cursor.skip(wire_type)?,
+            }
+        }
+        Ok(message)
+    }
+}
+
+const SERVER_DEBUG_OBJECT_WEIGHTS_QUANTIZATION: Quantization = Quantization::Range {
+    min: 0.0,
+    max: 1.0,
+    bits: 8,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerDebugObject {
+    pub kind: Option<i32>,
+    pub points: Option<Vec<f32>>,
+    pub weights: Option<Vec<f32>>,
+}
+
+impl NetMessage for ServerDebugObject {
+    const NAME: &'static str = "ServerDebugObject";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        let mut count = 0u16;
+        if self.kind.is_some() {
+            count += 1;
+        }
+        if self.points.is_some() {
+            count += 1;
+        }
+        if self.weights.is_some() {
+            count += 1;
+        }
+        format.write_u16(buffer, count);
+        if let Some(value) = &self.kind {
+            format.write_u16(buffer, 1);
+            format.write_i32(buffer, *value);
+        }
+        if let Some(value) = &self.points {
+            format.write_u16(buffer, 2);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("points", "at most 65535 items"))?;
+            format.write_u16(buffer, len);
+            for value in value {
+                write_f32(buffer, *value);
+            }
+        }
+        if let Some(value) = &self.weights {
+            format.write_u16(buffer, 3);
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("weights", "at most 65535 items"))?;
+            format.write_u16(buffer, len);
+            for value in value {
+                SERVER_DEBUG_OBJECT_WEIGHTS_QUANTIZATION.write(buffer, *value);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        let field_count = cursor.read_u16()?;
+        let mut message = Self::default();
+        for _ in 0..field_count {
+            match cursor.read_u16()? {
+                1 => message.kind = Some(cursor.read_i32()?),
+                2 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        values.push(cursor.read_f32()?);
+                    }
+                    message.points = Some(values);
+                }
+                3 => {
+                    let count = cursor.read_u16()? as usize;
+                    let mut values = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        values.push(SERVER_DEBUG_OBJECT_WEIGHTS_QUANTIZATION.read(cursor)?);
+                    }
+                    message.weights = Some(values);
+                }
+                number => return Err(CodecError::UnknownFieldNumber(number)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsStatus {
+    Alive(bool),
+    RespawnIn(f32),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub name: Option<String>,
+    pub tags: Option<Vec<u32>>,
+    pub hp: Option<f32>,
+    pub status: Option<StatsStatus>,
+}
+
+impl NetMessage for Stats {
+    const NAME: &'static str = "Stats";
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()> {
+        let mut presence = [0u8; 1];
+        if self.hp.is_some() {
+            presence[0] |= 0x01;
+        }
+        if self.name.is_some() {
+            presence[0] |= 0x02;
+        }
+        if self.tags.is_some() {
+            presence[0] |= 0x04;
+        }
+        if matches!(self.status, Some(StatsStatus::Alive(_))) {
+            presence[0] |= 0x08;
+        }
+        if matches!(self.status, Some(StatsStatus::RespawnIn(_))) {
+            presence[0] |= 0x10;
+        }
+        buffer.extend_from_slice(&presence);
+        if let Some(value) = &self.hp {
+            write_f32(buffer, *value);
+        }
+        if let Some(value) = &self.name {
+            format.write_bytes(buffer, value.as_bytes());
+        }
+        if let Some(value) = &self.tags {
+            let len = u16::try_from(value.len())
+                .map_err(|_| CodecError::invalid_value("tags", "at most 65535 items"))?;
+            format.write_u16(buffer, len);
+            for value in value {
+                format.write_u32(buffer, *value);
+            }
+        }
+        if let Some(StatsStatus::Alive(value)) = &self.status {
+            buffer.push(*value as u8);
+        }
+        if let Some(StatsStatus::RespawnIn(value)) = &self.status {
+            write_f32(buffer, *value);
+        }
+        Ok(())
+    }
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self> {
+        let presence = cursor.read_slice(1)?;
+        if presence[0] & 0xe0 != 0 {
+            return Err(CodecError::InvalidPresenceBitmap);
+        }
+        let mut message = Self::default();
+        if presence[0] & 0x01 != 0 {
+            message.hp = Some(cursor.read_f32()?);
+        }
+        if presence[0] & 0x02 != 0 {
+            message.name = Some(cursor.read_str()?.to_string());
+        }
+        if presence[0] & 0x04 != 0 {
+            let count = cursor.read_u16()? as usize;
+            let mut values = Vec::with_capacity(count);
+            for _ in 0..count {
+                values.push(cursor.read_u32()?);
+            }
+            message.tags = Some(values);
+        }
+        if presence[0] & 0x08 != 0 {
+            if !matches!(message.status, None | Some(StatsStatus::Alive(_))) {
+                return Err(CodecError::OneofConflict("status".to_string()));
+            }
+            let value = cursor.read_u8()? == 1;
+            message.status = Some(StatsStatus::Alive(value));
+        }
+        if presence[0] & 0x10 != 0 {
+            if !matches!(message.status, None | Some(StatsStatus::RespawnIn(_))) {
+                return Err(CodecError::OneofConflict("status".to_string()));
+            }
+            let value = cursor.read_f32()?;
+            message.status = Some(StatsStatus::RespawnIn(value));
+        }
+        Ok(message)
+    }
+}
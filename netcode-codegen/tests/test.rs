@@ -0,0 +1,286 @@
+use netcode::wire::Cursor;
+use netcode::{Codec, CodecError, Message, NetMessage, NetSchema, Value, WireFormat};
+
+mod generated {
+    include!("generated.rs");
+}
+
+use generated::{
+    Area, Empty, Entity, EntityTarget, Event, EventChoice, ServerDebugObject, Stats, StatsStatus,
+};
+
+const SCHEMA_JSON: &str = include_str!("net_schema.json");
+
+fn schema() -> NetSchema {
+    NetSchema::from_json(SCHEMA_JSON).expect("valid schema")
+}
+
+fn typed_entity() -> Entity {
+    Entity {
+        id: Some(7),
+        x: Some(1.5),
+        name: Some("Jolt".to_string()),
+        dead: Some(true),
+        r#type: Some(3),
+        effects: Some(vec![4, 5]),
+        area: Some(Box::new(Area {
+            name: Some("Central Core".to_string()),
+            data: Some(vec![1, 2, 3]),
+            objects: Some(vec![ServerDebugObject {
+                kind: Some(1),
+                points: Some(vec![0.5, -0.5]),
+                weights: Some(vec![0.0, 1.0]),
+            }]),
+        })),
+        level: Some(-2),
+        angle: Some(-1.25),
+        stats: Some(Box::new(Stats {
+            hp: Some(12.5),
+            tags: Some(vec![1, 300]),
+            status: Some(StatsStatus::Alive(true)),
+            ..Default::default()
+        })),
+        owner: Some(u64::MAX),
+        created_at: Some(-1_700_000_000_123),
+        mass: Some(0.1),
+        target: Some(EntityTarget::TargetId(9)),
+        scores: Some(vec![("alice".to_string(), -3), ("bob".to_string(), 12)]),
+        loadout: Some(vec![(
+            2,
+            Stats {
+                status: Some(StatsStatus::RespawnIn(1.5)),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    }
+}
+
+fn dynamic_entity() -> Message {
+    let debug_object = Message::new()
+        .with("kind", 1)
+        .with("points", vec![Value::Float(0.5), Value::Float(-0.5)])
+        .with("weights", vec![Value::Float(0.0), Value::Float(1.0)]);
+    Message::new()
+        .with("id", 7u32)
+        .with("x", 1.5f32)
+        .with("name", "Jolt")
+        .with("dead", true)
+        .with("type", 3)
+        .with("effects", vec![Value::UInt32(4), Value::UInt32(5)])
+        .with(
+            "area",
+            Message::new()
+                .with("name", "Central Core")
+                .with("data", vec![1u8, 2, 3])
+                .with("objects", vec![Value::Message(debug_object)]),
+        )
+        .with("level", -2)
+        .with("angle", -1.25f32)
+        .with(
+            "stats",
+            Message::new()
+                .with("hp", 12.5f32)
+                .with("tags", vec![Value::UInt32(1), Value::UInt32(300)])
+                .with("alive", true),
+        )
+        .with("owner", u64::MAX)
+        .with("created_at", -1_700_000_000_123i64)
+        .with("mass", 0.1f64)
+        .with("target_id", 9u32)
+        .with(
+            "scores",
+            vec![
+                (Value::from("alice"), Value::Int32(-3)),
+                (Value::from("bob"), Value::Int32(12)),
+            ],
+        )
+        .with(
+            "loadout",
+            vec![(
+                Value::UInt32(2),
+                Value::Message(Message::new().with("respawn_in", 1.5f32)),
+            )],
+        )
+}
+
+fn typed_event() -> Event {
+    Event {
+        id: Some(300),
+        at: Some(-5),
+        label: Some("spawn".to_string()),
+        area: Some(Box::new(Area {
+            name: Some("Arena".to_string()),
+            ..Default::default()
+        })),
+        tags: Some(vec![1, 2]),
+        angle: Some(0.5),
+        counts: Some(vec![("kills".to_string(), 3)]),
+        ok: Some(true),
+        mass: Some(2.5),
+        choice: Some(EventChoice::PickName("left".to_string())),
+    }
+}
+
+fn dynamic_event() -> Message {
+    Message::new()
+        .with("id", 300u32)
+        .with("at", -5i64)
+        .with("label", "spawn")
+        .with("area", Message::new().with("name", "Arena"))
+        .with("tags", vec![Value::UInt32(1), Value::UInt32(2)])
+        .with("angle", 0.5f32)
+        .with("counts", vec![(Value::from("kills"), Value::UInt32(3))])
+        .with("ok", true)
+        .with("mass", 2.5f64)
+        .with("pick_name", "left")
+}
+
+#[test]
+fn test_generated_source_is_up_to_date() {
+    let source = netcode_codegen::generate(&schema()).unwrap();
+    assert_eq!(source, include_str!("generated.rs"));
+}
+
+#[test]
+fn test_generated_encode_matches_codec() {
+    let schema = schema();
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let codec = Codec::with_format(&schema, format);
+        let mut expected = Vec::new();
+        codec
+            .encode_message(Entity::NAME, &dynamic_entity(), &mut expected)
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        typed_entity().encode(&mut buffer, format).unwrap();
+        assert_eq!(buffer, expected);
+    }
+}
+
+#[test]
+fn test_generated_encode_rejects_oversized_lists_and_maps() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let len = u16::MAX as usize + 1;
+    let cases = [
+        (
+            Entity {
+                effects: Some(vec![0; len]),
+                ..Entity::default()
+            },
+            Message::new().with("effects", vec![Value::UInt32(0); len]),
+            CodecError::invalid_value("effects", "at most 65535 items"),
+        ),
+        (
+            Entity {
+                scores: Some(vec![(String::new(), 0); len]),
+                ..Entity::default()
+            },
+            Message::new().with("scores", vec![(Value::from(""), Value::Int32(0)); len]),
+            CodecError::invalid_value("scores", "at most 65535 map entries"),
+        ),
+    ];
+    for (typed, dynamic, err) in cases {
+        assert_eq!(
+            typed.encode(&mut Vec::new(), WireFormat::Fixed),
+            Err(err.clone())
+        );
+        assert_eq!(
+            codec.encode_message(Entity::NAME, &dynamic, &mut Vec::new()),
+            Err(err)
+        );
+    }
+}
+
+#[test]
+fn test_generated_decode_round_trip() {
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let mut buffer = Vec::new();
+        typed_entity().encode(&mut buffer, format).unwrap();
+        let decoded = Entity::decode(&mut Cursor::with_format(&buffer, format)).unwrap();
+        assert_eq!(decoded, typed_entity());
+    }
+
+    let mut buffer = Vec::new();
+    Empty {}.encode(&mut buffer, WireFormat::Fixed).unwrap();
+    assert_eq!(buffer, vec![0, 0]);
+    assert_eq!(Empty::decode(&mut Cursor::new(&buffer)).unwrap(), Empty {});
+}
+
+#[test]
+fn test_generated_decode_rejects_oneof_conflicts() {
+    // Both members of Stats.status are marked present.
+    let mut buffer = vec![0x18, 1];
+    buffer.extend_from_slice(&1.5f32.to_le_bytes());
+    assert_eq!(
+        Stats::decode(&mut Cursor::new(&buffer)),
+        Err(CodecError::OneofConflict("status".to_string()))
+    );
+    assert_eq!(
+        Codec::new(&schema()).decode_message(Stats::NAME, &buffer),
+        Err(CodecError::OneofConflict("status".to_string()))
+    );
+}
+
+#[test]
+fn test_unknown_message_type_is_rejected() {
+    let mut schema = NetSchema::from_json(
+        r#"{"messages": {
+            "A": {"name": "A", "fields": [
+                {"name": "b", "number": 1, "type": "message", "label": "optional", "type_name": "B"}
+            ]},
+            "B": {"name": "B", "fields": []}
+        }}"#,
+    )
+    .unwrap();
+    schema.messages.remove("B");
+    assert!(netcode_codegen::generate(&schema).is_err());
+}
+
+#[test]
+fn test_generated_typed_layout_matches_codec() {
+    let schema = schema();
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let codec = Codec::with_format(&schema, format);
+        let mut expected = Vec::new();
+        codec
+            .encode_message(Event::NAME, &dynamic_event(), &mut expected)
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        typed_event().encode(&mut buffer, format).unwrap();
+        assert_eq!(buffer, expected);
+        let decoded = Event::decode(&mut Cursor::with_format(&buffer, format)).unwrap();
+        assert_eq!(decoded, typed_event());
+    }
+}
+
+#[test]
+fn test_generated_typed_decode_skips_unknown_fields() {
+    // A newer schema with fields the generated code does not know.
+    let newer = NetSchema::from_json(&SCHEMA_JSON.replace(
+        r#"{"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "at""#,
+        r#"{"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "note", "number": 20, "type": "string", "label": "optional"},
+                {"name": "origin", "number": 21, "type": "message", "label": "optional", "type_name": "Area"},
+                {"name": "seed", "number": 22, "type": "sint64", "label": "optional"},
+                {"name": "scale", "number": 23, "type": "float", "label": "optional"},
+                {"name": "at""#,
+    ))
+    .unwrap();
+    let message = dynamic_event()
+        .with("note", "hello")
+        .with("origin", Message::new().with("data", vec![1u8, 2]))
+        .with("seed", -9i64)
+        .with("scale", 2.0f32);
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let mut buffer = Vec::new();
+        Codec::with_format(&newer, format)
+            .encode_message(Event::NAME, &message, &mut buffer)
+            .unwrap();
+        let decoded = Event::decode(&mut Cursor::with_format(&buffer, format)).unwrap();
+        assert_eq!(decoded, typed_event());
+    }
+}
@@ -0,0 +1,34 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Copies the schema into OUT_DIR so lib.rs can embed it with include_str!
+// whether or not it has been generated. NETCODE_SCHEMA_PATH overrides the
+// default location.
+fn main() {
+    println!("cargo:rerun-if-env-changed=NETCODE_SCHEMA_PATH");
+    let override_path = env::var_os("NETCODE_SCHEMA_PATH").map(PathBuf::from);
+    let schema_path = override_path.clone().unwrap_or_else(|| {
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("schema/gen/net_schema.json")
+    });
+    println!("cargo:rerun-if-changed={}", schema_path.display());
+
+    let raw_json = match fs::read_to_string(&schema_path) {
+        Ok(raw_json) => raw_json,
+        Err(err) if override_path.is_some() => {
+            panic!(
+                "Failed to read NETCODE_SCHEMA_PATH {}: {err}",
+                schema_path.display()
+            )
+        }
+        Err(err) => {
+            println!(
+                "cargo:warning=No net schema embedded, failed to read {}: {err}",
+                schema_path.display()
+            );
+            String::new()
+        }
+    };
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("net_schema.json");
+    fs::write(out_path, raw_json).expect("Failed to write embedded net schema");
+}
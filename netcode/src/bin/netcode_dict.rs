@@ -0,0 +1,74 @@
+use std::process::ExitCode;
+
+use netcode::{read_capture, train_dictionary, CompressionConfig, FrameCompressor};
+
+const USAGE: &str = "usage: netcode-dict <capture> <dictionary> [max_size]";
+const DEFAULT_MAX_SIZE: usize = 16 * 1024;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !(2..=3).contains(&args.len()) || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let (capture_path, dictionary_path) = (&args[0], &args[1]);
+    let max_size = match args.get(2).map(|arg| arg.parse::<usize>()) {
+        None => DEFAULT_MAX_SIZE,
+        Some(Ok(max_size)) => max_size,
+        Some(Err(err)) => {
+            eprintln!("Invalid max_size {}: {err}", args[2]);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let capture = match std::fs::read(capture_path) {
+        Ok(capture) => capture,
+        Err(err) => {
+            eprintln!("Failed to read {capture_path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let frames = match read_capture(&capture) {
+        Ok(frames) => frames,
+        Err(err) => {
+            eprintln!("Invalid capture {capture_path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let dictionary = match train_dictionary(&frames, max_size) {
+        Ok(dictionary) => dictionary,
+        Err(err) => {
+            eprintln!("Failed to train on {} frames: {err}", frames.len());
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = std::fs::write(dictionary_path, &dictionary) {
+        eprintln!("Failed to write {dictionary_path}: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    // Report how the capture itself compresses with the new dictionary.
+    let mut compressor =
+        match FrameCompressor::with_dictionary(&dictionary, CompressionConfig::default()) {
+            Ok(compressor) => compressor,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        };
+    let mut compressed = Vec::new();
+    for frame in &frames {
+        if let Err(err) = compressor.compress(frame, &mut compressed) {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    let raw_len: usize = frames.iter().map(|frame| frame.len()).sum();
+    eprintln!(
+        "Trained a {}-byte dictionary on {} frames: {raw_len} bytes compress to {}",
+        dictionary.len(),
+        frames.len(),
+        compressed.len()
+    );
+    ExitCode::SUCCESS
+}
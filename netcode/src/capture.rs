@@ -0,0 +1,19 @@
+use crate::error::CodecResult;
+use crate::wire::{write_bytes, Cursor};
+
+/// Appends one frame to a capture: the frame's length as a little-endian
+/// `u32` followed by its bytes. Captures are what `netcode-dict` trains
+/// compression dictionaries from, so they should hold uncompressed frames.
+pub fn write_capture_frame(buffer: &mut Vec<u8>, frame: &[u8]) {
+    write_bytes(buffer, frame);
+}
+
+/// Splits a capture written by `write_capture_frame` into its frames.
+pub fn read_capture(bytes: &[u8]) -> CodecResult<Vec<&[u8]>> {
+    let mut cursor = Cursor::new(bytes);
+    let mut frames = Vec::new();
+    while !cursor.is_empty() {
+        frames.push(cursor.read_bytes()?);
+    }
+    Ok(frames)
+}
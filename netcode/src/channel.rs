@@ -0,0 +1,484 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::codec::Codec;
+use crate::error::{CodecError, CodecResult};
+use crate::value::Message;
+use crate::wire::{write_bytes, write_u16, write_u32, Cursor};
+use crate::{FrameSection, SectionKind};
+
+/// Bytes at the start of every packet: the packet sequence, then the
+/// newest received sequence plus one as `u16`s, then the ack bitfield as a
+/// `u32`, then the number of reliable messages that follow as a `u16`, all
+/// little-endian. Bit `n` of the bitfield acks sequence `ack - 1 - n`.
+pub const CHANNEL_HEADER_LEN: usize = 10;
+
+/// How many sequences the ack bitfield covers.
+const ACK_BITS: u16 = 32;
+/// Reliable messages in flight at once, counted from the oldest unacked
+/// one. Later messages wait to be sent, so the receiver never buffers more
+/// than this many out of order.
+const RELIABLE_WINDOW: u16 = 1024;
+/// Sent packets kept waiting for an ack; older ones count as lost.
+const SENT_WINDOW: usize = 256;
+/// Weight of each new sample in the smoothed RTT and packet loss.
+const SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelConfig {
+    /// Frame sections `send_frame` moves to the reliable stream.
+    pub reliable_sections: Vec<String>,
+    /// Round-trip time assumed until the first ack, in seconds.
+    pub initial_rtt: f64,
+    /// Unacked reliable messages are sent again after one and a half round
+    /// trips, but never sooner than this many seconds.
+    pub min_resend: f64,
+    /// Reliable message bytes added to one packet; a larger message is sent
+    /// on its own.
+    pub max_reliable_bytes: usize,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            reliable_sections: vec!["chat".to_string(), "mod_tools_response".to_string()],
+            initial_rtt: 0.2,
+            min_resend: 0.05,
+            max_reliable_bytes: 1024,
+        }
+    }
+}
+
+/// What `Channel::receive` read from a packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Received<'b> {
+    pub payload: &'b [u8],
+    /// The packet was received before, or is too old to ack; its payload
+    /// should be ignored.
+    pub stale: bool,
+    /// Reliable messages that can now be delivered, as `(kind, bytes)` in
+    /// the order they were sent.
+    pub reliable: Vec<(u16, Vec<u8>)>,
+}
+
+/// What `Channel::receive_frame` read from a packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedFrame {
+    /// The decoded frame, or None for stale packets and frames that failed
+    /// to decode.
+    pub frame: Option<Message>,
+    /// Reliable sections that can now be delivered, in the order they were
+    /// sent.
+    pub reliable: Vec<(String, Message)>,
+    /// Why the frame or a reliable section failed to decode. Either is
+    /// dropped, but the packet is still acked, so sections are not resent.
+    pub errors: Vec<CodecError>,
+}
+
+#[derive(Debug)]
+struct SentPacket {
+    sequence: u16,
+    sent_at: f64,
+    messages: Vec<u16>,
+    acked: bool,
+}
+
+/// A packet read by `read_packet`, not yet applied to the channel.
+struct Packet<'b> {
+    sequence: u16,
+    ack: u16,
+    ack_bits: u32,
+    messages: Vec<(u16, u16, &'b [u8])>,
+    payload: &'b [u8],
+}
+
+/// A reliable message received out of order, with the version byte of the
+/// frame it arrived with, if any.
+#[derive(Debug)]
+struct UndeliveredMessage {
+    kind: u16,
+    bytes: Vec<u8>,
+    version: Option<u8>,
+}
+
+#[derive(Debug)]
+struct PendingMessage {
+    id: u16,
+    kind: u16,
+    bytes: Vec<u8>,
+    sent_at: Option<f64>,
+}
+
+/// One end of a connection over an unreliable transport such as UDP or a
+/// WebRTC data channel. Both ends use a `Channel`.
+///
+/// Every packet carries a sequence number and acks the newest packet
+/// received from the other end and the 31 before it. Acks give the
+/// round-trip time and packet loss, and retire reliable messages: those are
+/// resent until acked and delivered exactly once, in order, however packets
+/// are lost, duplicated or reordered. Everything else in a packet is
+/// delivered at most once.
+///
+/// Times are in seconds, on any clock that only moves forward.
+#[derive(Debug, Default)]
+pub struct Channel {
+    config: ChannelConfig,
+    local_sequence: u16,
+    remote_sequence: Option<u16>,
+    /// Bit `n` is set when `remote_sequence - n` was received.
+    received_bits: u32,
+    sent: VecDeque<SentPacket>,
+    rtt: Option<f64>,
+    packet_loss: f64,
+    next_message_id: u16,
+    unacked: VecDeque<PendingMessage>,
+    next_delivery: u16,
+    undelivered: HashMap<u16, UndeliveredMessage>,
+}
+
+impl Channel {
+    pub fn new(config: ChannelConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &ChannelConfig {
+        &self.config
+    }
+
+    /// The smoothed round-trip time, once a packet has been acked.
+    pub fn rtt(&self) -> Option<f64> {
+        self.rtt
+    }
+
+    /// The smoothed fraction of sent packets that were never acked.
+    pub fn packet_loss(&self) -> f64 {
+        self.packet_loss
+    }
+
+    /// The number of reliable messages not acked yet.
+    pub fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// The sequence of the next packet sent.
+    pub fn local_sequence(&self) -> u16 {
+        self.local_sequence
+    }
+
+    /// The newest sequence received from the other end.
+    pub fn remote_sequence(&self) -> Option<u16> {
+        self.remote_sequence
+    }
+
+    /// Queues `bytes` to be delivered reliably and in order, tagged with
+    /// `kind`. Returns the message id.
+    pub fn send_reliable(&mut self, kind: u16, bytes: Vec<u8>) -> u16 {
+        let id = self.next_message_id;
+        self.next_message_id = id.wrapping_add(1);
+        self.unacked.push_back(PendingMessage {
+            id,
+            kind,
+            bytes,
+            sent_at: None,
+        });
+        id
+    }
+
+    /// Appends a packet carrying `payload` and the reliable messages due to
+    /// be sent to `buffer`, returning its sequence.
+    pub fn send(&mut self, payload: &[u8], now: f64, buffer: &mut Vec<u8>) -> u16 {
+        let sequence = self.local_sequence;
+        self.local_sequence = sequence.wrapping_add(1);
+        let ack = self
+            .remote_sequence
+            .map_or(0, |remote| remote.wrapping_add(1));
+        write_u16(buffer, sequence);
+        write_u16(buffer, ack);
+        write_u32(buffer, self.received_bits);
+
+        let resend_after =
+            (self.rtt.unwrap_or(self.config.initial_rtt) * 1.5).max(self.config.min_resend);
+        let count_at = buffer.len();
+        write_u16(buffer, 0);
+        let mut messages = Vec::new();
+        let mut reliable_bytes = 0;
+        let oldest = self.unacked.front().map_or(0, |message| message.id);
+        for message in &mut self.unacked {
+            if message.id.wrapping_sub(oldest) >= RELIABLE_WINDOW {
+                break;
+            }
+            if message
+                .sent_at
+                .is_some_and(|sent_at| now - sent_at < resend_after)
+            {
+                continue;
+            }
+            reliable_bytes += message.bytes.len();
+            if (!messages.is_empty() && reliable_bytes > self.config.max_reliable_bytes)
+                || messages.len() == u16::MAX as usize
+            {
+                break;
+            }
+            write_u16(buffer, message.id);
+            write_u16(buffer, message.kind);
+            write_bytes(buffer, &message.bytes);
+            message.sent_at = Some(now);
+            messages.push(message.id);
+        }
+        buffer[count_at..count_at + 2].copy_from_slice(&(messages.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(payload);
+
+        if self.sent.len() == SENT_WINDOW {
+            self.retire_front();
+        }
+        self.sent.push_back(SentPacket {
+            sequence,
+            sent_at: now,
+            messages,
+            acked: false,
+        });
+        sequence
+    }
+
+    /// Reads a packet received at `now`. A packet that fails to parse
+    /// leaves the channel untouched.
+    pub fn receive<'b>(&mut self, packet: &'b [u8], now: f64) -> CodecResult<Received<'b>> {
+        let packet = read_packet(packet)?;
+        let (stale, reliable) = self.apply(&packet, now, None);
+        Ok(Received {
+            payload: packet.payload,
+            stale,
+            reliable: reliable
+                .into_iter()
+                .map(|message| (message.kind, message.bytes))
+                .collect(),
+        })
+    }
+
+    /// Sends `frame` with its reliable sections moved to the reliable
+    /// stream; the rest of the frame is the packet's payload.
+    pub fn send_frame(
+        &mut self,
+        codec: &Codec,
+        frame: &Message,
+        now: f64,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<u16> {
+        let layout = codec.frame_layout();
+        let mut reliable = Vec::new();
+        for name in &self.config.reliable_sections {
+            if !frame.contains(name) {
+                continue;
+            }
+            let (index, section) = layout
+                .sections
+                .iter()
+                .enumerate()
+                .find(|(_, section)| section.name == *name && is_reliable_section(section))
+                .ok_or_else(|| CodecError::invalid_value(name, "an optional message section"))?;
+            if let Some(bytes) = codec.frame_message(frame, section)? {
+                reliable.push((index as u16, bytes.into_owned()));
+            }
+        }
+        let mut payload = Vec::new();
+        codec.write_frame(&mut payload, |_, section, buffer| {
+            if self.config.reliable_sections.contains(&section.name) && is_reliable_section(section)
+            {
+                return Ok(false);
+            }
+            codec.write_section(section, frame, buffer)
+        })?;
+
+        for (kind, bytes) in reliable {
+            self.send_reliable(kind, bytes);
+        }
+        Ok(self.send(&payload, now, buffer))
+    }
+
+    /// Reads a packet sent with `send_frame`, decoding its frame and the
+    /// reliable sections now deliverable. Like `receive`, a packet that
+    /// fails to parse leaves the channel untouched; one that parses is acked
+    /// even if its frame or sections fail to decode. Each section is decoded
+    /// with the protocol version of the frame it arrived with.
+    pub fn receive_frame(
+        &mut self,
+        codec: &Codec,
+        packet: &[u8],
+        now: f64,
+    ) -> CodecResult<ReceivedFrame> {
+        let packet = read_packet(packet)?;
+        let version = packet.payload.first().copied();
+        let (stale, messages) = self.apply(&packet, now, version);
+        let mut received = ReceivedFrame {
+            frame: None,
+            reliable: Vec::with_capacity(messages.len()),
+            errors: Vec::new(),
+        };
+        for message in messages {
+            match decode_reliable_section(codec, &message) {
+                Ok(section) => received.reliable.push(section),
+                Err(err) => received.errors.push(err),
+            }
+        }
+        if !stale {
+            match codec.decode_frame(packet.payload) {
+                Ok(frame) => received.frame = Some(frame),
+                Err(err) => received.errors.push(err),
+            }
+        }
+        Ok(received)
+    }
+
+    /// Records `packet` as received and buffers its reliable messages,
+    /// tagged with `version`. Returns whether the packet was stale and the
+    /// messages that can now be delivered, in order.
+    fn apply(
+        &mut self,
+        packet: &Packet,
+        now: f64,
+        version: Option<u8>,
+    ) -> (bool, Vec<UndeliveredMessage>) {
+        self.process_acks(packet.ack, packet.ack_bits, now);
+        let stale = !self.record_received(packet.sequence);
+        for &(id, kind, bytes) in &packet.messages {
+            if self.accepts(id) {
+                self.undelivered
+                    .entry(id)
+                    .or_insert_with(|| UndeliveredMessage {
+                        kind,
+                        bytes: bytes.to_vec(),
+                        version,
+                    });
+            }
+        }
+        let mut reliable = Vec::new();
+        while let Some(message) = self.undelivered.remove(&self.next_delivery) {
+            reliable.push(message);
+            self.next_delivery = self.next_delivery.wrapping_add(1);
+        }
+        (stale, reliable)
+    }
+
+    /// Whether reliable message `id` is buffered. Ids behind the next one to
+    /// deliver were delivered already.
+    fn accepts(&self, id: u16) -> bool {
+        id.wrapping_sub(self.next_delivery) < RELIABLE_WINDOW
+    }
+
+    /// Acks the sent packets covered by `ack` and `ack_bits`, then retires
+    /// packets that fell out of the ack window.
+    fn process_acks(&mut self, ack: u16, ack_bits: u32, now: f64) {
+        for packet in &mut self.sent {
+            let behind = ack.wrapping_sub(1).wrapping_sub(packet.sequence);
+            if packet.acked || behind >= ACK_BITS || ack_bits & (1 << behind) == 0 {
+                continue;
+            }
+            packet.acked = true;
+            let sample = now - packet.sent_at;
+            self.rtt = Some(match self.rtt {
+                Some(rtt) => rtt + (sample - rtt) * SMOOTHING,
+                None => sample,
+            });
+            self.packet_loss -= self.packet_loss * SMOOTHING;
+            self.unacked
+                .retain(|message| !packet.messages.contains(&message.id));
+        }
+        while let Some(packet) = self.sent.front() {
+            let behind = ack.wrapping_sub(1).wrapping_sub(packet.sequence);
+            if !packet.acked && !(ACK_BITS..0x8000).contains(&behind) {
+                break;
+            }
+            self.retire_front();
+        }
+    }
+
+    fn retire_front(&mut self) {
+        if let Some(packet) = self.sent.pop_front() {
+            if !packet.acked {
+                self.packet_loss += (1.0 - self.packet_loss) * SMOOTHING;
+            }
+        }
+    }
+
+    /// Whether `sequence` was not received before and is recent enough to
+    /// tell.
+    fn is_new(&self, sequence: u16) -> bool {
+        let Some(remote) = self.remote_sequence else {
+            return true;
+        };
+        let ahead = sequence.wrapping_sub(remote);
+        if ahead != 0 && ahead < 0x8000 {
+            return true;
+        }
+        let behind = remote.wrapping_sub(sequence);
+        behind < ACK_BITS && self.received_bits & (1 << behind) == 0
+    }
+
+    /// Marks `sequence` received, returning false if it was received
+    /// before or is too old to tell.
+    fn record_received(&mut self, sequence: u16) -> bool {
+        if !self.is_new(sequence) {
+            return false;
+        }
+        let Some(remote) = self.remote_sequence else {
+            self.remote_sequence = Some(sequence);
+            self.received_bits = 1;
+            return true;
+        };
+        let ahead = sequence.wrapping_sub(remote);
+        if ahead < 0x8000 {
+            self.received_bits = self.received_bits.checked_shl(ahead as u32).unwrap_or(0) | 1;
+            self.remote_sequence = Some(sequence);
+        } else {
+            self.received_bits |= 1 << remote.wrapping_sub(sequence);
+        }
+        true
+    }
+}
+
+fn read_packet(packet: &[u8]) -> CodecResult<Packet<'_>> {
+    let mut cursor = Cursor::new(packet);
+    let sequence = cursor.read_u16()?;
+    let ack = cursor.read_u16()?;
+    let ack_bits = cursor.read_u32()?;
+    let count = cursor.read_u16()? as usize;
+    let mut messages = Vec::with_capacity(count.min(cursor.remaining()));
+    for _ in 0..count {
+        let id = cursor.read_u16()?;
+        let kind = cursor.read_u16()?;
+        messages.push((id, kind, cursor.read_bytes()?));
+    }
+    Ok(Packet {
+        sequence,
+        ack,
+        ack_bits,
+        messages,
+        payload: cursor.read_slice(cursor.remaining())?,
+    })
+}
+
+/// Decodes a reliable section with the layout of the protocol it was sent
+/// in.
+fn decode_reliable_section(
+    codec: &Codec,
+    message: &UndeliveredMessage,
+) -> CodecResult<(String, Message)> {
+    let version = message.version.ok_or(CodecError::UnexpectedEnd)?;
+    let codec = codec.frame_codec(version)?;
+    let section = codec
+        .frame_layout()
+        .sections
+        .get(message.kind as usize)
+        .filter(|section| is_reliable_section(section))
+        .ok_or_else(|| {
+            CodecError::invalid_value(&format!("section {}", message.kind), "a message section")
+        })?;
+    let decoded = codec.decode_section_message(section, &message.bytes)?;
+    Ok((section.name.clone(), decoded))
+}
+
+fn is_reliable_section(section: &FrameSection) -> bool {
+    section.kind == SectionKind::Message && !section.is_repeated && section.bit.is_some()
+}
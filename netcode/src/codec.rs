@@ -0,0 +1,978 @@
+use std::borrow::Cow;
+
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+use crate::view::{FrameRef, MessageRef, ValueRef};
+use crate::wire::{write_f32, write_f64, Cursor, WireFormat, WireType};
+use crate::{
+    FieldKind, FieldSchema, FrameSchema, FrameSection, MapSchema, MessageLayout, MessageSchema,
+    NetSchema, OneofSchema, SectionKind,
+};
+
+/// Implemented by the typed messages emitted by `netcode-codegen`, which
+/// produce the same bytes as `Codec::encode_message` for the same values and
+/// wire format, and reject the same oversized lists and maps. `decode`
+/// follows the format of the cursor.
+pub trait NetMessage: Sized {
+    const NAME: &'static str;
+
+    fn encode(&self, buffer: &mut Vec<u8>, format: WireFormat) -> CodecResult<()>;
+
+    fn decode(cursor: &mut Cursor) -> CodecResult<Self>;
+}
+
+/// Schema-driven encoder/decoder for messages and frames.
+#[derive(Debug, Clone, Copy)]
+pub struct Codec<'a> {
+    schema: &'a NetSchema,
+    format: WireFormat,
+    frame: &'a FrameSchema,
+    nest_inline: bool,
+}
+
+impl<'a> Codec<'a> {
+    pub fn new(schema: &'a NetSchema) -> Self {
+        Self::with_format(schema, WireFormat::Fixed)
+    }
+
+    pub fn with_format(schema: &'a NetSchema, format: WireFormat) -> Self {
+        Self {
+            schema,
+            format,
+            frame: schema.frame_layout(),
+            nest_inline: false,
+        }
+    }
+
+    /// Speaks protocol `version` instead of the current one: frames use its
+    /// layout and messages leave out fields introduced after it.
+    pub fn with_protocol(self, version: u8) -> CodecResult<Self> {
+        let frame = self
+            .schema
+            .protocol(version)
+            .ok_or(CodecError::UnsupportedFrameVersion(version))?;
+        Ok(Self { frame, ..self })
+    }
+
+    pub fn protocol(&self) -> u8 {
+        self.frame.version
+    }
+
+    /// The frame layout of the codec's protocol.
+    pub fn frame_layout(&self) -> &'a FrameSchema {
+        self.frame
+    }
+
+    /// The layout of client input frames.
+    pub fn input_layout(&self) -> &'a FrameSchema {
+        self.schema.input_layout()
+    }
+
+    /// The version byte of frames this codec encodes.
+    pub fn frame_version(&self) -> u8 {
+        self.frame.version(self.format)
+    }
+
+    /// When set, decoding moves fields that belong to an inline group with a
+    /// field list back under the group's key, undoing the flattening done by
+    /// `encode_message`.
+    pub fn with_inline_nesting(self, nest_inline: bool) -> Self {
+        Self {
+            nest_inline,
+            ..self
+        }
+    }
+
+    pub fn nests_inline(&self) -> bool {
+        self.nest_inline
+    }
+
+    pub fn schema(&self) -> &'a NetSchema {
+        self.schema
+    }
+
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    pub fn message_schema(&self, name: &str) -> CodecResult<&'a MessageSchema> {
+        self.schema
+            .messages
+            .get(name)
+            .ok_or_else(|| CodecError::UnknownMessage(name.to_string()))
+    }
+
+    pub fn encode_message(
+        &self,
+        name: &str,
+        message: &Message,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        let schema = self.message_schema(name)?;
+        self.write_message(schema, message, buffer)
+    }
+
+    pub fn decode_message(&self, name: &str, bytes: &[u8]) -> CodecResult<Message> {
+        let schema = self.message_schema(name)?;
+        let mut cursor = Cursor::with_format(bytes, self.format);
+        self.read_message(schema, &mut cursor)
+    }
+
+    /// Like `decode_message`, but returns a view that borrows strings and
+    /// bytes from `bytes`. The whole message is checked up front; fields are
+    /// copied out when they are read.
+    pub fn decode_message_ref<'b>(&self, name: &str, bytes: &'b [u8]) -> CodecResult<MessageRef<'b>>
+    where
+        'a: 'b,
+    {
+        let schema = self.message_schema(name)?;
+        let mut cursor = Cursor::with_format(bytes, self.format);
+        MessageRef::read(*self, schema, &mut cursor)
+    }
+
+    /// Encodes a frame laid out by the codec's protocol. Missing
+    /// optional sections and false flags clear their presence bit; missing
+    /// required sections are written as zero or empty. Message sections take
+    /// a `Message` or pre-encoded `Bytes`.
+    pub fn encode_frame(&self, frame: &Message, buffer: &mut Vec<u8>) -> CodecResult<()> {
+        self.write_frame(buffer, |_, section, buffer| {
+            self.write_section(section, frame, buffer)
+        })
+    }
+
+    /// Writes the version byte and presence bits around the sections written
+    /// by `write_section`, which is called with each section of the layout
+    /// and its index, and returns whether the section is present.
+    pub(crate) fn write_frame(
+        &self,
+        buffer: &mut Vec<u8>,
+        write_section: impl FnMut(usize, &FrameSection, &mut Vec<u8>) -> CodecResult<bool>,
+    ) -> CodecResult<()> {
+        self.write_layout(self.frame, buffer, write_section)
+    }
+
+    fn write_layout(
+        &self,
+        layout: &FrameSchema,
+        buffer: &mut Vec<u8>,
+        mut write_section: impl FnMut(usize, &FrameSection, &mut Vec<u8>) -> CodecResult<bool>,
+    ) -> CodecResult<()> {
+        buffer.push(layout.version(self.format));
+        let presence = buffer.len();
+        buffer.resize(presence + layout.presence_len(), 0);
+
+        for (index, section) in layout.sections.iter().enumerate() {
+            let present = write_section(index, section, buffer)?;
+            if let (true, Some(bit)) = (present, section.bit) {
+                buffer[presence + bit as usize / 8] |= 1 << (bit % 8);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes one section of `frame`, returning whether it is present.
+    pub(crate) fn write_section(
+        &self,
+        section: &FrameSection,
+        frame: &Message,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<bool> {
+        let format = self.format;
+        let key = section.name.as_str();
+        let required = section.bit.is_none();
+        let present = match section.kind {
+            SectionKind::Flag => frame_bool(frame, key)?,
+            SectionKind::UInt32 => match frame_u32(frame, key)?.or(required.then_some(0)) {
+                Some(value) => {
+                    format.write_u32(buffer, value);
+                    true
+                }
+                None => false,
+            },
+            SectionKind::Float => match frame_f32(frame, key)?.or(required.then_some(0.0)) {
+                Some(value) => {
+                    write_f32(buffer, value);
+                    true
+                }
+                None => false,
+            },
+            SectionKind::Bytes => match frame_bytes(frame, key)?.or(required.then_some(&[])) {
+                Some(value) => {
+                    format.write_bytes(buffer, value);
+                    true
+                }
+                None => false,
+            },
+            SectionKind::Message if section.is_repeated => {
+                let schema = self.frame_section_schema(section)?;
+                self.write_message_list(schema, frame, key, buffer)?;
+                true
+            }
+            SectionKind::Message => match self.frame_message(frame, section)? {
+                Some(value) => {
+                    format.write_bytes(buffer, &value);
+                    true
+                }
+                None => false,
+            },
+        };
+        Ok(present)
+    }
+
+    /// Decodes a frame produced by `encode_frame` back into the same shape.
+    /// Flags are always present as bools, repeated sections as (possibly
+    /// empty) lists, and nested messages are decoded with their schemas.
+    ///
+    /// The protocol and wire format are taken from the frame version, so a
+    /// codec decodes frames of every protocol in the schema, fixed or
+    /// compact, regardless of its own.
+    pub fn decode_frame(&self, bytes: &[u8]) -> CodecResult<Message> {
+        let mut cursor = Cursor::new(bytes);
+        let (codec, presence) = self.read_frame_header(&mut cursor)?;
+        let frame = codec.read_sections(codec.frame, presence, &mut cursor)?;
+        if !cursor.is_empty() {
+            return Err(CodecError::TrailingBytes(cursor.remaining()));
+        }
+        Ok(frame)
+    }
+
+    /// Encodes a client input frame laid out by `input_layout`, the same way
+    /// `encode_frame` encodes frames. Messages in it leave out fields newer
+    /// than the codec's protocol.
+    pub fn encode_input(&self, input: &Message, buffer: &mut Vec<u8>) -> CodecResult<()> {
+        self.write_layout(self.input_layout(), buffer, |_, section, buffer| {
+            self.write_section(section, input, buffer)
+        })
+    }
+
+    /// Decodes an input frame produced by `encode_input`, in either wire
+    /// format, into the shape `decode_frame` gives frames.
+    pub fn decode_input(&self, bytes: &[u8]) -> CodecResult<Message> {
+        let layout = self.input_layout();
+        let mut cursor = Cursor::new(bytes);
+        let version = cursor.read_u8()?;
+        let format = layout
+            .format(version)
+            .ok_or(CodecError::UnsupportedFrameVersion(version))?;
+        let presence = read_frame_presence(layout, &mut cursor)?;
+        cursor.set_format(format);
+        let codec = Codec { format, ..*self };
+        let input = codec.read_sections(layout, presence, &mut cursor)?;
+        if !cursor.is_empty() {
+            return Err(CodecError::TrailingBytes(cursor.remaining()));
+        }
+        Ok(input)
+    }
+
+    /// Reads the sections of `layout` that follow the presence bits.
+    fn read_sections(
+        &self,
+        layout: &FrameSchema,
+        presence: &[u8],
+        cursor: &mut Cursor,
+    ) -> CodecResult<Message> {
+        let mut frame = Message::new();
+        for section in &layout.sections {
+            let key = section.name.as_str();
+            let present = is_present(presence, section);
+            match section.kind {
+                SectionKind::Flag => frame.insert(key, present),
+                _ if !present => {}
+                SectionKind::UInt32 => frame.insert(key, cursor.read_u32()?),
+                SectionKind::Float => frame.insert(key, cursor.read_f32()?),
+                SectionKind::Bytes => frame.insert(key, cursor.read_bytes()?.to_vec()),
+                SectionKind::Message if section.is_repeated => {
+                    let schema = self.frame_section_schema(section)?;
+                    let count = cursor.read_u32()? as usize;
+                    let mut items = Vec::with_capacity(count.min(cursor.remaining()));
+                    for _ in 0..count {
+                        items.push(Value::Message(self.read_message(schema, cursor)?));
+                    }
+                    frame.insert(key, Value::List(items));
+                }
+                SectionKind::Message => {
+                    let message = self.decode_section_message(section, cursor.read_bytes()?)?;
+                    frame.insert(key, message);
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    /// Like `decode_frame`, but bytes sections borrow from `bytes` and
+    /// message sections are read as `MessageRef`s.
+    pub fn decode_frame_ref<'b>(&self, bytes: &'b [u8]) -> CodecResult<FrameRef<'b>>
+    where
+        'a: 'b,
+    {
+        let mut cursor = Cursor::new(bytes);
+        let (codec, presence) = self.read_frame_header(&mut cursor)?;
+        let format = codec.format;
+
+        let mut sections = Vec::with_capacity(codec.frame.sections.len());
+        for section in &codec.frame.sections {
+            let present = is_present(presence, section);
+            let value = match section.kind {
+                SectionKind::Flag => ValueRef::Bool(present),
+                _ if !present => continue,
+                SectionKind::UInt32 => ValueRef::UInt32(cursor.read_u32()?),
+                SectionKind::Float => ValueRef::Float(cursor.read_f32()?),
+                SectionKind::Bytes => ValueRef::Bytes(cursor.read_bytes()?),
+                SectionKind::Message if section.is_repeated => {
+                    let schema = codec.frame_section_schema(section)?;
+                    let count = cursor.read_u32()? as usize;
+                    let mut items = Vec::with_capacity(count.min(cursor.remaining()));
+                    for _ in 0..count {
+                        items.push(ValueRef::Message(MessageRef::read(
+                            codec,
+                            schema,
+                            &mut cursor,
+                        )?));
+                    }
+                    ValueRef::List(items)
+                }
+                SectionKind::Message => {
+                    let schema = codec.frame_section_schema(section)?;
+                    let mut section = Cursor::with_format(cursor.read_bytes()?, format);
+                    let message = MessageRef::read(codec, schema, &mut section)?;
+                    if !section.is_empty() {
+                        return Err(CodecError::TrailingBytes(section.remaining()));
+                    }
+                    ValueRef::Message(message)
+                }
+            };
+            sections.push((section, value));
+        }
+
+        if !cursor.is_empty() {
+            return Err(CodecError::TrailingBytes(cursor.remaining()));
+        }
+        Ok(FrameRef::new(sections))
+    }
+
+    /// A codec for the protocol and wire format of frames with version byte
+    /// `version`.
+    pub(crate) fn frame_codec(&self, version: u8) -> CodecResult<Codec<'a>> {
+        let (frame, format) = self
+            .schema
+            .frame_layouts()
+            .find_map(|layout| Some((layout, layout.format(version)?)))
+            .ok_or(CodecError::UnsupportedFrameVersion(version))?;
+        Ok(Codec {
+            format,
+            frame,
+            ..*self
+        })
+    }
+
+    /// Decodes the contents of a singular message section.
+    pub(crate) fn decode_section_message(
+        &self,
+        section: &FrameSection,
+        bytes: &[u8],
+    ) -> CodecResult<Message> {
+        let schema = self.frame_section_schema(section)?;
+        let mut cursor = Cursor::with_format(bytes, self.format);
+        let message = self.read_message(schema, &mut cursor)?;
+        if !cursor.is_empty() {
+            return Err(CodecError::TrailingBytes(cursor.remaining()));
+        }
+        Ok(message)
+    }
+
+    /// Reads the version byte and presence bits of a frame, returning a
+    /// codec for the frame's protocol and wire format.
+    fn read_frame_header<'b>(&self, cursor: &mut Cursor<'b>) -> CodecResult<(Codec<'a>, &'b [u8])> {
+        let codec = self.frame_codec(cursor.read_u8()?)?;
+        let presence = read_frame_presence(codec.frame, cursor)?;
+        cursor.set_format(codec.format);
+        Ok((codec, presence))
+    }
+
+    pub fn frame_section_schema(&self, section: &FrameSection) -> CodecResult<&'a MessageSchema> {
+        match section.type_name.as_deref() {
+            Some(name) => self.message_schema(name),
+            None => Err(CodecError::MissingMessageType(section.name.clone())),
+        }
+    }
+
+    pub(crate) fn frame_message<'m>(
+        &self,
+        frame: &'m Message,
+        section: &FrameSection,
+    ) -> CodecResult<Option<Cow<'m, [u8]>>> {
+        let key = section.name.as_str();
+        let message = match frame.get(key) {
+            Some(Value::Bytes(bytes)) => return Ok(Some(Cow::Borrowed(bytes))),
+            Some(Value::Message(message)) => Cow::Borrowed(message),
+            Some(_) => return Err(CodecError::invalid_value(key, "message or bytes")),
+            None if section.bit.is_none() => Cow::Owned(Message::new()),
+            None => return Ok(None),
+        };
+        let schema = self.frame_section_schema(section)?;
+        let mut buffer = Vec::with_capacity(128);
+        self.write_message(schema, &message, &mut buffer)?;
+        Ok(Some(Cow::Owned(buffer)))
+    }
+
+    fn write_message_list(
+        &self,
+        schema: &MessageSchema,
+        frame: &Message,
+        key: &str,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        let items = match frame.get(key) {
+            Some(value) => value
+                .as_list()
+                .ok_or_else(|| CodecError::invalid_value(key, "list"))?,
+            None => {
+                self.format.write_u32(buffer, 0);
+                return Ok(());
+            }
+        };
+
+        self.format.write_u32(buffer, items.len() as u32);
+        for item in items {
+            let message = item
+                .as_message()
+                .ok_or_else(|| CodecError::invalid_value(key, "message"))?;
+            self.write_message(schema, message, buffer)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_message(
+        &self,
+        schema: &MessageSchema,
+        message: &Message,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        let mut entries = inline_entries(schema, message)?;
+        entries.retain(|(field, _)| field.in_protocol(self.protocol()));
+
+        for oneof in &schema.oneofs {
+            check_oneof(oneof, |name| {
+                entries.iter().any(|(field, _)| field.name == name)
+            })?;
+        }
+
+        if schema.layout == MessageLayout::Bitmap {
+            return self.write_bitmap_fields(schema, &entries, buffer);
+        }
+
+        entries.sort_by_key(|(field, _)| field.number);
+        self.format.write_u16(buffer, entries.len() as u16);
+
+        for (field, value) in entries {
+            if schema.layout == MessageLayout::Tagged {
+                self.format.write_u16(buffer, field.number);
+                self.write_field_value(field, value, buffer)?;
+                continue;
+            }
+            let wire_type = wire_type(field, self.format);
+            self.format.write_key(buffer, field.number, wire_type);
+            if is_wrapped(field, wire_type) {
+                let mut nested = Vec::new();
+                self.write_field_value(field, value, &mut nested)?;
+                self.format.write_bytes(buffer, &nested);
+            } else {
+                self.write_field_value(field, value, buffer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bitmap_fields(
+        &self,
+        schema: &MessageSchema,
+        entries: &[(&FieldSchema, &Value)],
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        let presence_start = buffer.len();
+        buffer.resize(presence_start + self.presence_len(schema), 0);
+
+        for (index, field) in self.bitmap_fields(schema).enumerate() {
+            let Some((_, value)) = entries
+                .iter()
+                .find(|(entry, _)| entry.number == field.number)
+            else {
+                continue;
+            };
+            buffer[presence_start + index / 8] |= 1 << (index % 8);
+            self.write_field_value(field, value, buffer)?;
+        }
+        Ok(())
+    }
+
+    fn write_field_value(
+        &self,
+        field: &FieldSchema,
+        value: &Value,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        if field.is_repeated {
+            let items = value
+                .as_list()
+                .ok_or_else(|| CodecError::invalid_value(&field.name, "list"))?;
+            let count = u16::try_from(items.len())
+                .map_err(|_| CodecError::invalid_value(&field.name, "at most 65535 items"))?;
+            self.format.write_u16(buffer, count);
+            for item in items {
+                self.write_single_value(field, item, buffer)?;
+            }
+            return Ok(());
+        }
+
+        self.write_single_value(field, value, buffer)
+    }
+
+    fn write_single_value(
+        &self,
+        field: &FieldSchema,
+        value: &Value,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        let name = field.name.as_str();
+        match field.kind {
+            FieldKind::Int32 | FieldKind::Enum => {
+                let value = value
+                    .as_i32()
+                    .ok_or_else(|| CodecError::invalid_value(name, "int32"))?;
+                self.format.write_i32(buffer, value);
+            }
+            FieldKind::UInt32 => {
+                let value = match value {
+                    Value::Int32(v) if *v < 0 => {
+                        return Err(CodecError::invalid_value(name, "non-negative uint32"))
+                    }
+                    other => other
+                        .as_u32()
+                        .ok_or_else(|| CodecError::invalid_value(name, "uint32"))?,
+                };
+                self.format.write_u32(buffer, value);
+            }
+            FieldKind::Int64 => {
+                let value = value
+                    .as_i64()
+                    .ok_or_else(|| CodecError::invalid_value(name, "int64"))?;
+                self.format.write_i64(buffer, value);
+            }
+            FieldKind::UInt64 => {
+                let value = match value {
+                    Value::Int32(v) if *v < 0 => {
+                        return Err(CodecError::invalid_value(name, "non-negative uint64"))
+                    }
+                    Value::Int64(v) if *v < 0 => {
+                        return Err(CodecError::invalid_value(name, "non-negative uint64"))
+                    }
+                    other => other
+                        .as_u64()
+                        .ok_or_else(|| CodecError::invalid_value(name, "uint64"))?,
+                };
+                self.format.write_u64(buffer, value);
+            }
+            FieldKind::Float => {
+                let value = value
+                    .as_f32()
+                    .ok_or_else(|| CodecError::invalid_value(name, "float"))?;
+                match field.quantization {
+                    Some(quantization) => quantization.write(buffer, value),
+                    None => write_f32(buffer, value),
+                }
+            }
+            FieldKind::Double => {
+                let value = value
+                    .as_f64()
+                    .ok_or_else(|| CodecError::invalid_value(name, "double"))?;
+                write_f64(buffer, value);
+            }
+            FieldKind::Bool => {
+                let value = value
+                    .as_bool()
+                    .ok_or_else(|| CodecError::invalid_value(name, "bool"))?;
+                buffer.push(if value { 1 } else { 0 });
+            }
+            FieldKind::String => {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| CodecError::invalid_value(name, "string"))?;
+                self.format.write_bytes(buffer, value.as_bytes());
+            }
+            FieldKind::Bytes => {
+                let value = value
+                    .as_bytes()
+                    .ok_or_else(|| CodecError::invalid_value(name, "bytes"))?;
+                self.format.write_bytes(buffer, value);
+            }
+            FieldKind::Message => {
+                let message = value
+                    .as_message()
+                    .ok_or_else(|| CodecError::invalid_value(name, "message"))?;
+                let message_schema = self.field_message_schema(field)?;
+                self.write_message(message_schema, message, buffer)?;
+            }
+            FieldKind::Map => {
+                let entries = value
+                    .as_map()
+                    .ok_or_else(|| CodecError::invalid_value(name, "map"))?;
+                let map = field_map_schema(field)?;
+                let count = u16::try_from(entries.len())
+                    .map_err(|_| CodecError::invalid_value(name, "at most 65535 map entries"))?;
+                self.format.write_u16(buffer, count);
+                for (key, value) in entries {
+                    self.write_single_value(&map.key, key, buffer)?;
+                    self.write_single_value(&map.value, value, buffer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_message(
+        &self,
+        schema: &MessageSchema,
+        cursor: &mut Cursor,
+    ) -> CodecResult<Message> {
+        let message = match schema.layout {
+            MessageLayout::Tagged => self.read_tagged_fields(schema, cursor)?,
+            MessageLayout::Bitmap => self.read_bitmap_fields(schema, cursor)?,
+            MessageLayout::Typed => self.read_typed_fields(schema, cursor)?,
+        };
+        check_oneofs(schema, &message)?;
+        if self.nest_inline {
+            return Ok(nest_inline(schema, message));
+        }
+        Ok(message)
+    }
+
+    fn read_tagged_fields(
+        &self,
+        schema: &MessageSchema,
+        cursor: &mut Cursor,
+    ) -> CodecResult<Message> {
+        let field_count = cursor.read_u16()? as usize;
+        let mut message = Message::with_capacity(field_count);
+        for _ in 0..field_count {
+            let number = cursor.read_u16()?;
+            let field = schema
+                .fields_by_number
+                .get(&number)
+                .ok_or(CodecError::UnknownFieldNumber(number))?;
+            let value = self.read_field_value(field, cursor)?;
+            // Written by a peer on a newer protocol; skipped so messages
+            // have the same shape whichever side encoded them.
+            if field.in_protocol(self.protocol()) {
+                message.insert(field.name.as_str(), value);
+            }
+        }
+        Ok(message)
+    }
+
+    fn read_typed_fields(
+        &self,
+        schema: &MessageSchema,
+        cursor: &mut Cursor,
+    ) -> CodecResult<Message> {
+        let field_count = cursor.read_u16()? as usize;
+        let mut message = Message::with_capacity(field_count);
+        for _ in 0..field_count {
+            let (number, found) = cursor.read_key()?;
+            let Some(field) = schema.fields_by_number.get(&number) else {
+                cursor.skip(found)?;
+                continue;
+            };
+            let wire_type = wire_type(field, cursor.format());
+            if found != wire_type {
+                return Err(CodecError::WireTypeMismatch(field.name.clone()));
+            }
+            let value = if is_wrapped(field, wire_type) {
+                let mut nested = Cursor::with_format(cursor.read_bytes()?, cursor.format());
+                let value = self.read_field_value(field, &mut nested)?;
+                if !nested.is_empty() {
+                    return Err(CodecError::TrailingBytes(nested.remaining()));
+                }
+                value
+            } else {
+                self.read_field_value(field, cursor)?
+            };
+            if field.in_protocol(self.protocol()) {
+                message.insert(field.name.as_str(), value);
+            }
+        }
+        Ok(message)
+    }
+
+    fn read_bitmap_fields(
+        &self,
+        schema: &MessageSchema,
+        cursor: &mut Cursor,
+    ) -> CodecResult<Message> {
+        let presence = self.read_presence(schema, cursor)?;
+        let mut message = Message::new();
+        for (index, field) in self.bitmap_fields(schema).enumerate() {
+            if presence[index / 8] & (1 << (index % 8)) != 0 {
+                let value = self.read_field_value(field, cursor)?;
+                message.insert(field.name.as_str(), value);
+            }
+        }
+        Ok(message)
+    }
+
+    fn read_field_value(&self, field: &FieldSchema, cursor: &mut Cursor) -> CodecResult<Value> {
+        if field.is_repeated {
+            let count = cursor.read_u16()? as usize;
+            let mut items = Vec::with_capacity(count);
+            for _ in 0..count {
+                items.push(self.read_single_value(field, cursor)?);
+            }
+            return Ok(Value::List(items));
+        }
+
+        self.read_single_value(field, cursor)
+    }
+
+    fn read_single_value(&self, field: &FieldSchema, cursor: &mut Cursor) -> CodecResult<Value> {
+        match field.kind {
+            FieldKind::Int32 | FieldKind::Enum => Ok(Value::Int32(cursor.read_i32()?)),
+            FieldKind::UInt32 => Ok(Value::UInt32(cursor.read_u32()?)),
+            FieldKind::Int64 => Ok(Value::Int64(cursor.read_i64()?)),
+            FieldKind::UInt64 => Ok(Value::UInt64(cursor.read_u64()?)),
+            FieldKind::Float => match field.quantization {
+                Some(quantization) => Ok(Value::Float(quantization.read(cursor)?)),
+                None => Ok(Value::Float(cursor.read_f32()?)),
+            },
+            FieldKind::Double => Ok(Value::Double(cursor.read_f64()?)),
+            FieldKind::Bool => Ok(Value::Bool(cursor.read_u8()? == 1)),
+            FieldKind::String => Ok(Value::String(cursor.read_str()?.to_string())),
+            FieldKind::Bytes => Ok(Value::Bytes(cursor.read_bytes()?.to_vec())),
+            FieldKind::Message => {
+                let message_schema = self.field_message_schema(field)?;
+                Ok(Value::Message(self.read_message(message_schema, cursor)?))
+            }
+            FieldKind::Map => {
+                let map = field_map_schema(field)?;
+                let count = cursor.read_u16()? as usize;
+                let mut entries = Vec::with_capacity(count.min(cursor.remaining()));
+                for _ in 0..count {
+                    let key = self.read_single_value(&map.key, cursor)?;
+                    let value = self.read_single_value(&map.value, cursor)?;
+                    entries.push((key, value));
+                }
+                Ok(Value::Map(entries))
+            }
+        }
+    }
+
+    /// Reads the presence bits of a `MessageLayout::Bitmap` message.
+    pub(crate) fn read_presence<'b>(
+        &self,
+        schema: &MessageSchema,
+        cursor: &mut Cursor<'b>,
+    ) -> CodecResult<&'b [u8]> {
+        let field_count = self.bitmap_fields(schema).count();
+        let presence = cursor.read_slice(self.presence_len(schema))?;
+        let unused_bits = presence.len() * 8 - field_count;
+        if unused_bits > 0 && presence[presence.len() - 1] >> (8 - unused_bits) != 0 {
+            return Err(CodecError::InvalidPresenceBitmap);
+        }
+        Ok(presence)
+    }
+
+    /// The fields that have a presence bit in the codec's protocol.
+    pub(crate) fn bitmap_fields<'s>(
+        &self,
+        schema: &'s MessageSchema,
+    ) -> impl Iterator<Item = &'s FieldSchema> {
+        let protocol = self.protocol();
+        schema
+            .fields
+            .iter()
+            .filter(move |field| field.in_protocol(protocol))
+    }
+
+    fn presence_len(&self, schema: &MessageSchema) -> usize {
+        self.bitmap_fields(schema).count().div_ceil(8)
+    }
+
+    pub fn field_message_schema(&self, field: &FieldSchema) -> CodecResult<&'a MessageSchema> {
+        match field.type_name.as_deref() {
+            Some(name) => self.message_schema(name),
+            None => Err(CodecError::MissingMessageType(field.name.clone())),
+        }
+    }
+}
+
+/// The wire type of `field` in `MessageLayout::Typed` messages.
+pub fn wire_type(field: &FieldSchema, format: WireFormat) -> WireType {
+    if field.is_repeated || field.quantization.is_some() {
+        return WireType::Len;
+    }
+    match field.kind {
+        FieldKind::Int32 | FieldKind::UInt32 | FieldKind::Enum => format.int32_wire_type(),
+        FieldKind::Int64 | FieldKind::UInt64 => format.int64_wire_type(),
+        FieldKind::Float => WireType::Fixed32,
+        FieldKind::Double => WireType::Fixed64,
+        FieldKind::Bool => WireType::Varint,
+        FieldKind::String | FieldKind::Bytes | FieldKind::Message | FieldKind::Map => WireType::Len,
+    }
+}
+
+/// Whether a `Len` value needs a length prefix of its own; strings and bytes
+/// already have one.
+pub(crate) fn is_wrapped(field: &FieldSchema, wire_type: WireType) -> bool {
+    wire_type == WireType::Len
+        && (field.is_repeated || !matches!(field.kind, FieldKind::String | FieldKind::Bytes))
+}
+
+pub(crate) fn field_map_schema(field: &FieldSchema) -> CodecResult<&MapSchema> {
+    field
+        .map
+        .as_deref()
+        .ok_or_else(|| CodecError::invalid_value(&field.name, "map schema"))
+}
+
+pub(crate) fn check_oneof(oneof: &OneofSchema, is_set: impl Fn(&str) -> bool) -> CodecResult<()> {
+    if oneof.fields.iter().filter(|name| is_set(name)).count() > 1 {
+        return Err(CodecError::OneofConflict(oneof.name.clone()));
+    }
+    Ok(())
+}
+
+fn check_oneofs(schema: &MessageSchema, message: &Message) -> CodecResult<()> {
+    for oneof in &schema.oneofs {
+        check_oneof(oneof, |name| message.contains(name))?;
+    }
+    Ok(())
+}
+
+/// Resolves the fields of `message`, splicing in the entries of inline
+/// groups. Keys the schema does not know are dropped; an inline group that is
+/// not a message, or a field given more than once, is an error.
+pub(crate) fn inline_entries<'m>(
+    schema: &'m MessageSchema,
+    message: &'m Message,
+) -> CodecResult<Vec<(&'m FieldSchema, &'m Value)>> {
+    let mut entries: Vec<(&FieldSchema, &Value)> = Vec::with_capacity(message.len());
+    let mut push = |field: &'m FieldSchema, value: &'m Value| {
+        if entries
+            .iter()
+            .any(|(entry, _)| entry.number == field.number)
+        {
+            return Err(CodecError::invalid_value(&field.name, "a single value"));
+        }
+        entries.push((field, value));
+        Ok(())
+    };
+    for (key, value) in message.iter() {
+        if let Some(group) = schema.inline_group(key) {
+            let inline = value
+                .as_message()
+                .ok_or_else(|| CodecError::invalid_value(key, "message"))?;
+            for (key, value) in inline.iter() {
+                match schema.fields_by_name.get(key) {
+                    Some(field) if group.accepts(key) => push(field, value)?,
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        if let Some(field) = schema.fields_by_name.get(key) {
+            push(field, value)?;
+        }
+    }
+    Ok(entries)
+}
+
+/// Moves fields listed by an inline group under the group's key.
+pub(crate) fn nest_inline(schema: &MessageSchema, message: Message) -> Message {
+    if schema
+        .inline_groups
+        .iter()
+        .all(|group| group.fields.is_none())
+    {
+        return message;
+    }
+    let mut nested = Message::with_capacity(message.len());
+    let mut groups: Vec<Option<Message>> = vec![None; schema.inline_groups.len()];
+    for (key, value) in message {
+        let index = schema
+            .inline_groups
+            .iter()
+            .position(|group| group.nests(&key));
+        match index {
+            Some(index) => groups[index]
+                .get_or_insert_with(Message::new)
+                .insert(key, value),
+            None => nested.insert(key, value),
+        }
+    }
+    for (group, fields) in schema.inline_groups.iter().zip(groups) {
+        if let Some(fields) = fields {
+            nested.insert(group.name.as_str(), fields);
+        }
+    }
+    nested
+}
+
+/// Reads the presence bits of a frame laid out by `layout`.
+fn read_frame_presence<'b>(layout: &FrameSchema, cursor: &mut Cursor<'b>) -> CodecResult<&'b [u8]> {
+    let presence = cursor.read_slice(layout.presence_len())?;
+    // Bits the layout does not define mean the frame was written with a
+    // different layout.
+    let set_bits: u32 = presence.iter().map(|byte| byte.count_ones()).sum();
+    let known_bits = layout
+        .sections
+        .iter()
+        .filter(|section| section.bit.is_some() && is_present(presence, section))
+        .count();
+    if known_bits != set_bits as usize {
+        return Err(CodecError::InvalidPresenceBitmap);
+    }
+    Ok(presence)
+}
+
+fn is_present(presence: &[u8], section: &FrameSection) -> bool {
+    section
+        .bit
+        .is_none_or(|bit| presence[bit as usize / 8] & (1 << (bit % 8)) != 0)
+}
+
+fn frame_u32(frame: &Message, key: &str) -> CodecResult<Option<u32>> {
+    match frame.get(key) {
+        Some(value) => value
+            .as_u32()
+            .map(Some)
+            .ok_or_else(|| CodecError::invalid_value(key, "uint32")),
+        None => Ok(None),
+    }
+}
+
+fn frame_f32(frame: &Message, key: &str) -> CodecResult<Option<f32>> {
+    match frame.get(key) {
+        Some(value) => value
+            .as_f32()
+            .map(Some)
+            .ok_or_else(|| CodecError::invalid_value(key, "float")),
+        None => Ok(None),
+    }
+}
+
+fn frame_bool(frame: &Message, key: &str) -> CodecResult<bool> {
+    match frame.get(key) {
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| CodecError::invalid_value(key, "bool")),
+        None => Ok(false),
+    }
+}
+
+fn frame_bytes<'m>(frame: &'m Message, key: &str) -> CodecResult<Option<&'m [u8]>> {
+    match frame.get(key) {
+        Some(value) => value
+            .as_bytes()
+            .map(Some)
+            .ok_or_else(|| CodecError::invalid_value(key, "bytes")),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,150 @@
+use std::io;
+
+use zstd::bulk::{Compressor, Decompressor};
+
+use crate::error::{CodecError, CodecResult};
+use crate::frame::COMPRESSED_FRAME_BIT;
+use crate::wire::{write_varint, Cursor, WireFormat};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// The zstd compression level.
+    pub level: i32,
+    /// Frames shorter than this are sent uncompressed.
+    pub min_size: usize,
+    /// The largest decompressed frame accepted, so a corrupt length cannot
+    /// make the receiver allocate without bound.
+    pub max_frame_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            min_size: 64,
+            max_frame_size: 1 << 20,
+        }
+    }
+}
+
+/// Optional compression stage between `Codec::encode_frame` and the socket.
+///
+/// A compressed frame keeps its version byte with `COMPRESSED_FRAME_BIT`
+/// set, followed by the varint length of the rest of the frame and the rest
+/// of the frame as one zstd frame. Frames that are short or do not shrink
+/// are passed through unchanged, so receivers decompress every frame and
+/// only compressed ones are touched.
+///
+/// Both ends must use the same dictionary, usually trained with
+/// `train_dictionary` from a capture of real frames.
+pub struct FrameCompressor {
+    config: CompressionConfig,
+    compressor: Compressor<'static>,
+    decompressor: Decompressor<'static>,
+    buffer: Vec<u8>,
+}
+
+impl FrameCompressor {
+    pub fn new(config: CompressionConfig) -> CodecResult<Self> {
+        Self::with_dictionary(&[], config)
+    }
+
+    pub fn with_dictionary(dictionary: &[u8], config: CompressionConfig) -> CodecResult<Self> {
+        Ok(Self {
+            config,
+            compressor: Compressor::with_dictionary(config.level, dictionary)
+                .map_err(compression_error)?,
+            decompressor: Decompressor::with_dictionary(dictionary).map_err(compression_error)?,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> CompressionConfig {
+        self.config
+    }
+
+    /// Appends `frame`, an encoded frame, to `buffer`, compressed if that
+    /// makes it smaller.
+    pub fn compress(&mut self, frame: &[u8], buffer: &mut Vec<u8>) -> CodecResult<()> {
+        let (&version, body) = frame.split_first().ok_or(CodecError::UnexpectedEnd)?;
+        if version & COMPRESSED_FRAME_BIT != 0 {
+            return Err(CodecError::CompressedFrame);
+        }
+        if frame.len() < self.config.min_size {
+            buffer.extend_from_slice(frame);
+            return Ok(());
+        }
+
+        let compressed = &mut self.buffer;
+        compressed.clear();
+        compressed.push(version | COMPRESSED_FRAME_BIT);
+        write_varint(compressed, body.len() as u64);
+        let header = compressed.len();
+        compressed.resize(header + zstd::zstd_safe::compress_bound(body.len()), 0);
+        let len = self
+            .compressor
+            .compress_to_buffer(body, &mut compressed[header..])
+            .map_err(compression_error)?;
+        compressed.truncate(header + len);
+
+        if compressed.len() < frame.len() {
+            buffer.extend_from_slice(compressed);
+        } else {
+            buffer.extend_from_slice(frame);
+        }
+        Ok(())
+    }
+
+    /// Returns the frame in `bytes` ready for `Codec::decode_frame`: `bytes`
+    /// itself if it is not compressed, otherwise the decompressed frame,
+    /// which is overwritten by the next call.
+    pub fn decompress<'b>(&'b mut self, bytes: &'b [u8]) -> CodecResult<&'b [u8]> {
+        let (&version, rest) = bytes.split_first().ok_or(CodecError::UnexpectedEnd)?;
+        if version & COMPRESSED_FRAME_BIT == 0 {
+            return Ok(bytes);
+        }
+        let mut cursor = Cursor::with_format(rest, WireFormat::Compact);
+        let len = cursor.read_varint()?;
+        if len > self.config.max_frame_size as u64 {
+            return Err(CodecError::FrameTooLarge(len));
+        }
+        let len = len as usize;
+
+        let frame = &mut self.buffer;
+        frame.clear();
+        frame.resize(len + 1, 0);
+        frame[0] = version & !COMPRESSED_FRAME_BIT;
+        let written = self
+            .decompressor
+            .decompress_to_buffer(&rest[cursor.offset()..], &mut frame[1..])
+            .map_err(compression_error)?;
+        if written != len {
+            return Err(CodecError::Compression(format!(
+                "expected {len} decompressed bytes, got {written}"
+            )));
+        }
+        Ok(frame)
+    }
+}
+
+/// Trains a dictionary of at most `max_size` bytes from uncompressed
+/// frames, such as those of a capture. zstd needs a few hundred frames or
+/// more to find anything worth keeping.
+pub fn train_dictionary<F: AsRef<[u8]>>(frames: &[F], max_size: usize) -> CodecResult<Vec<u8>> {
+    let mut bodies = Vec::with_capacity(frames.len());
+    for frame in frames {
+        match frame.as_ref().split_first() {
+            Some((version, _)) if version & COMPRESSED_FRAME_BIT != 0 => {
+                return Err(CodecError::CompressedFrame)
+            }
+            // The version byte is never compressed, so leave it out.
+            Some((_, body)) => bodies.push(body),
+            None => {}
+        }
+    }
+    zstd::dict::from_samples(&bodies, max_size).map_err(compression_error)
+}
+
+fn compression_error(error: io::Error) -> CodecError {
+    CodecError::Compression(error.to_string())
+}
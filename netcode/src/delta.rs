@@ -0,0 +1,355 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::codec::{inline_entries, nest_inline, Codec};
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+use crate::wire::Cursor;
+use crate::MessageSchema;
+
+const KEY_FIELD: &str = "id";
+const STATE_FULL: u8 = 0;
+const STATE_DELTA: u8 = 1;
+
+struct Snapshot {
+    sequence: u32,
+    entities: Vec<Message>,
+}
+
+#[derive(Default)]
+struct ClientHistory {
+    snapshots: VecDeque<Snapshot>,
+    acked: Option<u32>,
+}
+
+impl ClientHistory {
+    fn find(&self, sequence: u32) -> Option<&Snapshot> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.sequence == sequence)
+    }
+
+    fn push(&mut self, snapshot: Snapshot, history: usize) {
+        self.snapshots
+            .retain(|existing| existing.sequence != snapshot.sequence);
+        while self.snapshots.len() >= history {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Drops every snapshot stored before `sequence`, which can no longer be
+    /// used as a baseline once `sequence` is.
+    fn prune_before(&mut self, sequence: u32) {
+        if self.find(sequence).is_none() {
+            return;
+        }
+        while let Some(front) = self.snapshots.front() {
+            if front.sequence == sequence {
+                break;
+            }
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+/// Per-client history of entity lists sent in each frame, used to encode the
+/// next list as a delta against the last one the client acknowledged.
+///
+/// The server calls `encode_entities` for every frame and `ack` whenever a
+/// client reports the last `sequence` it received. The client keeps its own
+/// store and calls `decode_entities` with the same sequence. If the client has
+/// not acknowledged anything yet, or the acknowledged frame has already fallen
+/// out of the history window, the full list is sent instead.
+///
+/// Entities are matched by their `id` field. Decoded lists keep the order of
+/// the baseline, with new entities appended in the order they were sent.
+pub struct SnapshotStore {
+    message: String,
+    history: usize,
+    clients: HashMap<u32, ClientHistory>,
+}
+
+impl SnapshotStore {
+    pub fn new(message: impl Into<String>, history: usize) -> Self {
+        Self {
+            message: message.into(),
+            history: history.max(1),
+            clients: HashMap::new(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn history(&self) -> usize {
+        self.history
+    }
+
+    /// Records that `client` received frame `sequence`. Acks older than the
+    /// latest one are ignored.
+    pub fn ack(&mut self, client: u32, sequence: u32) {
+        let client = self.clients.entry(client).or_default();
+        if client.acked.is_some_and(|acked| acked >= sequence) {
+            return;
+        }
+        client.acked = Some(sequence);
+        client.prune_before(sequence);
+    }
+
+    pub fn acked(&self, client: u32) -> Option<u32> {
+        self.clients.get(&client).and_then(|client| client.acked)
+    }
+
+    pub fn remove_client(&mut self, client: u32) {
+        self.clients.remove(&client);
+    }
+
+    /// Encodes `entities` for `client` and stores them as the snapshot for
+    /// `sequence`. Returns the baseline sequence the delta was computed
+    /// against, or `None` when the full list was written.
+    pub fn encode_entities(
+        &mut self,
+        codec: &Codec,
+        client: u32,
+        sequence: u32,
+        entities: &[Message],
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<Option<u32>> {
+        let schema = codec.message_schema(&self.message)?;
+        let entities = entities
+            .iter()
+            .map(|entity| flatten(schema, entity))
+            .collect::<CodecResult<Vec<_>>>()?;
+        for entity in &entities {
+            entity_id(entity)?;
+        }
+
+        let history = self.clients.entry(client).or_default();
+        let format = codec.format();
+        let baseline = history
+            .acked
+            .and_then(|acked| history.find(acked))
+            .filter(|baseline| baseline.sequence != sequence);
+        let baseline_sequence = baseline.map(|baseline| baseline.sequence);
+        match baseline {
+            Some(baseline) => {
+                buffer.push(STATE_DELTA);
+                format.write_u32(buffer, baseline.sequence);
+                write_delta(codec, schema, &baseline.entities, &entities, buffer)?;
+            }
+            None => {
+                buffer.push(STATE_FULL);
+                format.write_u32(buffer, entities.len() as u32);
+                for entity in &entities {
+                    codec.write_message(schema, entity, buffer)?;
+                }
+            }
+        }
+
+        history.push(Snapshot { sequence, entities }, self.history);
+        Ok(baseline_sequence)
+    }
+
+    /// Decodes a list written by `encode_entities` for frame `sequence`,
+    /// resolving deltas against the snapshots this store has decoded before.
+    /// Snapshots are kept flat; inline groups are only re-nested in the
+    /// returned entities.
+    pub fn decode_entities(
+        &mut self,
+        codec: &Codec,
+        client: u32,
+        sequence: u32,
+        bytes: &[u8],
+    ) -> CodecResult<Vec<Message>> {
+        let nest = codec.nests_inline();
+        let codec = &codec.with_inline_nesting(false);
+        let schema = codec.message_schema(&self.message)?;
+        let history = self.clients.entry(client).or_default();
+        let mut cursor = Cursor::with_format(bytes, codec.format());
+
+        let (entities, baseline) = match cursor.read_u8()? {
+            STATE_FULL => {
+                let count = cursor.read_u32()? as usize;
+                let mut entities = Vec::with_capacity(count.min(cursor.remaining()));
+                for _ in 0..count {
+                    entities.push(codec.read_message(schema, &mut cursor)?);
+                }
+                (entities, None)
+            }
+            STATE_DELTA => {
+                let baseline = cursor.read_u32()?;
+                let snapshot = history
+                    .find(baseline)
+                    .ok_or(CodecError::MissingBaseline(baseline))?;
+                let entities = read_delta(codec, schema, &snapshot.entities, &mut cursor)?;
+                (entities, Some(baseline))
+            }
+            other => return Err(CodecError::UnknownSnapshotState(other)),
+        };
+        if !cursor.is_empty() {
+            return Err(CodecError::TrailingBytes(cursor.remaining()));
+        }
+
+        if let Some(baseline) = baseline {
+            history.prune_before(baseline);
+        }
+        history.push(
+            Snapshot {
+                sequence,
+                entities: entities.clone(),
+            },
+            self.history,
+        );
+        if nest {
+            return Ok(entities
+                .into_iter()
+                .map(|entity| nest_inline(schema, entity))
+                .collect());
+        }
+        Ok(entities)
+    }
+}
+
+/// Delta layout: removed ids, then each new or changed entity as a message
+/// holding its id and changed fields, followed by the numbers of the fields
+/// it no longer has.
+fn write_delta(
+    codec: &Codec,
+    schema: &MessageSchema,
+    baseline: &[Message],
+    entities: &[Message],
+    buffer: &mut Vec<u8>,
+) -> CodecResult<()> {
+    let format = codec.format();
+    let mut previous = HashMap::with_capacity(baseline.len());
+    for entity in baseline {
+        previous.insert(entity_id(entity)?, entity);
+    }
+    let mut current = HashSet::with_capacity(entities.len());
+    for entity in entities {
+        current.insert(entity_id(entity)?);
+    }
+
+    let mut removed = Vec::new();
+    for entity in baseline {
+        let id = entity_id(entity)?;
+        if !current.contains(&id) {
+            removed.push(id);
+        }
+    }
+    format.write_u32(buffer, removed.len() as u32);
+    for id in removed {
+        format.write_u32(buffer, id);
+    }
+
+    let mut updates = Vec::new();
+    for entity in entities {
+        let Some(old) = previous.get(&entity_id(entity)?) else {
+            updates.push((entity.clone(), Vec::new()));
+            continue;
+        };
+        let mut changed = Message::new();
+        for (key, value) in entity.iter() {
+            if old.get(key) != Some(value) {
+                changed.insert(key, value.clone());
+            }
+        }
+        let mut cleared = old
+            .iter()
+            .filter(|(key, _)| !entity.contains(key))
+            .filter_map(|(key, _)| schema.fields_by_name.get(key))
+            .map(|field| field.number)
+            .collect::<Vec<_>>();
+        cleared.sort_unstable();
+        if changed.is_empty() && cleared.is_empty() {
+            continue;
+        }
+        if let Some(id) = entity.get(KEY_FIELD) {
+            changed.insert(KEY_FIELD, id.clone());
+        }
+        updates.push((changed, cleared));
+    }
+
+    format.write_u32(buffer, updates.len() as u32);
+    for (update, cleared) in updates {
+        codec.write_message(schema, &update, buffer)?;
+        format.write_u16(buffer, cleared.len() as u16);
+        for number in cleared {
+            format.write_u16(buffer, number);
+        }
+    }
+    Ok(())
+}
+
+fn read_delta(
+    codec: &Codec,
+    schema: &MessageSchema,
+    baseline: &[Message],
+    cursor: &mut Cursor,
+) -> CodecResult<Vec<Message>> {
+    let removed_count = cursor.read_u32()? as usize;
+    let mut removed = HashSet::with_capacity(removed_count.min(cursor.remaining()));
+    for _ in 0..removed_count {
+        removed.insert(cursor.read_u32()?);
+    }
+
+    let mut entities = Vec::with_capacity(baseline.len());
+    let mut positions = HashMap::with_capacity(baseline.len());
+    for entity in baseline {
+        let id = entity_id(entity)?;
+        if !removed.contains(&id) {
+            positions.insert(id, entities.len());
+            entities.push(entity.clone());
+        }
+    }
+
+    let update_count = cursor.read_u32()? as usize;
+    for _ in 0..update_count {
+        let update = codec.read_message(schema, cursor)?;
+        let id = entity_id(&update)?;
+        let cleared_count = cursor.read_u16()? as usize;
+        let mut cleared = Vec::with_capacity(cleared_count.min(cursor.remaining()));
+        for _ in 0..cleared_count {
+            let number = cursor.read_u16()?;
+            let field = schema
+                .fields_by_number
+                .get(&number)
+                .ok_or(CodecError::UnknownFieldNumber(number))?;
+            cleared.push(field.name.as_str());
+        }
+
+        match positions.get(&id) {
+            Some(&index) => {
+                let entity = &mut entities[index];
+                for (key, value) in update {
+                    entity.insert(key, value);
+                }
+                for name in cleared {
+                    entity.remove(name);
+                }
+            }
+            None => {
+                positions.insert(id, entities.len());
+                entities.push(update);
+            }
+        }
+    }
+    Ok(entities)
+}
+
+/// Resolves inline groups into top-level fields and drops keys the schema
+/// does not know, so snapshots compare the same way they are encoded.
+fn flatten(schema: &MessageSchema, entity: &Message) -> CodecResult<Message> {
+    Ok(inline_entries(schema, entity)?
+        .into_iter()
+        .map(|(field, value)| (field.name.as_str(), value.clone()))
+        .collect())
+}
+
+fn entity_id(entity: &Message) -> CodecResult<u32> {
+    entity
+        .get(KEY_FIELD)
+        .and_then(Value::as_u32)
+        .ok_or_else(|| CodecError::invalid_value(KEY_FIELD, "uint32"))
+}
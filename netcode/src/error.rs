@@ -0,0 +1,91 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    UnexpectedEnd,
+    InvalidUtf8,
+    InvalidVarint,
+    UnknownMessage(String),
+    UnknownFieldNumber(u16),
+    InvalidPresenceBitmap,
+    MissingMessageType(String),
+    UnsupportedFrameVersion(u8),
+    TrailingBytes(usize),
+    MissingBaseline(u32),
+    UnknownSnapshotState(u8),
+    OneofConflict(String),
+    InvalidFieldKey(u32),
+    WireTypeMismatch(String),
+    UnknownEntity(u32),
+    SharedFrameVersion(u8),
+    CompressedFrame,
+    FrameTooLarge(u64),
+    Compression(String),
+    InvalidFragment(u32),
+    InvalidValue {
+        field: String,
+        expected: &'static str,
+    },
+}
+
+pub type CodecResult<T> = Result<T, CodecError>;
+
+impl CodecError {
+    pub fn invalid_value(field: &str, expected: &'static str) -> Self {
+        CodecError::InvalidValue {
+            field: field.to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::UnexpectedEnd => write!(f, "Unexpected end of buffer"),
+            CodecError::InvalidUtf8 => write!(f, "Invalid UTF-8 string"),
+            CodecError::InvalidVarint => write!(f, "Invalid varint"),
+            CodecError::UnknownMessage(name) => write!(f, "Unknown message schema: {name}"),
+            CodecError::UnknownFieldNumber(number) => write!(f, "Unknown field number: {number}"),
+            CodecError::InvalidPresenceBitmap => write!(f, "Invalid presence bitmap"),
+            CodecError::MissingMessageType(field) => write!(f, "Missing message type for {field}"),
+            CodecError::UnsupportedFrameVersion(version) => {
+                write!(f, "Unsupported frame version: {version}")
+            }
+            CodecError::TrailingBytes(count) => write!(f, "Unexpected trailing bytes: {count}"),
+            CodecError::MissingBaseline(sequence) => {
+                write!(f, "Missing baseline snapshot: {sequence}")
+            }
+            CodecError::UnknownSnapshotState(state) => {
+                write!(f, "Unknown snapshot state: {state}")
+            }
+            CodecError::OneofConflict(oneof) => {
+                write!(f, "More than one field set for oneof {oneof}")
+            }
+            CodecError::InvalidFieldKey(key) => write!(f, "Invalid field key: {key}"),
+            CodecError::WireTypeMismatch(field) => {
+                write!(f, "Unexpected wire type for {field}")
+            }
+            CodecError::UnknownEntity(id) => write!(f, "No previous position for entity {id}"),
+            CodecError::SharedFrameVersion(version) => {
+                write!(
+                    f,
+                    "Shared sections were encoded for frame version {version}"
+                )
+            }
+            CodecError::CompressedFrame => write!(f, "Frame is already compressed"),
+            CodecError::FrameTooLarge(len) => {
+                write!(f, "Frame of {len} bytes exceeds the size limit")
+            }
+            CodecError::Compression(error) => write!(f, "Compression failed: {error}"),
+            CodecError::InvalidFragment(sequence) => {
+                write!(f, "Invalid fragment header for frame {sequence}")
+            }
+            CodecError::InvalidValue { field, expected } => {
+                write!(f, "Expected {expected} for {field}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use crate::error::{CodecError, CodecResult};
+use crate::wire::{write_u16, write_u32, Cursor};
+
+/// Bytes at the start of every fragment: the frame sequence as a `u32`,
+/// then the fragment index and the fragment count as `u16`s, little-endian.
+pub const FRAGMENT_HEADER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FragmentConfig {
+    /// The largest packet a `Fragmenter` produces, header included. A
+    /// `Reassembler` rejects frames split into more fragments than a
+    /// `max_frame_size` frame takes at this size, so it must not be larger
+    /// than the sender's.
+    pub max_packet_size: usize,
+    /// Seconds a partly received frame is kept before `expire` drops it.
+    pub timeout: f64,
+    /// The largest frame a `Reassembler` buffers.
+    pub max_frame_size: usize,
+    /// The most partly received frames a `Reassembler` keeps, and the most
+    /// returned frames it remembers; the oldest are dropped first. With
+    /// `max_frame_size` this bounds what a peer can make it hold.
+    pub max_frames: usize,
+}
+
+impl Default for FragmentConfig {
+    fn default() -> Self {
+        Self {
+            max_packet_size: 1200,
+            timeout: 1.0,
+            max_frame_size: 1 << 20,
+            max_frames: 64,
+        }
+    }
+}
+
+/// Splits frames into packets of at most `max_packet_size` bytes for
+/// transports with an MTU. Each frame takes the next sequence number,
+/// wrapping at `u32::MAX`; frames that fit in one packet are still sent as
+/// a single fragment so the receiver handles every packet alike.
+#[derive(Debug, Clone, Default)]
+pub struct Fragmenter {
+    config: FragmentConfig,
+    next_sequence: u32,
+}
+
+impl Fragmenter {
+    pub fn new(config: FragmentConfig) -> Self {
+        Self {
+            config,
+            next_sequence: 0,
+        }
+    }
+
+    pub fn config(&self) -> FragmentConfig {
+        self.config
+    }
+
+    pub fn split(&mut self, frame: &[u8]) -> CodecResult<Vec<Vec<u8>>> {
+        let payload_len = self
+            .config
+            .max_packet_size
+            .checked_sub(FRAGMENT_HEADER_LEN)
+            .filter(|&len| len > 0)
+            .ok_or_else(|| {
+                CodecError::invalid_value("max_packet_size", "a size above the fragment header")
+            })?;
+        let count = u16::try_from(frame.len().div_ceil(payload_len).max(1))
+            .map_err(|_| CodecError::FrameTooLarge(frame.len() as u64))?;
+        let sequence = self.next_sequence;
+        self.next_sequence = sequence.wrapping_add(1);
+
+        let mut packets = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            let start = index as usize * payload_len;
+            let payload = &frame[start..(start + payload_len).min(frame.len())];
+            let mut packet = Vec::with_capacity(FRAGMENT_HEADER_LEN + payload.len());
+            write_u32(&mut packet, sequence);
+            write_u16(&mut packet, index);
+            write_u16(&mut packet, count);
+            packet.extend_from_slice(payload);
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+}
+
+#[derive(Debug)]
+struct PendingFrame {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: u16,
+    len: usize,
+    started: f64,
+}
+
+/// Rebuilds frames from the packets of a `Fragmenter` in whatever order
+/// they arrive. Repeated fragments are ignored, including those of the last
+/// `max_frames` frames returned until `expire` forgets them `timeout` after
+/// they completed; `expire` also drops frames still missing fragments after
+/// `timeout`.
+///
+/// Times are in seconds, on any clock that only moves forward.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    config: FragmentConfig,
+    pending: HashMap<u32, PendingFrame>,
+    /// When each recently returned frame was completed.
+    completed: HashMap<u32, f64>,
+}
+
+impl Reassembler {
+    pub fn new(config: FragmentConfig) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+            completed: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> FragmentConfig {
+        self.config
+    }
+
+    /// The number of frames waiting for fragments.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds a packet received at `now`, returning its frame once every
+    /// fragment has arrived.
+    pub fn insert(&mut self, packet: &[u8], now: f64) -> CodecResult<Option<Vec<u8>>> {
+        let mut cursor = Cursor::new(packet);
+        let sequence = cursor.read_u32()?;
+        let index = cursor.read_u16()?;
+        let count = cursor.read_u16()?;
+        let payload = cursor.read_slice(cursor.remaining())?;
+        if index >= count || count as usize > self.max_fragments() {
+            return Err(CodecError::InvalidFragment(sequence));
+        }
+        if self.completed.contains_key(&sequence) {
+            return Ok(None);
+        }
+        if !self.pending.contains_key(&sequence) && self.pending.len() >= self.config.max_frames {
+            evict_oldest(&mut self.pending, |frame| frame.started);
+        }
+
+        let mut frame = self
+            .pending
+            .remove(&sequence)
+            .unwrap_or_else(|| PendingFrame {
+                fragments: vec![None; count as usize],
+                received: 0,
+                len: 0,
+                started: now,
+            });
+        if frame.fragments.len() != count as usize {
+            self.pending.insert(sequence, frame);
+            return Err(CodecError::InvalidFragment(sequence));
+        }
+        let fragment = &mut frame.fragments[index as usize];
+        if fragment.is_none() {
+            frame.len += payload.len();
+            if frame.len > self.config.max_frame_size {
+                // The frame is dropped along with its fragments.
+                return Err(CodecError::FrameTooLarge(frame.len as u64));
+            }
+            *fragment = Some(payload.to_vec());
+            frame.received += 1;
+        }
+        if frame.received < count {
+            self.pending.insert(sequence, frame);
+            return Ok(None);
+        }
+
+        if self.completed.len() >= self.config.max_frames {
+            evict_oldest(&mut self.completed, |completed| *completed);
+        }
+        self.completed.insert(sequence, now);
+        let mut bytes = Vec::with_capacity(frame.len);
+        for fragment in frame.fragments.into_iter().flatten() {
+            bytes.extend_from_slice(&fragment);
+        }
+        Ok(Some(bytes))
+    }
+
+    /// Drops frames whose first fragment arrived more than `timeout` seconds
+    /// before `now`, returning how many were dropped, and forgets frames
+    /// completed before then.
+    pub fn expire(&mut self, now: f64) -> usize {
+        let timeout = self.config.timeout;
+        let pending = self.pending.len();
+        self.pending
+            .retain(|_, frame| now - frame.started <= timeout);
+        self.completed
+            .retain(|_, completed| now - *completed <= timeout);
+        pending - self.pending.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.completed.clear();
+    }
+
+    /// The fragments a `max_frame_size` frame takes at `max_packet_size`.
+    fn max_fragments(&self) -> usize {
+        let payload_len = self
+            .config
+            .max_packet_size
+            .saturating_sub(FRAGMENT_HEADER_LEN)
+            .max(1);
+        self.config.max_frame_size.div_ceil(payload_len).max(1)
+    }
+}
+
+fn evict_oldest<T>(frames: &mut HashMap<u32, T>, time: impl Fn(&T) -> f64) {
+    let oldest = frames
+        .iter()
+        .min_by(|a, b| time(a.1).total_cmp(&time(b.1)))
+        .map(|(&sequence, _)| sequence);
+    if let Some(sequence) = oldest {
+        frames.remove(&sequence);
+    }
+}
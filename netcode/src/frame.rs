@@ -0,0 +1,205 @@
+use serde::Deserialize;
+
+use crate::validate::SchemaError;
+use crate::wire::WireFormat;
+
+pub const FRAME_VERSION: u8 = 1;
+pub const FRAME_VERSION_COMPACT: u8 = 2;
+/// Set in the version byte of frames compressed by `FrameCompressor`, so
+/// schemas cannot declare versions above 127.
+pub const COMPRESSED_FRAME_BIT: u8 = 0x80;
+
+/// The frame layout used by schemas without a `frame` entry.
+const DEFAULT_FRAME_JSON: &str = r#"{
+    "version": 1,
+    "compact_version": 2,
+    "sections": [
+        {"name": "sequence", "type": "uint32", "label": "required"},
+        {"name": "complete", "type": "flag"},
+        {"name": "complete_global", "type": "flag"},
+        {"name": "reset", "type": "flag"},
+        {"name": "self_id", "type": "uint32"},
+        {"name": "tick_rate", "type": "float"},
+        {"name": "pong", "type": "uint32"},
+        {"name": "area", "type": "message", "type_name": "Area"},
+        {"name": "map", "type": "message", "type_name": "Map"},
+        {"name": "chat", "type": "message", "type_name": "Chat"},
+        {"name": "settings", "type": "message", "type_name": "Settings"},
+        {"name": "mod_tools_response", "type": "message", "type_name": "ModToolsResponse"},
+        {"name": "quest_data", "type": "message", "type_name": "QuestData"},
+        {"name": "x_entities", "type": "bytes"},
+        {"name": "y_entities", "type": "bytes"},
+        {"name": "xy_entities", "type": "bytes"},
+        {"name": "xy_radius_entities", "type": "bytes"},
+        {"name": "entities", "type": "message", "type_name": "Entity", "label": "repeated"},
+        {"name": "global_entities", "type": "message", "type_name": "Entity", "label": "repeated"},
+        {"name": "debug_objects", "type": "message", "type_name": "ServerDebugObject", "label": "repeated"}
+    ]
+}"#;
+
+/// The input layout used by schemas without an `input` entry: the client
+/// tick the input is for, the newest server frame sequence received, the
+/// movement vector, pressed abilities as a bitmask and chat.
+const DEFAULT_INPUT_JSON: &str = r#"{
+    "version": 1,
+    "compact_version": 2,
+    "sections": [
+        {"name": "tick", "type": "uint32", "label": "required"},
+        {"name": "ack", "type": "uint32"},
+        {"name": "move_x", "type": "float"},
+        {"name": "move_y", "type": "float"},
+        {"name": "abilities", "type": "uint32"},
+        {"name": "chat", "type": "message", "type_name": "Chat"}
+    ]
+}"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    /// A presence bit with no payload, decoded as a bool.
+    Flag,
+    UInt32,
+    Float,
+    /// A length-prefixed message, or a count followed by messages when
+    /// repeated.
+    Message,
+    Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSection {
+    pub name: String,
+    pub kind: SectionKind,
+    pub type_name: Option<String>,
+    pub is_repeated: bool,
+    /// Presence bit of flags and optional sections; other sections are
+    /// always written.
+    pub bit: Option<u8>,
+}
+
+/// The frame envelope: a version byte, presence bits for flags and optional
+/// sections, then every written section in declaration order.
+///
+/// Presence bits are packed little-endian into as many bytes as the highest
+/// bit needs. Sections without an explicit `bit` take the one after the
+/// previous section's bit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSchema {
+    pub version: u8,
+    pub compact_version: u8,
+    pub sections: Vec<FrameSection>,
+}
+
+impl FrameSchema {
+    pub fn section(&self, name: &str) -> Option<&FrameSection> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    pub fn presence_len(&self) -> usize {
+        self.sections
+            .iter()
+            .filter_map(|section| section.bit)
+            .max()
+            .map_or(0, |bit| bit as usize / 8 + 1)
+    }
+
+    pub fn version(&self, format: WireFormat) -> u8 {
+        match format {
+            WireFormat::Fixed => self.version,
+            WireFormat::Compact => self.compact_version,
+        }
+    }
+
+    pub fn format(&self, version: u8) -> Option<WireFormat> {
+        if version == self.version {
+            Some(WireFormat::Fixed)
+        } else if version == self.compact_version {
+            Some(WireFormat::Compact)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for FrameSchema {
+    fn default() -> Self {
+        let raw = serde_json::from_str(DEFAULT_FRAME_JSON).expect("Invalid default frame JSON");
+        build_frame(raw).expect("Invalid default frame layout")
+    }
+}
+
+pub(crate) fn default_input_layout() -> FrameSchema {
+    let raw = serde_json::from_str(DEFAULT_INPUT_JSON).expect("Invalid default input JSON");
+    build_frame(raw).expect("Invalid default input layout")
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawFrameSchema {
+    version: u8,
+    compact_version: u8,
+    sections: Vec<RawFrameSection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawFrameSection {
+    name: String,
+    #[serde(rename = "type")]
+    section_type: String,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    type_name: Option<String>,
+    #[serde(default)]
+    bit: Option<u8>,
+}
+
+pub(crate) fn build_frame(raw: RawFrameSchema) -> Result<FrameSchema, SchemaError> {
+    let mut sections = Vec::with_capacity(raw.sections.len());
+    let mut next_bit = 0u16;
+    for section in raw.sections {
+        let invalid = |reason: &str| SchemaError::InvalidFrameSection {
+            section: section.name.clone(),
+            reason: reason.to_string(),
+        };
+        let kind = match section.section_type.as_str() {
+            "flag" => SectionKind::Flag,
+            "uint32" => SectionKind::UInt32,
+            "float" => SectionKind::Float,
+            "message" => SectionKind::Message,
+            "bytes" => SectionKind::Bytes,
+            other => return Err(invalid(&format!("unsupported section type: {other}"))),
+        };
+        let (is_repeated, is_optional) = match section.label.as_deref() {
+            None | Some("optional") => (false, true),
+            Some("required") if kind != SectionKind::Flag => (false, false),
+            Some("repeated") if kind == SectionKind::Message => (true, false),
+            Some(label) => {
+                return Err(invalid(&format!(
+                    "{label} is not supported for {} sections",
+                    section.section_type
+                )))
+            }
+        };
+        let bit = if is_optional {
+            let bit = section.bit.map_or(next_bit, u16::from);
+            let bit = u8::try_from(bit).map_err(|_| invalid("ran out of presence bits"))?;
+            next_bit = bit as u16 + 1;
+            Some(bit)
+        } else if section.bit.is_some() {
+            return Err(invalid("only flags and optional sections have a bit"));
+        } else {
+            None
+        };
+        sections.push(FrameSection {
+            name: section.name,
+            kind,
+            type_name: section.type_name,
+            is_repeated,
+            bit,
+        });
+    }
+    Ok(FrameSchema {
+        version: raw.version,
+        compact_version: raw.compact_version,
+        sections,
+    })
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputConfig {
+    /// The most inputs buffered at once; a client further ahead than this
+    /// has its inputs dropped.
+    pub capacity: usize,
+    /// Ticks of input the server wants buffered past the tick it consumes,
+    /// to absorb jitter.
+    pub target_lead: u32,
+    /// How many ticks the lead may drift from `target_lead` before the
+    /// client is reported ahead or behind.
+    pub tolerance: u32,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 32,
+            target_lead: 2,
+            tolerance: 1,
+        }
+    }
+}
+
+/// What `InputBuffer::insert` did with an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputStatus {
+    Buffered,
+    /// The input is for a tick the server already consumed.
+    Stale,
+    /// An input for the same tick is already buffered.
+    Duplicate,
+    /// The buffer holds `capacity` inputs.
+    Full,
+}
+
+/// How far a client's inputs run ahead of the server, relative to
+/// `InputConfig::target_lead`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputTiming {
+    OnTime,
+    /// The client should slow down by this many ticks.
+    Ahead(u32),
+    /// The client should speed up by this many ticks.
+    Behind(u32),
+}
+
+/// Server-side buffer of one client's inputs, keyed by the client tick they
+/// were sampled on.
+///
+/// Inputs may arrive out of order or more than once, as clients usually
+/// resend recent inputs in every packet. The server consumes one input per
+/// simulated tick with `pop`; inputs for ticks already consumed are stale,
+/// and `timing` compares the newest input received with the server's tick
+/// so the client can be told to speed up or slow down.
+#[derive(Debug, Default)]
+pub struct InputBuffer {
+    config: InputConfig,
+    inputs: BTreeMap<u32, Message>,
+    /// The newest tick consumed by `pop`.
+    consumed: Option<u32>,
+    /// The newest tick buffered.
+    latest: Option<u32>,
+}
+
+impl InputBuffer {
+    pub fn new(config: InputConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> InputConfig {
+        self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn latest_tick(&self) -> Option<u32> {
+        self.latest
+    }
+
+    pub fn consumed_tick(&self) -> Option<u32> {
+        self.consumed
+    }
+
+    pub fn clear(&mut self) {
+        self.inputs.clear();
+        self.consumed = None;
+        self.latest = None;
+    }
+
+    pub fn insert(&mut self, tick: u32, input: Message) -> InputStatus {
+        if self.consumed.is_some_and(|consumed| tick <= consumed) {
+            return InputStatus::Stale;
+        }
+        if self.inputs.contains_key(&tick) {
+            return InputStatus::Duplicate;
+        }
+        if self.inputs.len() >= self.config.capacity {
+            return InputStatus::Full;
+        }
+        self.inputs.insert(tick, input);
+        self.latest = Some(self.latest.map_or(tick, |latest| latest.max(tick)));
+        InputStatus::Buffered
+    }
+
+    /// Inserts an input in the shape returned by `Codec::decode_input`,
+    /// keyed by its `tick` section.
+    pub fn push(&mut self, input: Message) -> CodecResult<InputStatus> {
+        let tick = input
+            .get("tick")
+            .and_then(Value::as_u32)
+            .ok_or_else(|| CodecError::invalid_value("tick", "uint32"))?;
+        Ok(self.insert(tick, input))
+    }
+
+    /// Consumes the input for `tick`, dropping older ones that were never
+    /// consumed. Returns None if it has not arrived; it is stale if it does
+    /// later.
+    pub fn pop(&mut self, tick: u32) -> Option<Message> {
+        let input = self.inputs.remove(&tick);
+        self.inputs.retain(|&buffered, _| buffered > tick);
+        self.consumed = Some(self.consumed.map_or(tick, |consumed| consumed.max(tick)));
+        input
+    }
+
+    /// Compares the newest input received with server tick `tick`, or None
+    /// before any input arrived. Gaps beyond `u32::MAX` ticks saturate.
+    pub fn timing(&self, tick: u32) -> Option<InputTiming> {
+        let lead = i64::from(self.latest?) - i64::from(tick);
+        let target = i64::from(self.config.target_lead);
+        let tolerance = i64::from(self.config.tolerance);
+        let ticks = |gap: i64| u32::try_from(gap).unwrap_or(u32::MAX);
+        Some(if lead > target + tolerance {
+            InputTiming::Ahead(ticks(lead - target))
+        } else if lead < target - tolerance {
+            InputTiming::Behind(ticks(target - lead))
+        } else {
+            InputTiming::OnTime
+        })
+    }
+}
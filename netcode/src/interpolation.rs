@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+
+const ENTITY_LISTS: [&str; 2] = ["entities", "global_entities"];
+
+/// The smoothed parts of an `Entity`, as sampled from a `SnapshotBuffer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityState {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub radius: Option<f32>,
+}
+
+impl EntityState {
+    /// Reads an entity dict as produced by `Codec::decode_frame`; entities
+    /// without an id or a position are skipped.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        Some(Self {
+            id: message.get("id")?.as_u32()?,
+            x: message.get("x")?.as_f32()?,
+            y: message.get("y")?.as_f32()?,
+            radius: message.get("radius").and_then(Value::as_f32),
+        })
+    }
+
+    fn lerp(&self, next: &EntityState, alpha: f32) -> EntityState {
+        let radius = match (self.radius, next.radius) {
+            (Some(from), Some(to)) => Some(from + (to - from) * alpha),
+            (from, to) => to.or(from),
+        };
+        EntityState {
+            id: self.id,
+            x: self.x + (next.x - self.x) * alpha,
+            y: self.y + (next.y - self.y) * alpha,
+            radius,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferConfig {
+    /// Tick rate assumed until a frame carries `tick_rate`; must be positive.
+    pub tick_rate: f32,
+    /// Seconds of frames kept behind the newest one; `render_time` trails the
+    /// newest frame by this much. Must not be negative.
+    pub jitter_window: f64,
+    /// How far past the newest frame positions are extrapolated, in seconds;
+    /// must not be negative.
+    pub max_extrapolation: f64,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: 20.0,
+            jitter_window: 0.1,
+            max_extrapolation: 0.25,
+        }
+    }
+}
+
+struct BufferedFrame {
+    sequence: u32,
+    time: f64,
+    // Sorted by id.
+    entities: Vec<EntityState>,
+}
+
+impl BufferedFrame {
+    fn entity(&self, id: u32) -> Option<&EntityState> {
+        self.entities
+            .binary_search_by_key(&id, |entity| entity.id)
+            .ok()
+            .map(|index| &self.entities[index])
+    }
+}
+
+/// Buffers decoded frames on a timeline derived from `sequence` and
+/// `tick_rate` and samples entity positions at arbitrary times.
+///
+/// Times are in seconds. Sampling between two frames interpolates entities
+/// present in both; sampling past the newest frame extrapolates from the
+/// last two frames for at most `max_extrapolation` seconds. Duplicate frames
+/// and frames older than the oldest kept one are dropped; a frame with `reset`
+/// set clears the buffer.
+pub struct SnapshotBuffer {
+    config: BufferConfig,
+    tick_rate: f32,
+    frames: VecDeque<BufferedFrame>,
+}
+
+impl SnapshotBuffer {
+    pub fn new(config: BufferConfig) -> CodecResult<Self> {
+        if !(config.tick_rate > 0.0 && config.tick_rate.is_finite()) {
+            return Err(CodecError::invalid_value(
+                "tick_rate",
+                "a positive tick rate",
+            ));
+        }
+        for (name, seconds) in [
+            ("jitter_window", config.jitter_window),
+            ("max_extrapolation", config.max_extrapolation),
+        ] {
+            if !(seconds >= 0.0 && seconds.is_finite()) {
+                return Err(CodecError::invalid_value(name, "a non-negative duration"));
+            }
+        }
+        Ok(Self {
+            tick_rate: config.tick_rate,
+            config,
+            frames: VecDeque::new(),
+        })
+    }
+
+    pub fn config(&self) -> &BufferConfig {
+        &self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn latest_sequence(&self) -> Option<u32> {
+        self.frames.back().map(|frame| frame.sequence)
+    }
+
+    pub fn latest_time(&self) -> Option<f64> {
+        self.frames.back().map(|frame| frame.time)
+    }
+
+    /// The time to render at when no local clock is available: the newest
+    /// frame delayed by the jitter window.
+    pub fn render_time(&self) -> Option<f64> {
+        self.latest_time()
+            .map(|time| time - self.config.jitter_window)
+    }
+
+    /// Ingests a frame in the shape returned by `Codec::decode_frame`.
+    /// Returns false when the frame was dropped.
+    pub fn push_frame(&mut self, frame: &Message) -> bool {
+        let sequence = frame.get("sequence").and_then(Value::as_u32).unwrap_or(0);
+        let tick_rate = frame.get("tick_rate").and_then(Value::as_f32);
+        let reset = frame.get("reset").and_then(Value::as_bool).unwrap_or(false);
+        let entities = ENTITY_LISTS
+            .into_iter()
+            .filter_map(|key| frame.get(key).and_then(Value::as_list))
+            .flatten()
+            .filter_map(Value::as_message)
+            .filter_map(EntityState::from_message)
+            .collect();
+        self.push(sequence, tick_rate, reset, entities)
+    }
+
+    pub fn push(
+        &mut self,
+        sequence: u32,
+        tick_rate: Option<f32>,
+        reset: bool,
+        mut entities: Vec<EntityState>,
+    ) -> bool {
+        if reset {
+            self.frames.clear();
+        }
+        if let Some(tick_rate) = tick_rate.filter(|rate| *rate > 0.0 && rate.is_finite()) {
+            self.tick_rate = tick_rate;
+        }
+
+        let time = match self.frames.back() {
+            Some(latest) => {
+                let ticks = sequence as i64 - latest.sequence as i64;
+                latest.time + ticks as f64 / self.tick_rate as f64
+            }
+            None => sequence as f64 / self.tick_rate as f64,
+        };
+        if self.frames.front().is_some_and(|oldest| time < oldest.time)
+            || self.frames.iter().any(|frame| frame.sequence == sequence)
+        {
+            return false;
+        }
+
+        entities.sort_by_key(|entity| entity.id);
+        entities.dedup_by_key(|entity| entity.id);
+        let index = self.frames.partition_point(|frame| frame.time < time);
+        self.frames.insert(
+            index,
+            BufferedFrame {
+                sequence,
+                time,
+                entities,
+            },
+        );
+        self.prune();
+        true
+    }
+
+    /// Returns entity states at `time`, clamped to the oldest buffered frame.
+    pub fn sample(&self, time: f64) -> Vec<EntityState> {
+        let (Some(oldest), Some(newest)) = (self.frames.front(), self.frames.back()) else {
+            return Vec::new();
+        };
+        if time >= newest.time {
+            return self.extrapolate(time);
+        }
+        if time <= oldest.time {
+            return oldest.entities.clone();
+        }
+
+        let index = self.frames.partition_point(|frame| frame.time <= time);
+        let from = &self.frames[index - 1];
+        let to = &self.frames[index];
+        let alpha = ((time - from.time) / (to.time - from.time)) as f32;
+        from.entities
+            .iter()
+            .map(|entity| match to.entity(entity.id) {
+                Some(next) => entity.lerp(next, alpha),
+                None => *entity,
+            })
+            .collect()
+    }
+
+    fn extrapolate(&self, time: f64) -> Vec<EntityState> {
+        let newest = &self.frames[self.frames.len() - 1];
+        let previous = match self.frames.len() {
+            0 | 1 => None,
+            len => Some(&self.frames[len - 2]),
+        };
+        let elapsed = (time - newest.time).min(self.config.max_extrapolation);
+        newest
+            .entities
+            .iter()
+            .map(|entity| {
+                let Some(previous) = previous else {
+                    return *entity;
+                };
+                let Some(before) = previous.entity(entity.id) else {
+                    return *entity;
+                };
+                let scale = (elapsed / (newest.time - previous.time)) as f32;
+                EntityState {
+                    x: entity.x + (entity.x - before.x) * scale,
+                    y: entity.y + (entity.y - before.y) * scale,
+                    ..*entity
+                }
+            })
+            .collect()
+    }
+
+    /// Keeps one frame at or before the start of the jitter window so render
+    /// times inside the window always have a frame on each side.
+    fn prune(&mut self) {
+        let Some(newest) = self.frames.back() else {
+            return;
+        };
+        let cutoff = newest.time - self.config.jitter_window;
+        while self.frames.len() > 1 && self.frames[1].time <= cutoff {
+            self.frames.pop_front();
+        }
+    }
+}
@@ -1,17 +1,89 @@
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+mod capture;
+mod channel;
+mod codec;
+#[cfg(feature = "compression")]
+mod compression;
+mod delta;
+mod error;
+mod fragment;
+mod frame;
+mod input;
+mod interpolation;
+mod positions;
+mod quantize;
+mod scheduler;
+mod validate;
+mod value;
+mod view;
+pub mod wire;
+mod writer;
+
+use frame::{build_frame, default_input_layout, RawFrameSchema};
+
+pub use capture::{read_capture, write_capture_frame};
+pub use channel::{Channel, ChannelConfig, Received, ReceivedFrame, CHANNEL_HEADER_LEN};
+pub use codec::{wire_type, Codec, NetMessage};
+#[cfg(feature = "compression")]
+pub use compression::{train_dictionary, CompressionConfig, FrameCompressor};
+pub use delta::SnapshotStore;
+pub use error::{CodecError, CodecResult};
+pub use fragment::{FragmentConfig, Fragmenter, Reassembler, FRAGMENT_HEADER_LEN};
+pub use frame::{
+    FrameSchema, FrameSection, SectionKind, COMPRESSED_FRAME_BIT, FRAME_VERSION,
+    FRAME_VERSION_COMPACT,
+};
+pub use input::{InputBuffer, InputConfig, InputStatus, InputTiming};
+pub use interpolation::{BufferConfig, EntityState, SnapshotBuffer};
+pub use positions::{
+    PositionConfig, PositionDecoder, PositionEncoder, PositionStream, PositionStreams,
+    PositionUpdate,
+};
+pub use quantize::Quantization;
+pub use scheduler::{EntityScheduler, SchedulerConfig, Selection, ViewRect};
+pub use validate::{compare, validate_json, BreakingChange, SchemaError};
+pub use value::{Message, Value};
+pub use view::{FrameRef, MessageRef, ValueRef};
+pub use wire::{WireFormat, WireType};
+pub use writer::FrameWriter;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FieldKind {
     Int32,
     UInt32,
+    Int64,
+    UInt64,
     Float,
+    Double,
     Bool,
     String,
     Bytes,
     Enum,
     Message,
+    Map,
+}
+
+/// How a message's fields are framed on the wire.
+///
+/// `Tagged` writes a field count and a field number before every present
+/// field; readers reject field numbers their schema does not have, so
+/// messages that gain fields without a new protocol version should be
+/// `Typed`. `Bitmap` writes one presence bit per schema field, in declaration
+/// order, followed by the present values; it suits messages with many
+/// optional fields. `Typed` is `Tagged` with a `WireType` packed into each
+/// field key and nested messages, maps, lists and quantized floats
+/// length-prefixed, so readers skip fields their schema does not have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageLayout {
+    #[default]
+    Tagged,
+    Bitmap,
+    Typed,
 }
 
 #[derive(Debug, Clone)]
@@ -21,20 +93,89 @@ pub struct FieldSchema {
     pub kind: FieldKind,
     pub is_repeated: bool,
     pub type_name: Option<String>,
+    pub quantization: Option<Quantization>,
+    pub map: Option<Box<MapSchema>>,
+    pub oneof: Option<String>,
+    /// The protocol version that introduced the field; older protocols do
+    /// not encode it.
+    pub since: Option<u8>,
+}
+
+impl FieldSchema {
+    pub fn in_protocol(&self, version: u8) -> bool {
+        self.since.is_none_or(|since| since <= version)
+    }
+}
+
+/// The key and value of a `FieldKind::Map` field. Both are described as
+/// singular fields named after the map so values are checked and reported
+/// the same way as any other field.
+#[derive(Debug, Clone)]
+pub struct MapSchema {
+    pub key: FieldSchema,
+    pub value: FieldSchema,
+}
+
+/// A nested dict whose entries are encoded as fields of the parent message,
+/// like "hero" on entities. `fields` limits which fields the group may hold;
+/// a group without a field list accepts any field and is never re-nested on
+/// decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineGroup {
+    pub name: String,
+    pub fields: Option<Vec<String>>,
+}
+
+impl InlineGroup {
+    pub fn accepts(&self, field: &str) -> bool {
+        self.fields
+            .as_ref()
+            .is_none_or(|fields| fields.iter().any(|name| name == field))
+    }
+
+    /// Whether decoding with inline nesting moves `field` under the group.
+    pub fn nests(&self, field: &str) -> bool {
+        self.fields
+            .as_ref()
+            .is_some_and(|fields| fields.iter().any(|name| name == field))
+    }
+}
+
+/// A group of fields of which at most one may be set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OneofSchema {
+    pub name: String,
+    pub fields: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MessageSchema {
     pub name: String,
+    pub layout: MessageLayout,
     pub fields: Vec<FieldSchema>,
     pub fields_by_name: HashMap<String, FieldSchema>,
     pub fields_by_number: HashMap<u16, FieldSchema>,
+    pub oneofs: Vec<OneofSchema>,
+    pub inline_groups: Vec<InlineGroup>,
+}
+
+impl MessageSchema {
+    pub fn inline_group(&self, name: &str) -> Option<&InlineGroup> {
+        self.inline_groups.iter().find(|group| group.name == name)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct NetSchema {
     pub messages: HashMap<String, MessageSchema>,
     pub profiles: HashMap<String, ProfileSchema>,
+    /// The declared frame layout, if any; see `frame_layout`.
+    pub frame: Option<FrameSchema>,
+    /// Layouts of older protocol versions that can still be spoken.
+    pub legacy_frames: Vec<FrameSchema>,
+    /// The declared layout of client input frames, if any; see
+    /// `input_layout`.
+    pub input: Option<FrameSchema>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,12 +183,29 @@ struct RawSchema {
     messages: HashMap<String, RawMessageSchema>,
     #[serde(default)]
     profiles: HashMap<String, RawProfileSchema>,
+    #[serde(default)]
+    frame: Option<RawFrameSchema>,
+    #[serde(default)]
+    legacy_frames: Vec<RawFrameSchema>,
+    #[serde(default)]
+    input: Option<RawFrameSchema>,
 }
 
 #[derive(Debug, Deserialize)]
 struct RawMessageSchema {
     name: String,
+    #[serde(default)]
+    layout: Option<String>,
     fields: Vec<RawFieldSchema>,
+    #[serde(default)]
+    inline: Option<Vec<RawInlineGroup>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawInlineGroup {
+    name: String,
+    #[serde(default)]
+    fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -59,6 +217,23 @@ struct RawFieldSchema {
     label: String,
     #[serde(default)]
     type_name: Option<String>,
+    #[serde(default)]
+    quantize: Option<RawQuantization>,
+    #[serde(default)]
+    key_type: Option<String>,
+    #[serde(default)]
+    value_type: Option<String>,
+    #[serde(default)]
+    oneof: Option<String>,
+    #[serde(default)]
+    since: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawQuantization {
+    Range { min: f32, max: f32, bits: u8 },
+    FixedPoint { scale: f32, bits: u8 },
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,57 +249,234 @@ pub struct ProfileSchema {
     pub fields: Vec<String>,
 }
 
-fn parse_kind(field_type: &str) -> FieldKind {
+const DEFAULT_INLINE_GROUP: &str = "hero";
+
+fn parse_kind(field_type: &str) -> Option<FieldKind> {
     match field_type {
-        "int32" | "sint32" | "sfixed32" => FieldKind::Int32,
-        "uint32" | "fixed32" => FieldKind::UInt32,
-        "float" => FieldKind::Float,
-        "bool" => FieldKind::Bool,
-        "string" => FieldKind::String,
-        "bytes" => FieldKind::Bytes,
-        "enum" => FieldKind::Enum,
-        "message" => FieldKind::Message,
-        other => panic!("Unsupported field type: {other}"),
+        "int32" | "sint32" | "sfixed32" => Some(FieldKind::Int32),
+        "uint32" | "fixed32" => Some(FieldKind::UInt32),
+        "int64" | "sint64" | "sfixed64" => Some(FieldKind::Int64),
+        "uint64" | "fixed64" => Some(FieldKind::UInt64),
+        "float" => Some(FieldKind::Float),
+        "double" => Some(FieldKind::Double),
+        "bool" => Some(FieldKind::Bool),
+        "string" => Some(FieldKind::String),
+        "bytes" => Some(FieldKind::Bytes),
+        "enum" => Some(FieldKind::Enum),
+        "message" => Some(FieldKind::Message),
+        "map" => Some(FieldKind::Map),
+        _ => None,
+    }
+}
+
+fn parse_layout(message: &str, layout: Option<&str>) -> Result<MessageLayout, SchemaError> {
+    match layout {
+        None | Some("tagged") => Ok(MessageLayout::Tagged),
+        Some("bitmap") => Ok(MessageLayout::Bitmap),
+        Some("typed") => Ok(MessageLayout::Typed),
+        Some(other) => Err(SchemaError::UnknownLayout {
+            message: message.to_string(),
+            layout: other.to_string(),
+        }),
+    }
+}
+
+fn build_quantization(
+    message: &str,
+    field: &str,
+    kind: FieldKind,
+    raw: RawQuantization,
+) -> Result<Quantization, SchemaError> {
+    let quantization = match raw {
+        RawQuantization::Range { min, max, bits } => Quantization::Range { min, max, bits },
+        RawQuantization::FixedPoint { scale, bits } => Quantization::FixedPoint { scale, bits },
+    };
+    let result = if kind == FieldKind::Float {
+        quantization.validate()
+    } else {
+        Err("only float fields can be quantized".to_string())
+    };
+    result.map_err(|reason| SchemaError::InvalidQuantization {
+        message: message.to_string(),
+        field: field.to_string(),
+        reason,
+    })?;
+    Ok(quantization)
+}
+
+fn build_map(message: &str, field: &RawFieldSchema) -> Result<MapSchema, SchemaError> {
+    let invalid = |reason: &str| SchemaError::InvalidMap {
+        message: message.to_string(),
+        field: field.name.clone(),
+        reason: reason.to_string(),
+    };
+    if field.label == "repeated" {
+        return Err(invalid("map fields cannot be repeated"));
+    }
+    let (Some(key_type), Some(value_type)) = (&field.key_type, &field.value_type) else {
+        return Err(invalid("map fields need a key_type and a value_type"));
+    };
+    let parse = |field_type: &String| {
+        parse_kind(field_type).ok_or_else(|| SchemaError::UnknownFieldType {
+            message: message.to_string(),
+            field: field.name.clone(),
+            field_type: field_type.clone(),
+        })
+    };
+    let key = parse(key_type)?;
+    let value = parse(value_type)?;
+    // Same restriction as proto: keys are integral or strings.
+    if matches!(
+        key,
+        FieldKind::Float
+            | FieldKind::Double
+            | FieldKind::Bytes
+            | FieldKind::Enum
+            | FieldKind::Message
+            | FieldKind::Map
+    ) {
+        return Err(invalid(&format!("unsupported map key type: {key_type}")));
     }
+    if value == FieldKind::Map {
+        return Err(invalid("map values cannot be maps"));
+    }
+    let entry = |number, kind, type_name| FieldSchema {
+        name: field.name.clone(),
+        number,
+        kind,
+        is_repeated: false,
+        type_name,
+        quantization: None,
+        map: None,
+        oneof: None,
+        since: None,
+    };
+    Ok(MapSchema {
+        key: entry(1, key, None),
+        value: entry(2, value, field.type_name.clone()),
+    })
 }
 
-fn build_message(raw: RawMessageSchema) -> MessageSchema {
+fn build_message(raw: RawMessageSchema) -> Result<MessageSchema, SchemaError> {
+    let layout = parse_layout(&raw.name, raw.layout.as_deref())?;
     let mut fields = Vec::with_capacity(raw.fields.len());
     let mut fields_by_name = HashMap::new();
     let mut fields_by_number = HashMap::new();
+    let mut oneofs: Vec<OneofSchema> = Vec::new();
     for field in raw.fields {
+        let kind = parse_kind(&field.field_type).ok_or_else(|| SchemaError::UnknownFieldType {
+            message: raw.name.clone(),
+            field: field.name.clone(),
+            field_type: field.field_type.clone(),
+        })?;
+        let map = if kind == FieldKind::Map {
+            Some(Box::new(build_map(&raw.name, &field)?))
+        } else {
+            None
+        };
+        let is_repeated = field.label == "repeated";
+        if let Some(oneof) = &field.oneof {
+            if is_repeated || map.is_some() {
+                return Err(SchemaError::InvalidOneof {
+                    message: raw.name.clone(),
+                    field: field.name.clone(),
+                    oneof: oneof.clone(),
+                });
+            }
+            match oneofs.iter_mut().find(|group| group.name == *oneof) {
+                Some(group) => group.fields.push(field.name.clone()),
+                None => oneofs.push(OneofSchema {
+                    name: oneof.clone(),
+                    fields: vec![field.name.clone()],
+                }),
+            }
+        }
+        let quantization = field
+            .quantize
+            .map(|quantize| build_quantization(&raw.name, &field.name, kind, quantize))
+            .transpose()?;
         let schema = FieldSchema {
             name: field.name.clone(),
             number: field.number,
-            kind: parse_kind(&field.field_type),
-            is_repeated: field.label == "repeated",
+            kind,
+            is_repeated,
             type_name: field.type_name,
+            quantization,
+            map,
+            oneof: field.oneof,
+            since: field.since,
         };
         fields_by_name.insert(field.name, schema.clone());
         fields_by_number.insert(schema.number, schema.clone());
         fields.push(schema);
     }
-    MessageSchema {
+    let inline_groups = match raw.inline {
+        Some(groups) => groups
+            .into_iter()
+            .map(|group| InlineGroup {
+                name: group.name,
+                fields: group.fields,
+            })
+            .collect(),
+        // Messages that declare nothing keep accepting "hero", which
+        // predates inline declarations.
+        None if !fields_by_name.contains_key(DEFAULT_INLINE_GROUP) => vec![InlineGroup {
+            name: DEFAULT_INLINE_GROUP.to_string(),
+            fields: None,
+        }],
+        None => Vec::new(),
+    };
+    Ok(MessageSchema {
         name: raw.name,
+        layout,
         fields,
         fields_by_name,
         fields_by_number,
-    }
+        oneofs,
+        inline_groups,
+    })
 }
 
-fn load_schema() -> NetSchema {
-    let schema_path = concat!(env!("CARGO_MANIFEST_DIR"), "/schema/gen/net_schema.json");
-    let raw_json = std::fs::read_to_string(schema_path).unwrap_or_default();
-    if raw_json.trim().is_empty() {
-        return NetSchema {
-            messages: HashMap::new(),
-            profiles: HashMap::new(),
-        };
+impl NetSchema {
+    /// Parses and validates a schema; the same as `validate_json`.
+    pub fn from_json(raw_json: &str) -> Result<NetSchema, Vec<SchemaError>> {
+        validate_json(raw_json)
+    }
+
+    /// The layout used by `Codec::encode_frame`: the declared one, or the
+    /// default layout for schemas without a `frame` entry.
+    pub fn frame_layout(&self) -> &FrameSchema {
+        self.frame.as_ref().unwrap_or_else(|| default_frame())
     }
-    let raw: RawSchema = serde_json::from_str(&raw_json).expect("Invalid net_schema.json");
+
+    /// The current frame layout followed by the legacy ones.
+    pub fn frame_layouts(&self) -> impl Iterator<Item = &FrameSchema> {
+        std::iter::once(self.frame_layout()).chain(&self.legacy_frames)
+    }
+
+    /// The layout used by `Codec::encode_input`: the declared one, or the
+    /// default input layout for schemas without an `input` entry.
+    pub fn input_layout(&self) -> &FrameSchema {
+        self.input.as_ref().unwrap_or_else(|| default_input())
+    }
+
+    /// The layout of protocol `version`, which is the fixed-format version
+    /// byte of its frames.
+    pub fn protocol(&self, version: u8) -> Option<&FrameSchema> {
+        self.frame_layouts().find(|frame| frame.version == version)
+    }
+}
+
+pub(crate) fn parse_schema(raw_json: &str) -> Result<NetSchema, SchemaError> {
+    let raw: RawSchema =
+        serde_json::from_str(raw_json).map_err(|error| SchemaError::Json(error.to_string()))?;
+    build_schema(raw)
+}
+
+fn build_schema(raw: RawSchema) -> Result<NetSchema, SchemaError> {
     let mut messages = HashMap::new();
     for (name, message) in raw.messages {
-        messages.insert(name, build_message(message));
+        messages.insert(name, build_message(message)?);
     }
     let mut profiles = HashMap::new();
     for (name, profile) in raw.profiles {
@@ -137,7 +489,87 @@ fn load_schema() -> NetSchema {
             },
         );
     }
-    NetSchema { messages, profiles }
+    let frame = raw.frame.map(build_frame).transpose()?;
+    let legacy_frames = raw
+        .legacy_frames
+        .into_iter()
+        .map(build_frame)
+        .collect::<Result<_, _>>()?;
+    let input = raw.input.map(build_frame).transpose()?;
+    Ok(NetSchema {
+        messages,
+        profiles,
+        frame,
+        legacy_frames,
+        input,
+    })
 }
 
-pub static NET_SCHEMA: Lazy<NetSchema> = Lazy::new(load_schema);
+fn default_frame() -> &'static FrameSchema {
+    static DEFAULT_FRAME: OnceLock<FrameSchema> = OnceLock::new();
+    DEFAULT_FRAME.get_or_init(FrameSchema::default)
+}
+
+fn default_input() -> &'static FrameSchema {
+    static DEFAULT_INPUT: OnceLock<FrameSchema> = OnceLock::new();
+    DEFAULT_INPUT.get_or_init(default_input_layout)
+}
+
+const EMBEDDED_SCHEMA: &str = include_str!(concat!(env!("OUT_DIR"), "/net_schema.json"));
+
+static CURRENT_SCHEMA: RwLock<Option<Arc<NetSchema>>> = RwLock::new(None);
+
+impl NetSchema {
+    /// The schema embedded at build time from `schema/gen/net_schema.json`
+    /// (or `NETCODE_SCHEMA_PATH`), validated.
+    pub fn embedded() -> Result<NetSchema, Vec<SchemaError>> {
+        if EMBEDDED_SCHEMA.trim().is_empty() {
+            return Err(vec![SchemaError::NotEmbedded]);
+        }
+        validate_json(EMBEDDED_SCHEMA)
+    }
+
+    /// Reads and validates a schema file at runtime.
+    pub fn load(path: impl AsRef<Path>) -> Result<NetSchema, Vec<SchemaError>> {
+        let path = path.as_ref();
+        let raw_json = std::fs::read_to_string(path).map_err(|error| {
+            vec![SchemaError::Io {
+                path: path.display().to_string(),
+                error: error.to_string(),
+            }]
+        })?;
+        validate_json(&raw_json)
+    }
+}
+
+/// Returns the process-wide schema, loading the embedded one on first use.
+pub fn net_schema() -> Result<Arc<NetSchema>, Vec<SchemaError>> {
+    if let Some(schema) = CURRENT_SCHEMA
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return Ok(schema.clone());
+    }
+    let schema = Arc::new(NetSchema::embedded()?);
+    let mut current = CURRENT_SCHEMA
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    Ok(current.get_or_insert(schema).clone())
+}
+
+/// The process-wide schema as of its first use. Panics on first use if no
+/// schema can be loaded, and does not follow later `set_net_schema` calls.
+#[deprecated(note = "use `net_schema()`, which reports load errors and follows `set_net_schema`")]
+pub static NET_SCHEMA: Lazy<NetSchema> =
+    Lazy::new(|| NetSchema::clone(&net_schema().expect("Failed to load the net schema")));
+
+/// Replaces the process-wide schema returned by `net_schema`. Anything that
+/// already holds the previous schema keeps using it.
+pub fn set_net_schema(schema: NetSchema) -> Arc<NetSchema> {
+    let schema = Arc::new(schema);
+    *CURRENT_SCHEMA
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(schema.clone());
+    schema
+}
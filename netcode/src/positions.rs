@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+use crate::wire::{write_varint, zigzag_decode, zigzag_encode, Cursor, WireFormat};
+
+/// Where an entity's position goes for one tick, from cheapest to most
+/// expensive. Each stream is a frame section of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStream {
+    /// Only x changed.
+    X,
+    /// Only y changed.
+    Y,
+    /// x and y changed.
+    Xy,
+    /// The radius changed, or the entity has no previous position.
+    XyRadius,
+}
+
+impl PositionStream {
+    pub const ALL: [PositionStream; 4] = [
+        PositionStream::X,
+        PositionStream::Y,
+        PositionStream::Xy,
+        PositionStream::XyRadius,
+    ];
+
+    pub fn section(self) -> &'static str {
+        match self {
+            PositionStream::X => "x_entities",
+            PositionStream::Y => "y_entities",
+            PositionStream::Xy => "xy_entities",
+            PositionStream::XyRadius => "xy_radius_entities",
+        }
+    }
+
+    /// Which of x, y and radius the stream carries.
+    fn columns(self) -> [bool; 3] {
+        match self {
+            PositionStream::X => [true, false, false],
+            PositionStream::Y => [false, true, false],
+            PositionStream::Xy => [true, true, false],
+            PositionStream::XyRadius => [true, true, true],
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionUpdate {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// Fixed-point scales applied before delta encoding; a scale of 16 keeps
+/// positions to 1/16 of a unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionConfig {
+    pub position_scale: f32,
+    pub radius_scale: f32,
+}
+
+impl Default for PositionConfig {
+    fn default() -> Self {
+        Self {
+            position_scale: 16.0,
+            radius_scale: 16.0,
+        }
+    }
+}
+
+impl PositionConfig {
+    fn quantize(&self, update: &PositionUpdate) -> [i32; 3] {
+        [
+            quantize(update.x, self.position_scale),
+            quantize(update.y, self.position_scale),
+            quantize(update.radius, self.radius_scale),
+        ]
+    }
+
+    fn dequantize(&self, id: u32, [x, y, radius]: [i32; 3]) -> PositionUpdate {
+        PositionUpdate {
+            id,
+            x: dequantize(x, self.position_scale),
+            y: dequantize(y, self.position_scale),
+            radius: dequantize(radius, self.radius_scale),
+        }
+    }
+}
+
+fn quantize(value: f32, scale: f32) -> i32 {
+    (value as f64 * scale as f64)
+        .round()
+        .clamp(i32::MIN as f64, i32::MAX as f64) as i32
+}
+
+fn dequantize(value: i32, scale: f32) -> f32 {
+    (value as f64 / scale as f64) as f32
+}
+
+/// The four position blobs of a frame.
+///
+/// Each non-empty blob is a varint entity count, the entity ids in
+/// ascending order (the first as is, then the gap to the previous id minus
+/// one), then one column per value the stream carries, each a zigzag varint
+/// delta from the entity's previous quantized value. Blobs always use
+/// varints, whatever the frame's wire format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionStreams {
+    pub x: Vec<u8>,
+    pub y: Vec<u8>,
+    pub xy: Vec<u8>,
+    pub xy_radius: Vec<u8>,
+}
+
+impl PositionStreams {
+    pub fn get(&self, stream: PositionStream) -> &[u8] {
+        match stream {
+            PositionStream::X => &self.x,
+            PositionStream::Y => &self.y,
+            PositionStream::Xy => &self.xy,
+            PositionStream::XyRadius => &self.xy_radius,
+        }
+    }
+
+    pub fn get_mut(&mut self, stream: PositionStream) -> &mut Vec<u8> {
+        match stream {
+            PositionStream::X => &mut self.x,
+            PositionStream::Y => &mut self.y,
+            PositionStream::Xy => &mut self.xy,
+            PositionStream::XyRadius => &mut self.xy_radius,
+        }
+    }
+
+    /// Reads the streams from the sections of a decoded frame; missing
+    /// sections are empty.
+    pub fn from_frame(frame: &Message) -> CodecResult<Self> {
+        let mut streams = PositionStreams::default();
+        for stream in PositionStream::ALL {
+            let key = stream.section();
+            if let Some(value) = frame.get(key) {
+                let bytes = value
+                    .as_bytes()
+                    .ok_or_else(|| CodecError::invalid_value(key, "bytes"))?;
+                *streams.get_mut(stream) = bytes.to_vec();
+            }
+        }
+        Ok(streams)
+    }
+
+    /// Sets the frame sections of the non-empty streams.
+    pub fn write_to_frame(self, frame: &mut Message) {
+        let PositionStreams {
+            x,
+            y,
+            xy,
+            xy_radius,
+        } = self;
+        for (stream, bytes) in PositionStream::ALL.into_iter().zip([x, y, xy, xy_radius]) {
+            if !bytes.is_empty() {
+                frame.insert(stream.section(), Value::Bytes(bytes));
+            }
+        }
+    }
+}
+
+/// Server side of the position streams. Every tick's updates are encoded
+/// against the positions sent the tick before, so the client must decode
+/// every frame, in order, with a `PositionDecoder` of the same config.
+///
+/// Entities that leave the client's view should be `remove`d on both ends;
+/// after a `reset` every entity is sent in full again.
+#[derive(Debug, Clone, Default)]
+pub struct PositionEncoder {
+    config: PositionConfig,
+    sent: HashMap<u32, [i32; 3]>,
+}
+
+impl PositionEncoder {
+    pub fn new(config: PositionConfig) -> Self {
+        Self {
+            config,
+            sent: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> PositionConfig {
+        self.config
+    }
+
+    /// Encodes the entities whose quantized position changed. When an id
+    /// appears more than once the last update wins.
+    pub fn encode(&mut self, updates: &[PositionUpdate]) -> PositionStreams {
+        let updates: BTreeMap<u32, [i32; 3]> = updates
+            .iter()
+            .map(|update| (update.id, self.config.quantize(update)))
+            .collect();
+        let mut rows: [Vec<(u32, [i64; 3])>; 4] = Default::default();
+        for (id, position) in updates {
+            let previous = self.sent.insert(id, position);
+            let stream = match previous {
+                None => PositionStream::XyRadius,
+                Some(previous) if previous == position => continue,
+                Some(previous) if previous[2] != position[2] => PositionStream::XyRadius,
+                Some(previous) if previous[0] != position[0] && previous[1] != position[1] => {
+                    PositionStream::Xy
+                }
+                Some(previous) if previous[0] != position[0] => PositionStream::X,
+                Some(_) => PositionStream::Y,
+            };
+            let previous = previous.unwrap_or_default();
+            let delta = [0, 1, 2].map(|axis| position[axis] as i64 - previous[axis] as i64);
+            rows[stream.index()].push((id, delta));
+        }
+
+        let mut streams = PositionStreams::default();
+        for stream in PositionStream::ALL {
+            write_stream(stream, &rows[stream.index()], streams.get_mut(stream));
+        }
+        streams
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.sent.remove(&id);
+    }
+
+    pub fn reset(&mut self) {
+        self.sent.clear();
+    }
+}
+
+fn write_stream(stream: PositionStream, rows: &[(u32, [i64; 3])], buffer: &mut Vec<u8>) {
+    if rows.is_empty() {
+        return;
+    }
+    write_varint(buffer, rows.len() as u64);
+    let mut next_id = 0;
+    for &(id, _) in rows {
+        write_varint(buffer, id as u64 - next_id);
+        next_id = id as u64 + 1;
+    }
+    for (axis, carried) in stream.columns().into_iter().enumerate() {
+        if carried {
+            for (_, delta) in rows {
+                write_varint(buffer, zigzag_encode(delta[axis]));
+            }
+        }
+    }
+}
+
+/// Client side of the position streams; see `PositionEncoder`.
+#[derive(Debug, Clone, Default)]
+pub struct PositionDecoder {
+    config: PositionConfig,
+    received: HashMap<u32, [i32; 3]>,
+}
+
+impl PositionDecoder {
+    pub fn new(config: PositionConfig) -> Self {
+        Self {
+            config,
+            received: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> PositionConfig {
+        self.config
+    }
+
+    /// Applies one tick's streams and returns the updated entities, sorted
+    /// by id. Malformed streams are rejected without applying any of them.
+    pub fn decode(&mut self, streams: &PositionStreams) -> CodecResult<Vec<PositionUpdate>> {
+        let mut rows = Vec::new();
+        for stream in PositionStream::ALL {
+            read_stream(stream, streams.get(stream), &mut rows)?;
+        }
+        rows.sort_by_key(|(id, _, _)| *id);
+
+        let mut positions: Vec<(u32, [i32; 3])> = Vec::with_capacity(rows.len());
+        for (id, stream, delta) in rows {
+            let known = match positions.last() {
+                Some(&(last, position)) if last == id => Some(position),
+                _ => self.received.get(&id).copied(),
+            };
+            let previous = match (known, stream) {
+                (Some(previous), _) => previous,
+                (None, PositionStream::XyRadius) => [0; 3],
+                (None, _) => return Err(CodecError::UnknownEntity(id)),
+            };
+            let mut position = previous;
+            for axis in 0..3 {
+                position[axis] = i32::try_from(previous[axis] as i64 + delta[axis])
+                    .map_err(|_| CodecError::InvalidVarint)?;
+            }
+            positions.push((id, position));
+        }
+        self.received.extend(positions.iter().copied());
+        Ok(positions
+            .into_iter()
+            .map(|(id, position)| self.config.dequantize(id, position))
+            .collect())
+    }
+
+    /// The last decoded position of `id`.
+    pub fn get(&self, id: u32) -> Option<PositionUpdate> {
+        let position = self.received.get(&id)?;
+        Some(self.config.dequantize(id, *position))
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.received.remove(&id);
+    }
+
+    pub fn reset(&mut self) {
+        self.received.clear();
+    }
+}
+
+fn read_stream(
+    stream: PositionStream,
+    bytes: &[u8],
+    rows: &mut Vec<(u32, PositionStream, [i64; 3])>,
+) -> CodecResult<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    let mut cursor = Cursor::with_format(bytes, WireFormat::Compact);
+    let count = cursor.read_u32()? as usize;
+    let start = rows.len();
+    let mut next_id = 0;
+    for _ in 0..count {
+        let id = u32::try_from(next_id + cursor.read_u32()? as u64)
+            .map_err(|_| CodecError::InvalidVarint)?;
+        next_id = id as u64 + 1;
+        rows.push((id, stream, [0; 3]));
+    }
+    for (axis, carried) in stream.columns().into_iter().enumerate() {
+        if carried {
+            for row in &mut rows[start..] {
+                row.2[axis] = zigzag_decode(cursor.read_varint()?);
+            }
+        }
+    }
+    if !cursor.is_empty() {
+        return Err(CodecError::TrailingBytes(cursor.remaining()));
+    }
+    Ok(())
+}
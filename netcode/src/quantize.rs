@@ -0,0 +1,91 @@
+use crate::error::CodecResult;
+use crate::wire::Cursor;
+
+/// Lossy packing of a float field into 8, 16 or 24 bits.
+///
+/// `Range` maps `[min, max]` linearly onto the unsigned range of `bits`;
+/// `FixedPoint` stores `value * scale` as a signed integer of `bits`. Values
+/// outside the representable range saturate and rounding is half away from
+/// zero, so the same input always produces the same bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantization {
+    Range { min: f32, max: f32, bits: u8 },
+    FixedPoint { scale: f32, bits: u8 },
+}
+
+impl Quantization {
+    pub fn bits(&self) -> u8 {
+        match *self {
+            Quantization::Range { bits, .. } | Quantization::FixedPoint { bits, .. } => bits,
+        }
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !matches!(self.bits(), 8 | 16 | 24) {
+            return Err(format!(
+                "quantization bits must be 8, 16 or 24, got {}",
+                self.bits()
+            ));
+        }
+        match *self {
+            Quantization::Range { min, max, .. } if !(min.is_finite() && max.is_finite()) => {
+                Err("quantization range must be finite".to_string())
+            }
+            Quantization::Range { min, max, .. } if max <= min => Err(format!(
+                "quantization range max ({max}) must be greater than min ({min})"
+            )),
+            Quantization::FixedPoint { scale, .. } if !(scale.is_finite() && scale > 0.0) => Err(
+                format!("quantization scale must be a positive number, got {scale}"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the raw integer stored on the wire for `value`.
+    pub fn quantize(&self, value: f32) -> u32 {
+        let steps = ((1u64 << self.bits()) - 1) as f64;
+        match *self {
+            Quantization::Range { min, max, .. } => {
+                let normalized = (value as f64 - min as f64) / (max as f64 - min as f64);
+                (normalized.clamp(0.0, 1.0) * steps).round() as u32
+            }
+            Quantization::FixedPoint { scale, bits } => {
+                let limit = (1i64 << (bits - 1)) as f64;
+                let scaled = (value as f64 * scale as f64).round();
+                let signed = scaled.clamp(-limit, limit - 1.0) as i64;
+                (signed as u32) & (steps as u32)
+            }
+        }
+    }
+
+    pub fn dequantize(&self, raw: u32) -> f32 {
+        let steps = ((1u64 << self.bits()) - 1) as f64;
+        match *self {
+            Quantization::Range { min, max, .. } => {
+                let normalized = raw as f64 / steps;
+                (min as f64 + normalized * (max as f64 - min as f64)) as f32
+            }
+            Quantization::FixedPoint { scale, bits } => {
+                let shift = 32 - bits as u32;
+                let signed = ((raw << shift) as i32) >> shift;
+                (signed as f64 / scale as f64) as f32
+            }
+        }
+    }
+
+    pub fn write(&self, buffer: &mut Vec<u8>, value: f32) {
+        let raw = self.quantize(value).to_le_bytes();
+        buffer.extend_from_slice(&raw[..self.byte_len()]);
+    }
+
+    pub fn read(&self, cursor: &mut Cursor) -> CodecResult<f32> {
+        let mut raw = [0u8; 4];
+        let len = self.byte_len();
+        raw[..len].copy_from_slice(cursor.read_slice(len)?);
+        Ok(self.dequantize(u32::from_le_bytes(raw)))
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::codec::Codec;
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+
+const ENTITY_LISTS: [&str; 2] = ["entities", "global_entities"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchedulerConfig {
+    /// Priority of entities without one set by `set_priority`.
+    pub default_priority: f32,
+    /// Scales the priority of entities outside the view; at 0 they are
+    /// never sent. Global entities are always treated as in view.
+    pub outside_view: f32,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            default_priority: 1.0,
+            outside_view: 0.0,
+        }
+    }
+}
+
+/// The area a client sees, with `x` and `y` its top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ViewRect {
+    /// Whether a circle at `x`, `y` overlaps the view.
+    pub fn overlaps(&self, x: f32, y: f32, radius: f32) -> bool {
+        x + radius >= self.x
+            && x - radius <= self.x + self.width
+            && y + radius >= self.y
+            && y - radius <= self.y + self.height
+    }
+}
+
+/// The entity updates `EntityScheduler::select` chose for a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    /// Indices into the frame's `entities` to send, in list order.
+    pub entities: Vec<usize>,
+    /// Indices into the frame's `global_entities` to send, in list order.
+    pub global_entities: Vec<usize>,
+    /// Ids of the entities left out of this frame. Their updates are
+    /// dropped, not queued: send their full state in a later frame, or the
+    /// client misses whatever the dropped update changed.
+    pub deferred: Vec<u32>,
+    /// Encoded bytes of the entities sent.
+    pub bytes: usize,
+}
+
+impl Selection {
+    /// Removes the deferred entities from `frame`, dropping their updates;
+    /// see `deferred`.
+    pub fn apply(&self, frame: &mut Message) {
+        for (key, kept) in ENTITY_LISTS
+            .into_iter()
+            .zip([&self.entities, &self.global_entities])
+        {
+            let Some(items) = frame.get(key).and_then(Value::as_list) else {
+                continue;
+            };
+            let items: Vec<Value> = kept.iter().map(|&index| items[index].clone()).collect();
+            frame.insert(key, Value::List(items));
+        }
+    }
+
+    fn kept(&mut self, list: usize) -> &mut Vec<usize> {
+        if list == 0 {
+            &mut self.entities
+        } else {
+            &mut self.global_entities
+        }
+    }
+}
+
+struct Candidate {
+    list: usize,
+    index: usize,
+    id: u32,
+    size: usize,
+    score: f32,
+}
+
+/// Chooses which entity updates fit in one client's frame under a byte
+/// budget, deferring the rest.
+///
+/// Every entity has an accumulator that grows by its priority each frame
+/// it is in but not sent, scaled down outside the client's view, and resets when
+/// it is sent. Entities go out in order of their accumulators while they
+/// fit, so low priority entities are delayed rather than starved and a
+/// crowded area cannot exceed the budget. Entities without an `id` cannot
+/// be tracked and are always sent, and entities without `x` and `y` are
+/// taken to be updates to entities the client already placed, so they count
+/// as in view.
+///
+/// Deferred updates are dropped rather than merged into a later frame, so
+/// the caller must send the full state of every id in
+/// `Selection::deferred` the next time it sends that entity.
+///
+/// Use one scheduler per client. Entities missing from a frame keep their
+/// priority and accumulator until `remove` is called.
+#[derive(Debug, Default)]
+pub struct EntityScheduler {
+    config: SchedulerConfig,
+    priorities: HashMap<u32, f32>,
+    accumulators: HashMap<u32, f32>,
+}
+
+impl EntityScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> SchedulerConfig {
+        self.config
+    }
+
+    pub fn set_priority(&mut self, id: u32, priority: f32) {
+        self.priorities.insert(id, priority);
+    }
+
+    /// The priority entity `id` accumulated since it was last sent.
+    pub fn accumulator(&self, id: u32) -> Option<f32> {
+        self.accumulators.get(&id).copied()
+    }
+
+    /// Forgets the priority and accumulator of entity `id`, once it is gone.
+    pub fn remove(&mut self, id: u32) {
+        self.priorities.remove(&id);
+        self.accumulators.remove(&id);
+    }
+
+    pub fn clear(&mut self) {
+        self.priorities.clear();
+        self.accumulators.clear();
+    }
+
+    /// Picks the entities of `frame`, in the shape `Codec::encode_frame`
+    /// takes, whose encoded size fits in `budget` bytes. An id may appear
+    /// only once across both entity lists.
+    pub fn select(
+        &mut self,
+        codec: &Codec,
+        frame: &Message,
+        view: &ViewRect,
+        budget: usize,
+    ) -> CodecResult<Selection> {
+        let mut selection = Selection::default();
+        let mut candidates = Vec::new();
+        let mut seen = HashSet::new();
+        let mut buffer = Vec::with_capacity(128);
+        for (list, key) in ENTITY_LISTS.into_iter().enumerate() {
+            let Some(items) = frame.get(key) else {
+                continue;
+            };
+            let items = items
+                .as_list()
+                .ok_or_else(|| CodecError::invalid_value(key, "list"))?;
+            let Some(section) = codec.frame_layout().section(key) else {
+                continue;
+            };
+            let schema = codec.frame_section_schema(section)?;
+            for (index, item) in items.iter().enumerate() {
+                let entity = item
+                    .as_message()
+                    .ok_or_else(|| CodecError::invalid_value(key, "message"))?;
+                buffer.clear();
+                codec.write_message(schema, entity, &mut buffer)?;
+                let size = buffer.len();
+                let Some(id) = entity.get("id").and_then(Value::as_u32) else {
+                    selection.bytes += size;
+                    selection.kept(list).push(index);
+                    continue;
+                };
+
+                let in_view = key == "global_entities" || in_view(entity, view);
+                let scale = if in_view {
+                    1.0
+                } else {
+                    self.config.outside_view
+                };
+                let priority = self
+                    .priorities
+                    .get(&id)
+                    .copied()
+                    .unwrap_or(self.config.default_priority);
+                let score = self.accumulators.get(&id).copied().unwrap_or(0.0) + priority * scale;
+                if !seen.insert(id) {
+                    return Err(CodecError::invalid_value("id", "unique entity ids"));
+                }
+                candidates.push(Candidate {
+                    list,
+                    index,
+                    id,
+                    size,
+                    score,
+                });
+            }
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        for candidate in candidates {
+            // Smaller entities further down may still fit.
+            if candidate.score <= 0.0 || selection.bytes + candidate.size > budget {
+                self.accumulators.insert(candidate.id, candidate.score);
+                selection.deferred.push(candidate.id);
+                continue;
+            }
+            selection.bytes += candidate.size;
+            selection.kept(candidate.list).push(candidate.index);
+            self.accumulators.insert(candidate.id, 0.0);
+        }
+        selection.entities.sort_unstable();
+        selection.global_entities.sort_unstable();
+        Ok(selection)
+    }
+
+    /// Like `select`, then removes the deferred entities from `frame`.
+    pub fn select_frame(
+        &mut self,
+        codec: &Codec,
+        frame: &mut Message,
+        view: &ViewRect,
+        budget: usize,
+    ) -> CodecResult<Selection> {
+        let selection = self.select(codec, frame, view, budget)?;
+        selection.apply(frame);
+        Ok(selection)
+    }
+}
+
+fn in_view(entity: &Message, view: &ViewRect) -> bool {
+    let position = |key| entity.get(key).and_then(Value::as_f32);
+    match (position("x"), position("y")) {
+        (Some(x), Some(y)) => view.overlaps(x, y, position("radius").unwrap_or(0.0)),
+        // Entities without a position are updates to entities the client
+        // already placed.
+        _ => true,
+    }
+}
@@ -0,0 +1,798 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{
+    FieldKind, FieldSchema, FrameSchema, MessageLayout, MessageSchema, NetSchema, Quantization,
+    SectionKind, COMPRESSED_FRAME_BIT,
+};
+
+/// A problem found while loading or validating a `NetSchema`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    NotEmbedded,
+    Io {
+        path: String,
+        error: String,
+    },
+    Json(String),
+    UnknownFieldType {
+        message: String,
+        field: String,
+        field_type: String,
+    },
+    UnknownLayout {
+        message: String,
+        layout: String,
+    },
+    InvalidQuantization {
+        message: String,
+        field: String,
+        reason: String,
+    },
+    InvalidMap {
+        message: String,
+        field: String,
+        reason: String,
+    },
+    InvalidOneof {
+        message: String,
+        field: String,
+        oneof: String,
+    },
+    InvalidInlineGroup {
+        message: String,
+        group: String,
+        reason: String,
+    },
+    InvalidFrameSection {
+        section: String,
+        reason: String,
+    },
+    DuplicateFrameVersion(u8),
+    ReservedFrameVersion(u8),
+    LegacyFrameVersion {
+        version: u8,
+        current: u8,
+    },
+    InvalidFieldVersion {
+        message: String,
+        field: String,
+        since: u8,
+    },
+    MessageNameMismatch {
+        key: String,
+        name: String,
+    },
+    DuplicateFieldName {
+        message: String,
+        field: String,
+    },
+    DuplicateFieldNumber {
+        message: String,
+        number: u16,
+        first: String,
+        second: String,
+    },
+    MissingMessageType {
+        message: String,
+        field: String,
+    },
+    UnknownMessageType {
+        message: String,
+        field: String,
+        type_name: String,
+    },
+    UnknownProfileMessage {
+        profile: String,
+        message: String,
+    },
+    UnknownProfileField {
+        profile: String,
+        message: String,
+        field: String,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::NotEmbedded => write!(
+                f,
+                "No net schema was embedded at build time; generate schema/gen/net_schema.json, \
+                 set NETCODE_SCHEMA_PATH or load a schema at runtime"
+            ),
+            SchemaError::Io { path, error } => write!(f, "Failed to read {path}: {error}"),
+            SchemaError::Json(error) => write!(f, "Invalid schema JSON: {error}"),
+            SchemaError::UnknownFieldType {
+                message,
+                field,
+                field_type,
+            } => write!(f, "{message}.{field}: unsupported field type: {field_type}"),
+            SchemaError::UnknownLayout { message, layout } => {
+                write!(f, "{message}: unsupported message layout: {layout}")
+            }
+            SchemaError::InvalidQuantization {
+                message,
+                field,
+                reason,
+            } => write!(f, "{message}.{field}: {reason}"),
+            SchemaError::InvalidMap {
+                message,
+                field,
+                reason,
+            } => write!(f, "{message}.{field}: {reason}"),
+            SchemaError::InvalidOneof {
+                message,
+                field,
+                oneof,
+            } => write!(
+                f,
+                "{message}.{field}: repeated and map fields cannot be part of oneof {oneof}"
+            ),
+            SchemaError::InvalidInlineGroup {
+                message,
+                group,
+                reason,
+            } => write!(f, "{message}: inline group {group}: {reason}"),
+            SchemaError::InvalidFrameSection { section, reason } => {
+                write!(f, "Frame section {section}: {reason}")
+            }
+            SchemaError::DuplicateFrameVersion(version) => {
+                write!(f, "Frame version {version} is declared more than once")
+            }
+            SchemaError::ReservedFrameVersion(version) => write!(
+                f,
+                "Frame version {version} has the compressed frame bit set"
+            ),
+            SchemaError::LegacyFrameVersion { version, current } => write!(
+                f,
+                "Legacy frame version {version} is not older than the current version {current}"
+            ),
+            SchemaError::InvalidFieldVersion {
+                message,
+                field,
+                since,
+            } => write!(
+                f,
+                "{message}.{field}: protocol {since} is newer than the current frame version"
+            ),
+            SchemaError::MessageNameMismatch { key, name } => {
+                write!(f, "Message {key} is declared with name {name}")
+            }
+            SchemaError::DuplicateFieldName { message, field } => {
+                write!(f, "{message}.{field}: duplicate field name")
+            }
+            SchemaError::DuplicateFieldNumber {
+                message,
+                number,
+                first,
+                second,
+            } => write!(
+                f,
+                "{message}: field number {number} is used by both {first} and {second}"
+            ),
+            SchemaError::MissingMessageType { message, field } => {
+                write!(f, "{message}.{field}: message field has no type_name")
+            }
+            SchemaError::UnknownMessageType {
+                message,
+                field,
+                type_name,
+            } => write!(f, "{message}.{field}: unknown message type {type_name}"),
+            SchemaError::UnknownProfileMessage { profile, message } => {
+                write!(f, "Profile {profile}: unknown message {message}")
+            }
+            SchemaError::UnknownProfileField {
+                profile,
+                message,
+                field,
+            } => write!(f, "Profile {profile}: {message} has no field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A difference between two schemas that stops messages written with one from
+/// decoding to the same values with the other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakingChange {
+    RemovedMessage {
+        message: String,
+    },
+    ChangedLayout {
+        message: String,
+        old: MessageLayout,
+        new: MessageLayout,
+    },
+    RemovedField {
+        message: String,
+        field: String,
+        number: u16,
+    },
+    RenumberedField {
+        message: String,
+        field: String,
+        old: u16,
+        new: u16,
+    },
+    RenamedField {
+        message: String,
+        number: u16,
+        old: String,
+        new: String,
+    },
+    ChangedKind {
+        message: String,
+        field: String,
+        old: FieldKind,
+        new: FieldKind,
+    },
+    ChangedLabel {
+        message: String,
+        field: String,
+    },
+    ChangedMessageType {
+        message: String,
+        field: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    ChangedQuantization {
+        message: String,
+        field: String,
+        old: Option<Quantization>,
+        new: Option<Quantization>,
+    },
+    ChangedMapTypes {
+        message: String,
+        field: String,
+        old: (FieldKind, FieldKind),
+        new: (FieldKind, FieldKind),
+    },
+    ChangedOneof {
+        message: String,
+        field: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    ChangedSince {
+        message: String,
+        field: String,
+        old: Option<u8>,
+        new: Option<u8>,
+    },
+    /// A field was added to a `Tagged` or `Bitmap` message in a protocol
+    /// version `old` speaks. Peers reading `Tagged` messages reject unknown
+    /// field numbers, and `Bitmap` presence bits shift.
+    AddedField {
+        message: String,
+        field: String,
+        number: u16,
+    },
+    /// The presence bits of a `Bitmap` message are in a different order.
+    ReorderedFields {
+        message: String,
+    },
+    ChangedFrame {
+        version: u8,
+    },
+    RemovedProtocol {
+        version: u8,
+    },
+    /// Only one input layout is spoken, so any change to it breaks clients
+    /// that still send the old one.
+    ChangedInput,
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakingChange::RemovedMessage { message } => write!(f, "{message}: message removed"),
+            BreakingChange::ChangedLayout { message, old, new } => {
+                write!(f, "{message}: layout changed from {old:?} to {new:?}")
+            }
+            BreakingChange::RemovedField {
+                message,
+                field,
+                number,
+            } => write!(f, "{message}.{field}: field {number} removed"),
+            BreakingChange::RenumberedField {
+                message,
+                field,
+                old,
+                new,
+            } => write!(f, "{message}.{field}: renumbered from {old} to {new}"),
+            BreakingChange::RenamedField {
+                message,
+                number,
+                old,
+                new,
+            } => write!(f, "{message}: field {number} renamed from {old} to {new}"),
+            BreakingChange::ChangedKind {
+                message,
+                field,
+                old,
+                new,
+            } => write!(f, "{message}.{field}: kind changed from {old:?} to {new:?}"),
+            BreakingChange::ChangedLabel { message, field } => {
+                write!(f, "{message}.{field}: repeated label changed")
+            }
+            BreakingChange::ChangedMessageType {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "{message}.{field}: message type changed from {old:?} to {new:?}"
+            ),
+            BreakingChange::ChangedQuantization {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "{message}.{field}: quantization changed from {old:?} to {new:?}"
+            ),
+            BreakingChange::ChangedMapTypes {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "{message}.{field}: map types changed from {old:?} to {new:?}"
+            ),
+            BreakingChange::ChangedOneof {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "{message}.{field}: oneof changed from {old:?} to {new:?}"
+            ),
+            BreakingChange::ChangedSince {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "{message}.{field}: since changed from {old:?} to {new:?}"
+            ),
+            BreakingChange::AddedField {
+                message,
+                field,
+                number,
+            } => write!(
+                f,
+                "{message}.{field}: field {number} added to an existing protocol"
+            ),
+            BreakingChange::ReorderedFields { message } => {
+                write!(f, "{message}: presence bit order changed")
+            }
+            BreakingChange::ChangedFrame { version } => {
+                write!(f, "Frame layout changed without bumping version {version}")
+            }
+            BreakingChange::RemovedProtocol { version } => {
+                write!(f, "Protocol version {version} removed")
+            }
+            BreakingChange::ChangedInput => write!(f, "Input frame layout changed"),
+        }
+    }
+}
+
+impl NetSchema {
+    /// Checks the cross references that parsing does not: duplicate
+    /// field names and numbers, `type_name` of message fields and map values,
+    /// inline groups, the messages and fields named by profiles, field
+    /// protocol versions and the frame layouts. Errors are sorted by message.
+    pub fn validate(&self) -> Result<(), Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        for (key, message) in sorted(&self.messages) {
+            if *key != message.name {
+                errors.push(SchemaError::MessageNameMismatch {
+                    key: key.clone(),
+                    name: message.name.clone(),
+                });
+            }
+            self.validate_fields(message, &mut errors);
+            validate_inline_groups(message, &mut errors);
+        }
+
+        for (name, profile) in sorted(&self.profiles) {
+            let Some(message) = self.messages.get(&profile.message) else {
+                errors.push(SchemaError::UnknownProfileMessage {
+                    profile: name.clone(),
+                    message: profile.message.clone(),
+                });
+                continue;
+            };
+            for field in &profile.fields {
+                if !message.fields_by_name.contains_key(field) {
+                    errors.push(SchemaError::UnknownProfileField {
+                        profile: name.clone(),
+                        message: profile.message.clone(),
+                        field: field.clone(),
+                    });
+                }
+            }
+        }
+
+        self.validate_frames(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_fields(&self, message: &MessageSchema, errors: &mut Vec<SchemaError>) {
+        let mut names = HashMap::new();
+        let mut numbers: HashMap<u16, &FieldSchema> = HashMap::new();
+        let current = self.frame_layout().version;
+        for field in &message.fields {
+            if names.insert(field.name.as_str(), ()).is_some() {
+                errors.push(SchemaError::DuplicateFieldName {
+                    message: message.name.clone(),
+                    field: field.name.clone(),
+                });
+            }
+            if let Some(first) = numbers.insert(field.number, field) {
+                errors.push(SchemaError::DuplicateFieldNumber {
+                    message: message.name.clone(),
+                    number: field.number,
+                    first: first.name.clone(),
+                    second: field.name.clone(),
+                });
+            }
+            if let Some(since) = field.since.filter(|since| *since > current) {
+                errors.push(SchemaError::InvalidFieldVersion {
+                    message: message.name.clone(),
+                    field: field.name.clone(),
+                    since,
+                });
+            }
+            let value_kind = match &field.map {
+                Some(map) => map.value.kind,
+                None => field.kind,
+            };
+            if value_kind != FieldKind::Message {
+                continue;
+            }
+            match &field.type_name {
+                None => errors.push(SchemaError::MissingMessageType {
+                    message: message.name.clone(),
+                    field: field.name.clone(),
+                }),
+                Some(type_name) if !self.messages.contains_key(type_name) => {
+                    errors.push(SchemaError::UnknownMessageType {
+                        message: message.name.clone(),
+                        field: field.name.clone(),
+                        type_name: type_name.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn validate_frames(&self, errors: &mut Vec<SchemaError>) {
+        let mut versions = Vec::new();
+        for frame in self.frame_layouts() {
+            for version in [frame.version, frame.compact_version] {
+                if versions.contains(&version) {
+                    errors.push(SchemaError::DuplicateFrameVersion(version));
+                }
+                if version & COMPRESSED_FRAME_BIT != 0 {
+                    errors.push(SchemaError::ReservedFrameVersion(version));
+                }
+                versions.push(version);
+            }
+        }
+        let current = self.frame_layout().version;
+        for frame in &self.legacy_frames {
+            if frame.version >= current {
+                errors.push(SchemaError::LegacyFrameVersion {
+                    version: frame.version,
+                    current,
+                });
+            }
+        }
+        // Input frames travel the other way, so their versions only need to
+        // tell the two wire formats apart.
+        let input = self.input_layout();
+        if input.version == input.compact_version {
+            errors.push(SchemaError::DuplicateFrameVersion(input.version));
+        }
+        for version in [input.version, input.compact_version] {
+            if version & COMPRESSED_FRAME_BIT != 0 {
+                errors.push(SchemaError::ReservedFrameVersion(version));
+            }
+        }
+        // The default layouts are not checked: their message types are only
+        // needed by schemas that encode frames.
+        let declared = self.frame.iter().chain(&self.legacy_frames);
+        for frame in declared.chain(&self.input) {
+            self.validate_frame_sections(frame, errors);
+        }
+    }
+
+    fn validate_frame_sections(&self, frame: &FrameSchema, errors: &mut Vec<SchemaError>) {
+        for (index, section) in frame.sections.iter().enumerate() {
+            let mut invalid = |reason: String| {
+                errors.push(SchemaError::InvalidFrameSection {
+                    section: section.name.clone(),
+                    reason,
+                })
+            };
+            let previous = &frame.sections[..index];
+            if previous.iter().any(|other| other.name == section.name) {
+                invalid("declared more than once".to_string());
+            }
+            if let Some(other) = previous
+                .iter()
+                .find(|other| section.bit.is_some() && other.bit == section.bit)
+            {
+                invalid(format!("bit is already used by {}", other.name));
+            }
+            if section.kind != SectionKind::Message {
+                continue;
+            }
+            match &section.type_name {
+                None => invalid("message section has no type_name".to_string()),
+                Some(type_name) if !self.messages.contains_key(type_name) => {
+                    invalid(format!("unknown message type {type_name}"))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+fn validate_inline_groups(message: &MessageSchema, errors: &mut Vec<SchemaError>) {
+    let mut members: HashMap<&str, &str> = HashMap::new();
+    for (index, group) in message.inline_groups.iter().enumerate() {
+        let mut invalid = |reason: String| {
+            errors.push(SchemaError::InvalidInlineGroup {
+                message: message.name.clone(),
+                group: group.name.clone(),
+                reason,
+            })
+        };
+        if message.fields_by_name.contains_key(&group.name) {
+            invalid("conflicts with the field of the same name".to_string());
+        }
+        if message.inline_groups[..index]
+            .iter()
+            .any(|other| other.name == group.name)
+        {
+            invalid("declared more than once".to_string());
+        }
+        for field in group.fields.iter().flatten() {
+            if !message.fields_by_name.contains_key(field) {
+                invalid(format!("unknown field {field}"));
+            } else if let Some(other) = members.insert(field, &group.name) {
+                invalid(format!("field {field} is already in inline group {other}"));
+            }
+        }
+    }
+}
+
+/// Parses and validates a schema, collecting every problem instead of
+/// stopping at the first cross-reference error.
+pub fn validate_json(raw_json: &str) -> Result<NetSchema, Vec<SchemaError>> {
+    let schema = crate::parse_schema(raw_json).map_err(|error| vec![error])?;
+    schema.validate()?;
+    Ok(schema)
+}
+
+/// Lists the changes from `old` to `new` that would stop peers using one from
+/// decoding messages written with the other. Adding messages or protocol
+/// versions is not breaking; adding fields is only breaking for `Tagged` and
+/// `Bitmap` messages, unless the fields are new in a protocol version `old`
+/// does not speak.
+pub fn compare(old: &NetSchema, new: &NetSchema) -> Vec<BreakingChange> {
+    let mut changes = Vec::new();
+    let versions: Vec<u8> = old.frame_layouts().map(|frame| frame.version).collect();
+    for (name, old_message) in sorted(&old.messages) {
+        let Some(new_message) = new.messages.get(name) else {
+            changes.push(BreakingChange::RemovedMessage {
+                message: name.clone(),
+            });
+            continue;
+        };
+        if old_message.layout != new_message.layout {
+            changes.push(BreakingChange::ChangedLayout {
+                message: name.clone(),
+                old: old_message.layout,
+                new: new_message.layout,
+            });
+        }
+        compare_fields(name, old_message, new_message, &mut changes);
+        if old_message.layout == new_message.layout {
+            compare_added(name, old_message, new_message, &versions, &mut changes);
+        }
+    }
+    for old_frame in old.frame_layouts() {
+        let version = old_frame.version;
+        match new.protocol(version) {
+            Some(new_frame) if new_frame == old_frame => {}
+            Some(_) => changes.push(BreakingChange::ChangedFrame { version }),
+            None => changes.push(BreakingChange::RemovedProtocol { version }),
+        }
+    }
+    if old.input_layout() != new.input_layout() {
+        changes.push(BreakingChange::ChangedInput);
+    }
+    changes
+}
+
+fn compare_fields(
+    message: &str,
+    old: &MessageSchema,
+    new: &MessageSchema,
+    changes: &mut Vec<BreakingChange>,
+) {
+    for old_field in &old.fields {
+        // Decoded messages are keyed by name, so a rename breaks consumers
+        // even though the bytes are unchanged.
+        let new_field = match new.fields_by_name.get(&old_field.name) {
+            Some(new_field) => new_field,
+            None => match new.fields_by_number.get(&old_field.number) {
+                Some(renamed) if !old.fields_by_name.contains_key(&renamed.name) => {
+                    changes.push(BreakingChange::RenamedField {
+                        message: message.to_string(),
+                        number: old_field.number,
+                        old: old_field.name.clone(),
+                        new: renamed.name.clone(),
+                    });
+                    renamed
+                }
+                _ => {
+                    changes.push(BreakingChange::RemovedField {
+                        message: message.to_string(),
+                        field: old_field.name.clone(),
+                        number: old_field.number,
+                    });
+                    continue;
+                }
+            },
+        };
+        let field = || old_field.name.clone();
+        if old_field.number != new_field.number {
+            changes.push(BreakingChange::RenumberedField {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.number,
+                new: new_field.number,
+            });
+        }
+        if old_field.kind != new_field.kind {
+            changes.push(BreakingChange::ChangedKind {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.kind,
+                new: new_field.kind,
+            });
+        }
+        if old_field.is_repeated != new_field.is_repeated {
+            changes.push(BreakingChange::ChangedLabel {
+                message: message.to_string(),
+                field: field(),
+            });
+        }
+        if let (Some(old_map), Some(new_map)) = (&old_field.map, &new_field.map) {
+            let old_types = (old_map.key.kind, old_map.value.kind);
+            let new_types = (new_map.key.kind, new_map.value.kind);
+            if old_types != new_types {
+                changes.push(BreakingChange::ChangedMapTypes {
+                    message: message.to_string(),
+                    field: field(),
+                    old: old_types,
+                    new: new_types,
+                });
+            }
+        }
+        let old_value = old_field
+            .map
+            .as_ref()
+            .map_or(old_field.kind, |map| map.value.kind);
+        if old_value == FieldKind::Message && old_field.type_name != new_field.type_name {
+            changes.push(BreakingChange::ChangedMessageType {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.type_name.clone(),
+                new: new_field.type_name.clone(),
+            });
+        }
+        if old_field.quantization != new_field.quantization {
+            changes.push(BreakingChange::ChangedQuantization {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.quantization,
+                new: new_field.quantization,
+            });
+        }
+        if old_field.since != new_field.since {
+            changes.push(BreakingChange::ChangedSince {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.since,
+                new: new_field.since,
+            });
+        }
+        if old_field.oneof != new_field.oneof {
+            changes.push(BreakingChange::ChangedOneof {
+                message: message.to_string(),
+                field: field(),
+                old: old_field.oneof.clone(),
+                new: new_field.oneof.clone(),
+            });
+        }
+    }
+}
+
+/// Checks the fields `new` adds in the protocols in `versions` and, for
+/// `Bitmap` messages, the presence bits each protocol assigns. Removed fields
+/// are reported by `compare_fields`.
+fn compare_added(
+    message: &str,
+    old: &MessageSchema,
+    new: &MessageSchema,
+    versions: &[u8],
+    changes: &mut Vec<BreakingChange>,
+) {
+    if new.layout == MessageLayout::Typed {
+        return;
+    }
+    for field in &new.fields {
+        let added = !old.fields_by_name.contains_key(&field.name)
+            && !old.fields_by_number.contains_key(&field.number);
+        if added && versions.iter().any(|&version| field.in_protocol(version)) {
+            changes.push(BreakingChange::AddedField {
+                message: message.to_string(),
+                field: field.name.clone(),
+                number: field.number,
+            });
+        }
+    }
+    if new.layout != MessageLayout::Bitmap {
+        return;
+    }
+    let reordered = versions
+        .iter()
+        .any(|&version| bit_order(old, new, version) != bit_order(new, old, version));
+    if reordered {
+        changes.push(BreakingChange::ReorderedFields {
+            message: message.to_string(),
+        });
+    }
+}
+
+/// The fields of `schema` with a presence bit in `version` that `other` also
+/// gives a bit to, in bit order.
+fn bit_order<'s>(schema: &'s MessageSchema, other: &MessageSchema, version: u8) -> Vec<&'s str> {
+    schema
+        .fields
+        .iter()
+        .filter(|field| {
+            field.in_protocol(version)
+                && other
+                    .fields_by_name
+                    .get(&field.name)
+                    .is_some_and(|other| other.in_protocol(version))
+        })
+        .map(|field| field.name.as_str())
+        .collect()
+}
+
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
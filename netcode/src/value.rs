@@ -0,0 +1,304 @@
+/// A dynamically typed field value, mirroring what the Python bindings accept
+/// and return for each `FieldKind`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+    Message(Message),
+    List(Vec<Value>),
+    /// Map entries in wire order. Keys are not deduplicated; like a dict
+    /// built from the pairs, the last entry for a key wins.
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
+            Value::Int32(value) => Some(value),
+            Value::UInt32(value) => i32::try_from(value).ok(),
+            Value::Int64(value) => i32::try_from(value).ok(),
+            Value::UInt64(value) => i32::try_from(value).ok(),
+            Value::Float(value) => Some(value as i32),
+            Value::Double(value) => Some(value as i32),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Value::UInt32(value) => Some(value),
+            Value::Int32(value) => u32::try_from(value).ok(),
+            Value::Int64(value) => u32::try_from(value).ok(),
+            Value::UInt64(value) => u32::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int64(value) => Some(value),
+            Value::Int32(value) => Some(value as i64),
+            Value::UInt32(value) => Some(value as i64),
+            Value::UInt64(value) => i64::try_from(value).ok(),
+            Value::Float(value) => Some(value as i64),
+            Value::Double(value) => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::UInt64(value) => Some(value),
+            Value::UInt32(value) => Some(value as u64),
+            Value::Int32(value) => u64::try_from(value).ok(),
+            Value::Int64(value) => u64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::Float(value) => Some(value),
+            Value::Double(value) => Some(value as f32),
+            Value::Int32(value) => Some(value as f32),
+            Value::UInt32(value) => Some(value as f32),
+            Value::Int64(value) => Some(value as f32),
+            Value::UInt64(value) => Some(value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Double(value) => Some(value),
+            Value::Float(value) => Some(value as f64),
+            Value::Int32(value) => Some(value as f64),
+            Value::UInt32(value) => Some(value as f64),
+            Value::Int64(value) => Some(value as f64),
+            Value::UInt64(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_message(&self) -> Option<&Message> {
+        match self {
+            Value::Message(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Value::Map(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int32(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::UInt32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int64(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::UInt64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Double(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl From<Message> for Value {
+    fn from(value: Message) -> Self {
+        Value::Message(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::List(value)
+    }
+}
+
+impl From<Vec<(Value, Value)>> for Value {
+    fn from(value: Vec<(Value, Value)>) -> Self {
+        Value::Map(value)
+    }
+}
+
+/// An ordered set of named values, the Rust counterpart of a message dict.
+///
+/// Entries keep insertion order so decoded messages come back in wire order,
+/// but equality ignores order, like dict equality.
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    entries: Vec<(String, Value)>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Sets `name` to `value`, replacing any previous value in place.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        let name = name.into();
+        let value = value.into();
+        match self.entries.iter_mut().find(|(key, _)| *key == name) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((name, value)),
+        }
+    }
+
+    /// Builder-style variant of `insert`.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        let index = self.entries.iter().position(|(key, _)| key == name)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Message {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut message = Message::new();
+        for (key, value) in iter {
+            message.insert(key, value);
+        }
+        message
+    }
+}
+
+impl IntoIterator for Message {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
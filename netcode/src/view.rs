@@ -0,0 +1,377 @@
+use crate::codec::{check_oneof, field_map_schema, is_wrapped, wire_type, Codec};
+use crate::error::{CodecError, CodecResult};
+use crate::value::{Message, Value};
+use crate::wire::{Cursor, WireFormat};
+use crate::{FieldKind, FieldSchema, FrameSection, MessageLayout, MessageSchema};
+
+/// A field value borrowed from an encoded buffer. Strings and bytes point
+/// into the buffer and nested messages are `MessageRef`s.
+#[derive(Debug, Clone)]
+pub enum ValueRef<'a> {
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    String(&'a str),
+    Bytes(&'a [u8]),
+    Message(MessageRef<'a>),
+    List(Vec<ValueRef<'a>>),
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+}
+
+impl<'a> ValueRef<'a> {
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            ValueRef::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            ValueRef::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_message(&self) -> Option<&MessageRef<'a>> {
+        match self {
+            ValueRef::Message(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[ValueRef<'a>]> {
+        match self {
+            ValueRef::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Copies the value out of the buffer, decoding nested messages.
+    pub fn to_value(&self) -> CodecResult<Value> {
+        Ok(match self {
+            ValueRef::Int32(value) => Value::Int32(*value),
+            ValueRef::UInt32(value) => Value::UInt32(*value),
+            ValueRef::Int64(value) => Value::Int64(*value),
+            ValueRef::UInt64(value) => Value::UInt64(*value),
+            ValueRef::Float(value) => Value::Float(*value),
+            ValueRef::Double(value) => Value::Double(*value),
+            ValueRef::Bool(value) => Value::Bool(*value),
+            ValueRef::String(value) => Value::String(value.to_string()),
+            ValueRef::Bytes(value) => Value::Bytes(value.to_vec()),
+            ValueRef::Message(value) => Value::Message(value.to_message()?),
+            ValueRef::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(ValueRef::to_value)
+                    .collect::<CodecResult<_>>()?,
+            ),
+            ValueRef::Map(entries) => Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.to_value()?, value.to_value()?)))
+                    .collect::<CodecResult<_>>()?,
+            ),
+        })
+    }
+}
+
+/// A message read in place from an encoded buffer.
+///
+/// Reading it is eager: the whole message, nested messages included, is
+/// walked and checked up front to record where each field's value sits, so
+/// a malformed buffer fails here rather than on `get`. Values are only copied
+/// out of the buffer when asked for, but a nested message is walked again
+/// each time it is read. Fields are flat, as on the wire:
+/// inline groups are not re-nested, but `to_message` honours the codec's
+/// inline nesting.
+#[derive(Debug, Clone)]
+pub struct MessageRef<'a> {
+    codec: Codec<'a>,
+    schema: &'a MessageSchema,
+    format: WireFormat,
+    bytes: &'a [u8],
+    fields: Vec<(&'a FieldSchema, &'a [u8])>,
+}
+
+impl<'a> MessageRef<'a> {
+    pub(crate) fn read(
+        codec: Codec<'a>,
+        schema: &'a MessageSchema,
+        cursor: &mut Cursor<'a>,
+    ) -> CodecResult<Self> {
+        let start = cursor.offset();
+        let mut fields: Vec<(&FieldSchema, &[u8])> = Vec::new();
+        read_fields(codec, schema, cursor, &mut |field, value| {
+            // A repeated field number replaces the earlier value in place,
+            // as in `Message::insert`.
+            match fields
+                .iter_mut()
+                .find(|(known, _)| known.number == field.number)
+            {
+                Some(entry) => entry.1 = value,
+                None => fields.push((field, value)),
+            }
+        })?;
+        for oneof in &schema.oneofs {
+            check_oneof(oneof, |name| {
+                fields.iter().any(|(field, _)| field.name == name)
+            })?;
+        }
+        Ok(Self {
+            codec,
+            schema,
+            format: cursor.format(),
+            bytes: cursor.read_since(start),
+            fields,
+        })
+    }
+
+    pub fn schema(&self) -> &'a MessageSchema {
+        self.schema
+    }
+
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|(field, _)| field.name == name)
+    }
+
+    pub fn get(&self, name: &str) -> CodecResult<Option<ValueRef<'a>>> {
+        match self.fields.iter().find(|(field, _)| field.name == name) {
+            Some((field, value)) => self.read_value(field, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the present fields in wire order.
+    pub fn iter(&self) -> impl Iterator<Item = CodecResult<(&'a str, ValueRef<'a>)>> + '_ {
+        self.fields
+            .iter()
+            .map(|(field, value)| Ok((field.name.as_str(), self.read_value(field, value)?)))
+    }
+
+    /// Decodes an owned copy, as `Codec::decode_message` would.
+    pub fn to_message(&self) -> CodecResult<Message> {
+        let mut cursor = Cursor::with_format(self.bytes, self.format);
+        self.codec.read_message(self.schema, &mut cursor)
+    }
+
+    fn read_value(&self, field: &'a FieldSchema, value: &'a [u8]) -> CodecResult<ValueRef<'a>> {
+        let mut cursor = Cursor::with_format(value, self.format);
+        if field.is_repeated {
+            let count = cursor.read_u16()? as usize;
+            let mut items = Vec::with_capacity(count);
+            for _ in 0..count {
+                items.push(read_single_value(self.codec, field, &mut cursor)?);
+            }
+            return Ok(ValueRef::List(items));
+        }
+
+        read_single_value(self.codec, field, &mut cursor)
+    }
+}
+
+/// A frame read by `Codec::decode_frame_ref`, with the entries
+/// `Codec::decode_frame` would return.
+#[derive(Debug, Clone)]
+pub struct FrameRef<'a> {
+    sections: Vec<(&'a FrameSection, ValueRef<'a>)>,
+}
+
+impl<'a> FrameRef<'a> {
+    pub(crate) fn new(sections: Vec<(&'a FrameSection, ValueRef<'a>)>) -> Self {
+        Self { sections }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ValueRef<'a>> {
+        self.sections
+            .iter()
+            .find(|(section, _)| section.name == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &ValueRef<'a>)> {
+        self.sections
+            .iter()
+            .map(|(section, value)| (section.name.as_str(), value))
+    }
+
+    /// Decodes an owned copy, as `Codec::decode_frame` would.
+    pub fn to_message(&self) -> CodecResult<Message> {
+        let mut frame = Message::with_capacity(self.sections.len());
+        for (section, value) in &self.sections {
+            frame.insert(section.name.as_str(), value.to_value()?);
+        }
+        Ok(frame)
+    }
+}
+
+/// Walks the fields of a message, passing every field of the codec's
+/// protocol to `visit` with the bytes of its value.
+fn read_fields<'a>(
+    codec: Codec<'a>,
+    schema: &'a MessageSchema,
+    cursor: &mut Cursor<'a>,
+    visit: &mut dyn FnMut(&'a FieldSchema, &'a [u8]),
+) -> CodecResult<()> {
+    let protocol = codec.protocol();
+    match schema.layout {
+        MessageLayout::Tagged => {
+            let field_count = cursor.read_u16()?;
+            for _ in 0..field_count {
+                let number = cursor.read_u16()?;
+                let field = schema
+                    .fields_by_number
+                    .get(&number)
+                    .ok_or(CodecError::UnknownFieldNumber(number))?;
+                let start = cursor.offset();
+                skip_field_value(codec, field, cursor)?;
+                if field.in_protocol(protocol) {
+                    visit(field, cursor.read_since(start));
+                }
+            }
+        }
+        MessageLayout::Typed => {
+            let field_count = cursor.read_u16()?;
+            for _ in 0..field_count {
+                let (number, found) = cursor.read_key()?;
+                let Some(field) = schema.fields_by_number.get(&number) else {
+                    cursor.skip(found)?;
+                    continue;
+                };
+                let wire_type = wire_type(field, cursor.format());
+                if found != wire_type {
+                    return Err(CodecError::WireTypeMismatch(field.name.clone()));
+                }
+                let value = if is_wrapped(field, wire_type) {
+                    let value = cursor.read_bytes()?;
+                    let mut nested = Cursor::with_format(value, cursor.format());
+                    skip_field_value(codec, field, &mut nested)?;
+                    if !nested.is_empty() {
+                        return Err(CodecError::TrailingBytes(nested.remaining()));
+                    }
+                    value
+                } else {
+                    let start = cursor.offset();
+                    skip_field_value(codec, field, cursor)?;
+                    cursor.read_since(start)
+                };
+                if field.in_protocol(protocol) {
+                    visit(field, value);
+                }
+            }
+        }
+        MessageLayout::Bitmap => {
+            let presence = codec.read_presence(schema, cursor)?;
+            for (index, field) in codec.bitmap_fields(schema).enumerate() {
+                if presence[index / 8] & (1 << (index % 8)) != 0 {
+                    let start = cursor.offset();
+                    skip_field_value(codec, field, cursor)?;
+                    visit(field, cursor.read_since(start));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn skip_field_value<'a>(
+    codec: Codec<'a>,
+    field: &'a FieldSchema,
+    cursor: &mut Cursor<'a>,
+) -> CodecResult<()> {
+    let count = if field.is_repeated {
+        cursor.read_u16()?
+    } else {
+        1
+    };
+    for _ in 0..count {
+        skip_single_value(codec, field, cursor)?;
+    }
+    Ok(())
+}
+
+fn skip_single_value<'a>(
+    codec: Codec<'a>,
+    field: &'a FieldSchema,
+    cursor: &mut Cursor<'a>,
+) -> CodecResult<()> {
+    match field.kind {
+        FieldKind::Message => {
+            // Checked like `MessageRef::read`, so nested oneof conflicts
+            // fail up front too.
+            let schema = codec.field_message_schema(field)?;
+            let mut present: Vec<&str> = Vec::new();
+            read_fields(codec, schema, cursor, &mut |field, _| {
+                present.push(&field.name)
+            })?;
+            for oneof in &schema.oneofs {
+                check_oneof(oneof, |name| present.contains(&name))?;
+            }
+            Ok(())
+        }
+        FieldKind::Map => {
+            let map = field_map_schema(field)?;
+            for _ in 0..cursor.read_u16()? {
+                skip_single_value(codec, &map.key, cursor)?;
+                skip_single_value(codec, &map.value, cursor)?;
+            }
+            Ok(())
+        }
+        // Other values borrow or copy scalars, so reading them is as cheap as
+        // skipping.
+        _ => read_single_value(codec, field, cursor).map(drop),
+    }
+}
+
+fn read_single_value<'a>(
+    codec: Codec<'a>,
+    field: &'a FieldSchema,
+    cursor: &mut Cursor<'a>,
+) -> CodecResult<ValueRef<'a>> {
+    Ok(match field.kind {
+        FieldKind::Int32 | FieldKind::Enum => ValueRef::Int32(cursor.read_i32()?),
+        FieldKind::UInt32 => ValueRef::UInt32(cursor.read_u32()?),
+        FieldKind::Int64 => ValueRef::Int64(cursor.read_i64()?),
+        FieldKind::UInt64 => ValueRef::UInt64(cursor.read_u64()?),
+        FieldKind::Float => match field.quantization {
+            Some(quantization) => ValueRef::Float(quantization.read(cursor)?),
+            None => ValueRef::Float(cursor.read_f32()?),
+        },
+        FieldKind::Double => ValueRef::Double(cursor.read_f64()?),
+        FieldKind::Bool => ValueRef::Bool(cursor.read_u8()? == 1),
+        FieldKind::String => ValueRef::String(cursor.read_str()?),
+        FieldKind::Bytes => ValueRef::Bytes(cursor.read_bytes()?),
+        FieldKind::Message => {
+            let schema = codec.field_message_schema(field)?;
+            ValueRef::Message(MessageRef::read(codec, schema, cursor)?)
+        }
+        FieldKind::Map => {
+            let map = field_map_schema(field)?;
+            let count = cursor.read_u16()? as usize;
+            let mut entries = Vec::with_capacity(count.min(cursor.remaining()));
+            for _ in 0..count {
+                let key = read_single_value(codec, &map.key, cursor)?;
+                let value = read_single_value(codec, &map.value, cursor)?;
+                entries.push((key, value));
+            }
+            ValueRef::Map(entries)
+        }
+    })
+}
@@ -0,0 +1,314 @@
+use crate::error::{CodecError, CodecResult};
+
+pub fn write_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_i32(buffer: &mut Vec<u8>, value: i32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_i64(buffer: &mut Vec<u8>, value: i64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_f32(buffer: &mut Vec<u8>, value: f32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_f64(buffer: &mut Vec<u8>, value: f64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    let len = bytes.len() as u32;
+    write_u32(buffer, len);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Writes `value` as an unsigned LEB128 varint.
+pub fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+pub fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// How integers, counts and lengths are laid out on the wire.
+///
+/// `Fixed` writes field numbers and counts as `u16`, lengths and 32-bit
+/// integers as 4 bytes and 64-bit integers as 8 bytes. `Compact` writes all
+/// of them as LEB128 varints, zigzag-encoding signed values so small negative
+/// numbers stay small. Floats and bools are identical in both formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    #[default]
+    Fixed,
+    Compact,
+}
+
+impl WireFormat {
+    pub fn write_u16(self, buffer: &mut Vec<u8>, value: u16) {
+        match self {
+            WireFormat::Fixed => write_u16(buffer, value),
+            WireFormat::Compact => write_varint(buffer, value as u64),
+        }
+    }
+
+    pub fn write_u32(self, buffer: &mut Vec<u8>, value: u32) {
+        match self {
+            WireFormat::Fixed => write_u32(buffer, value),
+            WireFormat::Compact => write_varint(buffer, value as u64),
+        }
+    }
+
+    pub fn write_i32(self, buffer: &mut Vec<u8>, value: i32) {
+        match self {
+            WireFormat::Fixed => write_i32(buffer, value),
+            WireFormat::Compact => write_varint(buffer, zigzag_encode(value as i64)),
+        }
+    }
+
+    pub fn write_u64(self, buffer: &mut Vec<u8>, value: u64) {
+        match self {
+            WireFormat::Fixed => write_u64(buffer, value),
+            WireFormat::Compact => write_varint(buffer, value),
+        }
+    }
+
+    pub fn write_i64(self, buffer: &mut Vec<u8>, value: i64) {
+        match self {
+            WireFormat::Fixed => write_i64(buffer, value),
+            WireFormat::Compact => write_varint(buffer, zigzag_encode(value)),
+        }
+    }
+
+    pub fn write_bytes(self, buffer: &mut Vec<u8>, bytes: &[u8]) {
+        self.write_u32(buffer, bytes.len() as u32);
+        buffer.extend_from_slice(bytes);
+    }
+
+    /// Writes the key of a field in a `MessageLayout::Typed` message: the
+    /// field number shifted left by three, or'ed with the wire type.
+    pub fn write_key(self, buffer: &mut Vec<u8>, number: u16, wire_type: WireType) {
+        self.write_u32(buffer, (number as u32) << 3 | wire_type as u32);
+    }
+
+    pub fn int32_wire_type(self) -> WireType {
+        match self {
+            WireFormat::Fixed => WireType::Fixed32,
+            WireFormat::Compact => WireType::Varint,
+        }
+    }
+
+    pub fn int64_wire_type(self) -> WireType {
+        match self {
+            WireFormat::Fixed => WireType::Fixed64,
+            WireFormat::Compact => WireType::Varint,
+        }
+    }
+}
+
+/// How a value in a `MessageLayout::Typed` message is delimited, using the
+/// protobuf numbering. Knowing it is enough to skip a field.
+///
+/// `Varint` is also used for bools, whose single byte is a valid varint in
+/// both formats. `Len` values carry a length in the format's encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+    Varint = 0,
+    Fixed64 = 1,
+    Len = 2,
+    Fixed32 = 5,
+}
+
+impl WireType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(WireType::Varint),
+            1 => Some(WireType::Fixed64),
+            2 => Some(WireType::Len),
+            5 => Some(WireType::Fixed32),
+            _ => None,
+        }
+    }
+}
+
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+    format: WireFormat,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_format(data, WireFormat::Fixed)
+    }
+
+    pub fn with_format(data: &'a [u8], format: WireFormat) -> Self {
+        Self {
+            data,
+            offset: 0,
+            format,
+        }
+    }
+
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: WireFormat) {
+        self.format = format;
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn read_slice(&mut self, len: usize) -> CodecResult<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(CodecError::UnexpectedEnd);
+        }
+        let start = self.offset;
+        self.offset += len;
+        Ok(&self.data[start..self.offset])
+    }
+
+    /// The bytes read since the cursor was at `start`.
+    pub fn read_since(&self, start: usize) -> &'a [u8] {
+        &self.data[start..self.offset]
+    }
+
+    fn take_array<const N: usize>(&mut self) -> CodecResult<[u8; N]> {
+        Ok(self.read_slice(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> CodecResult<u8> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    pub fn read_varint(&mut self) -> CodecResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            // The tenth byte only has room for the top bit of a u64.
+            if shift == 63 && byte > 1 {
+                return Err(CodecError::InvalidVarint);
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CodecError::InvalidVarint)
+    }
+
+    pub fn read_u16(&mut self) -> CodecResult<u16> {
+        match self.format {
+            WireFormat::Fixed => Ok(u16::from_le_bytes(self.take_array()?)),
+            WireFormat::Compact => {
+                u16::try_from(self.read_varint()?).map_err(|_| CodecError::InvalidVarint)
+            }
+        }
+    }
+
+    pub fn read_u32(&mut self) -> CodecResult<u32> {
+        match self.format {
+            WireFormat::Fixed => Ok(u32::from_le_bytes(self.take_array()?)),
+            WireFormat::Compact => {
+                u32::try_from(self.read_varint()?).map_err(|_| CodecError::InvalidVarint)
+            }
+        }
+    }
+
+    pub fn read_i32(&mut self) -> CodecResult<i32> {
+        match self.format {
+            WireFormat::Fixed => Ok(i32::from_le_bytes(self.take_array()?)),
+            WireFormat::Compact => i32::try_from(zigzag_decode(self.read_varint()?))
+                .map_err(|_| CodecError::InvalidVarint),
+        }
+    }
+
+    pub fn read_u64(&mut self) -> CodecResult<u64> {
+        match self.format {
+            WireFormat::Fixed => Ok(u64::from_le_bytes(self.take_array()?)),
+            WireFormat::Compact => self.read_varint(),
+        }
+    }
+
+    pub fn read_i64(&mut self) -> CodecResult<i64> {
+        match self.format {
+            WireFormat::Fixed => Ok(i64::from_le_bytes(self.take_array()?)),
+            WireFormat::Compact => Ok(zigzag_decode(self.read_varint()?)),
+        }
+    }
+
+    pub fn read_f32(&mut self) -> CodecResult<f32> {
+        Ok(f32::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn read_f64(&mut self) -> CodecResult<f64> {
+        Ok(f64::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn read_bytes(&mut self) -> CodecResult<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.read_slice(len)
+    }
+
+    pub fn read_str(&mut self) -> CodecResult<&'a str> {
+        std::str::from_utf8(self.read_bytes()?).map_err(|_| CodecError::InvalidUtf8)
+    }
+
+    /// Reads a key written by `WireFormat::write_key`.
+    pub fn read_key(&mut self) -> CodecResult<(u16, WireType)> {
+        let key = self.read_u32()?;
+        let number = u16::try_from(key >> 3).map_err(|_| CodecError::InvalidFieldKey(key))?;
+        let wire_type =
+            WireType::from_u8((key & 0b111) as u8).ok_or(CodecError::InvalidFieldKey(key))?;
+        Ok((number, wire_type))
+    }
+
+    pub fn skip(&mut self, wire_type: WireType) -> CodecResult<()> {
+        match wire_type {
+            WireType::Varint => {
+                self.read_varint()?;
+            }
+            WireType::Fixed64 => {
+                self.read_slice(8)?;
+            }
+            WireType::Len => {
+                self.read_bytes()?;
+            }
+            WireType::Fixed32 => {
+                self.read_slice(4)?;
+            }
+        }
+        Ok(())
+    }
+}
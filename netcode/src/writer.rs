@@ -0,0 +1,102 @@
+use std::mem;
+use std::ops::Range;
+
+use crate::codec::Codec;
+use crate::error::{CodecError, CodecResult};
+use crate::value::Message;
+
+struct SharedSection {
+    bytes: Range<usize>,
+    present: bool,
+}
+
+/// Encodes the same frame for many clients without re-encoding the parts
+/// they share or reallocating between ticks.
+///
+/// Each tick the server calls `set_shared` once with the sections every
+/// client receives, then `write` for each client with its own sections.
+/// Shared sections are encoded once and copied into every frame; a client
+/// section replaces the shared section of the same name. The frames are
+/// identical to `Codec::encode_frame` of the merged sections.
+#[derive(Default)]
+pub struct FrameWriter {
+    shared: Vec<Option<SharedSection>>,
+    shared_bytes: Vec<u8>,
+    shared_version: u8,
+    buffer: Vec<u8>,
+}
+
+impl FrameWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encodes the sections of `frame` shared by every client, replacing the
+    /// previous ones. Frames must then be written with a codec of the same
+    /// protocol and wire format.
+    pub fn set_shared(&mut self, codec: &Codec, frame: &Message) -> CodecResult<()> {
+        self.clear_shared();
+        for section in &codec.frame_layout().sections {
+            if !frame.contains(&section.name) {
+                self.shared.push(None);
+                continue;
+            }
+            let start = self.shared_bytes.len();
+            let present = match codec.write_section(section, frame, &mut self.shared_bytes) {
+                Ok(present) => present,
+                Err(err) => {
+                    self.clear_shared();
+                    return Err(err);
+                }
+            };
+            self.shared.push(Some(SharedSection {
+                bytes: start..self.shared_bytes.len(),
+                present,
+            }));
+        }
+        self.shared_version = codec.frame_version();
+        Ok(())
+    }
+
+    pub fn clear_shared(&mut self) {
+        self.shared.clear();
+        self.shared_bytes.clear();
+    }
+
+    /// Writes the frame for one client into the writer's buffer, which is
+    /// reused by the next call.
+    pub fn write(&mut self, codec: &Codec, client: &Message) -> CodecResult<&[u8]> {
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.clear();
+        let written = self.write_into(codec, client, &mut buffer);
+        self.buffer = buffer;
+        written?;
+        Ok(&self.buffer)
+    }
+
+    /// Appends the frame for one client to `buffer`.
+    pub fn write_into(
+        &self,
+        codec: &Codec,
+        client: &Message,
+        buffer: &mut Vec<u8>,
+    ) -> CodecResult<()> {
+        if !self.shared.is_empty() && self.shared_version != codec.frame_version() {
+            return Err(CodecError::SharedFrameVersion(self.shared_version));
+        }
+        codec.write_frame(buffer, |index, section, buffer| {
+            let shared = self
+                .shared
+                .get(index)
+                .and_then(Option::as_ref)
+                .filter(|_| !client.contains(&section.name));
+            match shared {
+                Some(shared) => {
+                    buffer.extend_from_slice(&self.shared_bytes[shared.bytes.clone()]);
+                    Ok(shared.present)
+                }
+                None => codec.write_section(section, client, buffer),
+            }
+        })
+    }
+}
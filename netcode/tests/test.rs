@@ -0,0 +1,3257 @@
+use std::sync::Arc;
+
+use netcode::wire::{write_bytes, write_varint, zigzag_decode, zigzag_encode, Cursor};
+use netcode::{
+    compare, net_schema, read_capture, set_net_schema, validate_json, write_capture_frame,
+    BreakingChange, BufferConfig, Channel, ChannelConfig, Codec, CodecError, EntityScheduler,
+    EntityState, FieldKind, FragmentConfig, Fragmenter, FrameWriter, InputBuffer, InputConfig,
+    InputStatus, InputTiming, Message, NetSchema, PositionConfig, PositionDecoder, PositionEncoder,
+    PositionStreams, PositionUpdate, Reassembler, SchedulerConfig, SchemaError, SnapshotBuffer,
+    SnapshotStore, Value, ViewRect, WireFormat, WireType, CHANNEL_HEADER_LEN, FRAGMENT_HEADER_LEN,
+    FRAME_VERSION, FRAME_VERSION_COMPACT,
+};
+#[cfg(feature = "compression")]
+use netcode::{train_dictionary, CompressionConfig, FrameCompressor, COMPRESSED_FRAME_BIT};
+
+const SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Entity": {
+            "name": "Entity",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "x", "number": 2, "type": "float", "label": "optional"},
+                {"name": "y", "number": 3, "type": "float", "label": "optional"},
+                {"name": "name", "number": 4, "type": "string", "label": "optional"},
+                {"name": "dead", "number": 5, "type": "bool", "label": "optional"},
+                {"name": "level", "number": 6, "type": "int32", "label": "optional"},
+                {"name": "effects", "number": 7, "type": "uint32", "label": "repeated"},
+                {"name": "area", "number": 8, "type": "message", "label": "optional", "type_name": "Area"}
+            ]
+        },
+        "Area": {
+            "name": "Area",
+            "fields": [
+                {"name": "name", "number": 1, "type": "string", "label": "optional"},
+                {"name": "data", "number": 2, "type": "bytes", "label": "optional"}
+            ]
+        },
+        "ServerDebugObject": {
+            "name": "ServerDebugObject",
+            "fields": [
+                {"name": "kind", "number": 1, "type": "enum", "label": "optional"}
+            ]
+        }
+    },
+    "profiles": {
+        "EntityPosition": {"message": "Entity", "fields": ["x", "y"]}
+    }
+}"#;
+
+fn schema() -> NetSchema {
+    NetSchema::from_json(SCHEMA_JSON).expect("valid schema")
+}
+
+fn entity() -> Message {
+    Message::new()
+        .with("id", 7u32)
+        .with("x", 1.5f32)
+        .with("y", -2.0f32)
+        .with("name", "hero")
+        .with("dead", false)
+        .with("level", -3)
+        .with("effects", vec![Value::UInt32(1), Value::UInt32(2)])
+        .with("area", Message::new().with("name", "Central Core"))
+}
+
+#[test]
+fn test_message_round_trip() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &entity(), &mut buffer)
+        .unwrap();
+    let decoded = codec.decode_message("Entity", &buffer).unwrap();
+    assert_eq!(decoded, entity());
+}
+
+#[test]
+fn test_message_layout() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let message = Message::new().with("y", 1.0f32).with("id", 3u32);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+
+    let mut expected = vec![2, 0];
+    expected.extend_from_slice(&[1, 0, 3, 0, 0, 0]);
+    expected.extend_from_slice(&[3, 0]);
+    expected.extend_from_slice(&1.0f32.to_le_bytes());
+    assert_eq!(buffer, expected);
+}
+
+#[test]
+fn test_unknown_keys_are_ignored() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let message = Message::new().with("id", 3u32).with("unknown", 1);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+    let decoded = codec.decode_message("Entity", &buffer).unwrap();
+    assert_eq!(decoded, Message::new().with("id", 3u32));
+}
+
+#[test]
+fn test_hero_fields_are_flattened() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let message = Message::new()
+        .with("id", 3u32)
+        .with("hero", Message::new().with("level", 9).with("name", "Jolt"));
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+    let decoded = codec.decode_message("Entity", &buffer).unwrap();
+    assert_eq!(
+        decoded,
+        Message::new()
+            .with("id", 3u32)
+            .with("name", "Jolt")
+            .with("level", 9)
+    );
+}
+
+const INLINE_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Player": {
+            "name": "Player",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "cooldown", "number": 2, "type": "float", "label": "optional"},
+                {"name": "charges", "number": 3, "type": "uint32", "label": "optional"},
+                {"name": "skin", "number": 4, "type": "string", "label": "optional"}
+            ],
+            "inline": [
+                {"name": "abilities", "fields": ["cooldown", "charges"]},
+                {"name": "cosmetics", "fields": ["skin"]},
+                {"name": "hero"}
+            ]
+        },
+        "Plain": {
+            "name": "Plain",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"}
+            ],
+            "inline": []
+        }
+    }
+}"#;
+
+#[test]
+fn test_inline_groups_are_flattened_and_renested() {
+    let schema = NetSchema::from_json(INLINE_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let flat = Message::new()
+        .with("id", 1u32)
+        .with("cooldown", 0.5f32)
+        .with("charges", 2u32)
+        .with("skin", "gold");
+    let grouped = Message::new()
+        .with("id", 1u32)
+        .with(
+            "abilities",
+            Message::new()
+                .with("cooldown", 0.5f32)
+                .with("charges", 2u32)
+                // Not part of the group.
+                .with("skin", "silver"),
+        )
+        .with("hero", Message::new().with("skin", "gold"));
+
+    let mut expected = Vec::new();
+    codec
+        .encode_message("Player", &flat, &mut expected)
+        .unwrap();
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Player", &grouped, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, expected);
+    assert_eq!(codec.decode_message("Player", &buffer).unwrap(), flat);
+
+    let nesting = codec.with_inline_nesting(true);
+    assert!(nesting.nests_inline());
+    assert_eq!(
+        nesting.decode_message("Player", &buffer).unwrap(),
+        Message::new()
+            .with("id", 1u32)
+            .with(
+                "abilities",
+                Message::new()
+                    .with("cooldown", 0.5f32)
+                    .with("charges", 2u32)
+            )
+            .with("cosmetics", Message::new().with("skin", "gold"))
+    );
+
+    // Declaring groups replaces the implicit "hero" group.
+    let message = Message::new().with("hero", Message::new().with("id", 5u32));
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Plain", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![0, 0]);
+}
+
+#[test]
+fn test_snapshot_store_renests_inline_groups() {
+    let schema = NetSchema::from_json(INLINE_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let mut server = SnapshotStore::new("Player", 4);
+    let mut client = SnapshotStore::new("Player", 4);
+
+    let player = |charges: u32| {
+        Message::new()
+            .with("id", 1u32)
+            .with("abilities", Message::new().with("charges", charges))
+    };
+    let mut buffer = Vec::new();
+    server
+        .encode_entities(&codec, 0, 1, &[player(1)], &mut buffer)
+        .unwrap();
+    let nesting = codec.with_inline_nesting(true);
+    assert_eq!(
+        client.decode_entities(&nesting, 0, 1, &buffer),
+        Ok(vec![player(1)])
+    );
+
+    server.ack(0, 1);
+    let mut buffer = Vec::new();
+    server
+        .encode_entities(&codec, 0, 2, &[player(2)], &mut buffer)
+        .unwrap();
+    assert_eq!(
+        client.decode_entities(&nesting, 0, 2, &buffer),
+        Ok(vec![player(2)])
+    );
+}
+
+#[test]
+fn test_invalid_inline_groups() {
+    let raw = INLINE_SCHEMA_JSON
+        .replace(
+            r#""fields": ["skin"]"#,
+            r#""fields": ["skin", "charges", "mana"]"#,
+        )
+        .replace(r#"{"name": "hero"}"#, r#"{"name": "id"}"#);
+    let invalid = |group: &str, reason: &str| SchemaError::InvalidInlineGroup {
+        message: "Player".to_string(),
+        group: group.to_string(),
+        reason: reason.to_string(),
+    };
+    assert_eq!(
+        validate_json(&raw).unwrap_err(),
+        vec![
+            invalid(
+                "cosmetics",
+                "field charges is already in inline group abilities"
+            ),
+            invalid("cosmetics", "unknown field mana"),
+            invalid("id", "conflicts with the field of the same name"),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_inline_values() {
+    let schema = NetSchema::from_json(INLINE_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let abilities = Message::new().with("charges", 2);
+    let cases = [
+        (
+            Message::new().with("abilities", 2),
+            CodecError::invalid_value("abilities", "message"),
+        ),
+        (
+            Message::new()
+                .with("charges", 1)
+                .with("abilities", abilities.clone()),
+            CodecError::invalid_value("charges", "a single value"),
+        ),
+        (
+            Message::new()
+                .with("abilities", abilities.clone())
+                .with("hero", abilities),
+            CodecError::invalid_value("charges", "a single value"),
+        ),
+    ];
+    for (player, err) in cases {
+        assert_eq!(
+            codec.encode_message("Player", &player, &mut Vec::new()),
+            Err(err)
+        );
+    }
+}
+
+#[test]
+fn test_invalid_values() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut buffer = Vec::new();
+
+    let err = codec
+        .encode_message("Entity", &Message::new().with("id", -1), &mut buffer)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Expected non-negative uint32 for id");
+
+    let err = codec
+        .encode_message("Entity", &Message::new().with("dead", 1), &mut buffer)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Expected bool for dead");
+
+    let effects = vec![Value::UInt32(0); u16::MAX as usize + 1];
+    let err = codec
+        .encode_message(
+            "Entity",
+            &Message::new().with("effects", effects),
+            &mut buffer,
+        )
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Expected at most 65535 items for effects");
+
+    let err = codec
+        .encode_message("Missing", &Message::new(), &mut buffer)
+        .unwrap_err();
+    assert_eq!(err, CodecError::UnknownMessage("Missing".to_string()));
+}
+
+#[test]
+fn test_decode_errors() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    assert_eq!(
+        codec.decode_message("Entity", &[1, 0, 99, 0]).unwrap_err(),
+        CodecError::UnknownFieldNumber(99)
+    );
+    assert_eq!(
+        codec
+            .decode_message("Entity", &[1, 0, 1, 0, 0])
+            .unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+}
+
+#[test]
+fn test_frame_layout() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let area = Message::new().with("name", "Home");
+    let frame = Message::new()
+        .with("sequence", 5u32)
+        .with("complete", true)
+        .with("pong", 9u32)
+        .with("area", area.clone())
+        .with("xy_entities", vec![1u8, 2, 3])
+        .with("entities", vec![Value::Message(entity())]);
+    let mut buffer = Vec::new();
+    codec.encode_frame(&frame, &mut buffer).unwrap();
+
+    let mut area_bytes = Vec::new();
+    codec
+        .encode_message("Area", &area, &mut area_bytes)
+        .unwrap();
+    let mut entity_bytes = Vec::new();
+    codec
+        .encode_message("Entity", &entity(), &mut entity_bytes)
+        .unwrap();
+
+    let mut expected = vec![FRAME_VERSION, 0b0110_0001, 0b0100_0000];
+    expected.extend_from_slice(&5u32.to_le_bytes());
+    expected.extend_from_slice(&9u32.to_le_bytes());
+    expected.extend_from_slice(&(area_bytes.len() as u32).to_le_bytes());
+    expected.extend_from_slice(&area_bytes);
+    expected.extend_from_slice(&3u32.to_le_bytes());
+    expected.extend_from_slice(&[1, 2, 3]);
+    expected.extend_from_slice(&1u32.to_le_bytes());
+    expected.extend_from_slice(&entity_bytes);
+    expected.extend_from_slice(&0u32.to_le_bytes());
+    expected.extend_from_slice(&0u32.to_le_bytes());
+    assert_eq!(buffer, expected);
+}
+
+#[test]
+fn test_frame_accepts_pre_encoded_messages() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let area = Message::new().with("name", "Home");
+    let mut area_bytes = Vec::new();
+    codec
+        .encode_message("Area", &area, &mut area_bytes)
+        .unwrap();
+
+    let mut from_message = Vec::new();
+    codec
+        .encode_frame(&Message::new().with("area", area), &mut from_message)
+        .unwrap();
+    let mut from_bytes = Vec::new();
+    codec
+        .encode_frame(&Message::new().with("area", area_bytes), &mut from_bytes)
+        .unwrap();
+    assert_eq!(from_message, from_bytes);
+}
+
+#[test]
+fn test_frame_round_trip() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let frame = Message::new()
+        .with("sequence", 12u32)
+        .with("complete", true)
+        .with("complete_global", false)
+        .with("reset", true)
+        .with("self_id", 3u32)
+        .with("tick_rate", 30.0f32)
+        .with("pong", 11u32)
+        .with("area", Message::new().with("name", "Home"))
+        .with("x_entities", vec![1u8])
+        .with("xy_radius_entities", vec![2u8, 3])
+        .with("entities", vec![Value::Message(entity())])
+        .with("global_entities", Vec::<Value>::new())
+        .with(
+            "debug_objects",
+            vec![Value::Message(Message::new().with("kind", 2))],
+        );
+    let mut buffer = Vec::new();
+    codec.encode_frame(&frame, &mut buffer).unwrap();
+    assert_eq!(codec.decode_frame(&buffer).unwrap(), frame);
+}
+
+#[test]
+fn test_decode_frame_defaults() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut buffer = Vec::new();
+    codec.encode_frame(&Message::new(), &mut buffer).unwrap();
+    let decoded = codec.decode_frame(&buffer).unwrap();
+    assert_eq!(decoded.get("sequence"), Some(&Value::UInt32(0)));
+    assert_eq!(decoded.get("complete"), Some(&Value::Bool(false)));
+    assert_eq!(decoded.get("self_id"), None);
+    assert_eq!(decoded.get("entities"), Some(&Value::List(Vec::new())));
+}
+
+#[test]
+fn test_frame_writer_matches_encode_frame() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let shared = Message::new()
+        .with("complete", true)
+        .with("tick_rate", 30.0f32)
+        .with("area", Message::new().with("name", "Home"))
+        .with("x_entities", vec![1u8, 2]);
+    let clients = [
+        Message::new()
+            .with("sequence", 1u32)
+            .with("entities", vec![Value::Message(entity())]),
+        Message::new()
+            .with("sequence", 2u32)
+            .with("pong", 5u32)
+            .with("area", Message::new().with("name", "Away")),
+    ];
+
+    let mut writer = FrameWriter::new();
+    writer.set_shared(&codec, &shared).unwrap();
+    let mut first_frame = None;
+    for client in &clients {
+        let mut merged = shared.clone();
+        for (key, value) in client.iter() {
+            merged.insert(key, value.clone());
+        }
+        let mut expected = Vec::new();
+        codec.encode_frame(&merged, &mut expected).unwrap();
+        let frame = writer.write(&codec, client).unwrap();
+        assert_eq!(frame, &expected[..]);
+        first_frame.get_or_insert(frame.as_ptr());
+
+        let mut appended = vec![9];
+        writer.write_into(&codec, client, &mut appended).unwrap();
+        assert_eq!(appended[1..], expected[..]);
+    }
+    // The second frame is smaller, so it reuses the first one's buffer.
+    assert_eq!(
+        writer.write(&codec, &clients[0]).unwrap().as_ptr(),
+        first_frame.unwrap()
+    );
+
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    assert_eq!(
+        writer.write(&compact, &clients[0]).err(),
+        Some(CodecError::SharedFrameVersion(FRAME_VERSION))
+    );
+
+    // A failed `set_shared` leaves no shared sections behind.
+    let invalid = shared.clone().with("pong", "soon");
+    assert!(writer.set_shared(&codec, &invalid).is_err());
+    let mut expected = Vec::new();
+    compact.encode_frame(&clients[0], &mut expected).unwrap();
+    assert_eq!(writer.write(&compact, &clients[0]).unwrap(), &expected[..]);
+}
+
+#[test]
+fn test_decode_frame_validation() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut buffer = Vec::new();
+    codec
+        .encode_frame(&Message::new().with("pong", 1u32), &mut buffer)
+        .unwrap();
+
+    let mut wrong_version = buffer.clone();
+    wrong_version[0] = 0xff;
+    assert_eq!(
+        codec.decode_frame(&wrong_version).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(0xff)
+    );
+
+    let mut missing_section = buffer.clone();
+    missing_section[2] |= 1 << 4;
+    assert_eq!(
+        codec.decode_frame(&missing_section).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+
+    let mut trailing = buffer.clone();
+    trailing.push(0);
+    assert_eq!(
+        codec.decode_frame(&trailing).unwrap_err(),
+        CodecError::TrailingBytes(1)
+    );
+
+    assert_eq!(
+        codec.decode_frame(&buffer[..buffer.len() - 1]).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+}
+
+#[test]
+fn test_capture_round_trip() {
+    let frames = [vec![1u8, 2, 3], Vec::new(), vec![4]];
+    let mut capture = Vec::new();
+    for frame in &frames {
+        write_capture_frame(&mut capture, frame);
+    }
+    assert_eq!(capture.len(), 4 * 3 + 4);
+    assert_eq!(
+        read_capture(&capture).unwrap(),
+        frames.iter().map(Vec::as_slice).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        read_capture(&capture[..capture.len() - 1]).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_frame_compression_round_trip() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut capture = Vec::new();
+    for sequence in 0..500u32 {
+        let entities: Vec<Value> = (0..8)
+            .map(|id| Value::Message(entity().with("id", sequence * 8 + id)))
+            .collect();
+        let frame = Message::new()
+            .with("sequence", sequence)
+            .with("area", Message::new().with("name", "Central Core"))
+            .with("entities", entities);
+        let mut buffer = Vec::new();
+        codec.encode_frame(&frame, &mut buffer).unwrap();
+        write_capture_frame(&mut capture, &buffer);
+    }
+    let frames = read_capture(&capture).unwrap();
+    let dictionary = train_dictionary(&frames, 4096).unwrap();
+
+    let config = CompressionConfig::default();
+    let mut plain = FrameCompressor::new(config).unwrap();
+    let mut trained = FrameCompressor::with_dictionary(&dictionary, config).unwrap();
+    let (mut plain_len, mut trained_len) = (0, 0);
+    for frame in &frames {
+        let mut compressed = Vec::new();
+        trained.compress(frame, &mut compressed).unwrap();
+        assert_eq!(compressed[0], FRAME_VERSION | COMPRESSED_FRAME_BIT);
+        assert!(compressed.len() < frame.len());
+        trained_len += compressed.len();
+        assert_eq!(trained.decompress(&compressed).unwrap(), *frame);
+
+        let mut compressed = Vec::new();
+        plain.compress(frame, &mut compressed).unwrap();
+        plain_len += compressed.len();
+        assert_eq!(plain.decompress(&compressed).unwrap(), *frame);
+    }
+    assert!(trained_len < plain_len);
+
+    // Short frames are passed through and decode as is.
+    let mut short = Vec::new();
+    codec.encode_frame(&Message::new(), &mut short).unwrap();
+    let mut compressed = Vec::new();
+    trained.compress(&short, &mut compressed).unwrap();
+    assert_eq!(compressed, short);
+    let decompressed = trained.decompress(&compressed).unwrap();
+    assert_eq!(
+        codec.decode_frame(decompressed).unwrap().get("sequence"),
+        Some(&Value::UInt32(0))
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_frame_compression_errors() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let frame = Message::new().with(
+        "area",
+        Message::new().with("name", "Central Core".repeat(8)),
+    );
+    let mut encoded = Vec::new();
+    codec.encode_frame(&frame, &mut encoded).unwrap();
+    let frames: Vec<Vec<u8>> = (0..200u32)
+        .map(|sequence| {
+            let mut buffer = Vec::new();
+            let frame = frame.clone().with("sequence", sequence);
+            codec.encode_frame(&frame, &mut buffer).unwrap();
+            buffer
+        })
+        .collect();
+    let dictionary = train_dictionary(&frames, 1024).unwrap();
+
+    let config = CompressionConfig::default();
+    let mut plain = FrameCompressor::new(config).unwrap();
+    let mut compressed = Vec::new();
+    plain.compress(&encoded, &mut compressed).unwrap();
+    assert_ne!(compressed[0] & COMPRESSED_FRAME_BIT, 0);
+    assert_eq!(
+        plain.compress(&compressed, &mut Vec::new()).unwrap_err(),
+        CodecError::CompressedFrame
+    );
+    assert_eq!(
+        train_dictionary(&[&compressed], 1024).unwrap_err(),
+        CodecError::CompressedFrame
+    );
+    assert_eq!(
+        plain.compress(&[], &mut Vec::new()).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+    assert_eq!(
+        plain.decompress(&[]).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+
+    // The decompressed length is checked before anything is allocated.
+    let mut oversized = vec![FRAME_VERSION | COMPRESSED_FRAME_BIT];
+    write_varint(&mut oversized, 2 << 20);
+    assert_eq!(
+        plain.decompress(&oversized).unwrap_err(),
+        CodecError::FrameTooLarge(2 << 20)
+    );
+
+    let mut truncated = compressed.clone();
+    truncated.pop();
+    assert!(matches!(
+        plain.decompress(&truncated),
+        Err(CodecError::Compression(_))
+    ));
+
+    // Frames compressed with a dictionary need it to decompress.
+    let mut trained = FrameCompressor::with_dictionary(&dictionary, config).unwrap();
+    let mut compressed = Vec::new();
+    trained.compress(&encoded, &mut compressed).unwrap();
+    assert!(matches!(
+        plain.decompress(&compressed),
+        Err(CodecError::Compression(_))
+    ));
+}
+
+#[test]
+fn test_fragments_reassemble_out_of_order() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let frame = Message::new().with("complete", true).with(
+        "area",
+        Message::new()
+            .with("name", "Central Core")
+            .with("data", vec![7u8; 3000]),
+    );
+    let mut encoded = Vec::new();
+    codec.encode_frame(&frame, &mut encoded).unwrap();
+
+    let config = FragmentConfig {
+        max_packet_size: 1000,
+        ..FragmentConfig::default()
+    };
+    let mut fragmenter = Fragmenter::new(config);
+    let packets = fragmenter.split(&encoded).unwrap();
+    let count = encoded.len().div_ceil(1000 - FRAGMENT_HEADER_LEN);
+    assert_eq!(packets.len(), count);
+    assert!(packets.iter().all(|packet| packet.len() <= 1000));
+    assert_eq!(
+        packets[1][..FRAGMENT_HEADER_LEN],
+        [0, 0, 0, 0, 1, 0, count as u8, 0]
+    );
+
+    // Every fragment but the first arrives twice, in reverse order.
+    let mut reassembler = Reassembler::new(config);
+    for packet in packets[1..].iter().rev() {
+        assert_eq!(reassembler.insert(packet, 0.1).unwrap(), None);
+        assert_eq!(reassembler.insert(packet, 0.1).unwrap(), None);
+    }
+    assert_eq!(reassembler.pending(), 1);
+    let rebuilt = reassembler.insert(&packets[0], 0.2).unwrap().unwrap();
+    assert_eq!(rebuilt, encoded);
+    assert_eq!(
+        codec.decode_frame(&rebuilt).unwrap().get("area"),
+        frame.get("area")
+    );
+    assert_eq!(reassembler.pending(), 0);
+
+    // Late copies of a returned frame are not taken for a new one.
+    assert_eq!(reassembler.insert(&packets[2], 0.3).unwrap(), None);
+    assert_eq!(reassembler.pending(), 0);
+
+    // Frames that fit take a single fragment and the next sequence.
+    let small = fragmenter.split(&[1, 2]).unwrap();
+    assert_eq!(small, vec![vec![1, 0, 0, 0, 0, 0, 1, 0, 1, 2]]);
+    assert_eq!(
+        reassembler.insert(&small[0], 0.3).unwrap(),
+        Some(vec![1, 2])
+    );
+    let empty = fragmenter.split(&[]).unwrap();
+    assert_eq!(empty.len(), 1);
+    assert_eq!(
+        reassembler.insert(&empty[0], 0.3).unwrap(),
+        Some(Vec::new())
+    );
+}
+
+#[test]
+fn test_fragment_timeouts_and_errors() {
+    let config = FragmentConfig {
+        max_packet_size: FRAGMENT_HEADER_LEN + 4,
+        timeout: 1.0,
+        max_frame_size: 10,
+        max_frames: 4,
+    };
+    let mut fragmenter = Fragmenter::new(config);
+    let packets = fragmenter.split(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    assert_eq!(packets.len(), 2);
+
+    let mut reassembler = Reassembler::new(config);
+    assert_eq!(reassembler.insert(&packets[0], 0.0).unwrap(), None);
+    assert_eq!(reassembler.expire(1.0), 0);
+    assert_eq!(reassembler.expire(1.5), 1);
+    assert_eq!(reassembler.pending(), 0);
+    // The rest of an expired frame starts it over.
+    assert_eq!(reassembler.insert(&packets[1], 1.5).unwrap(), None);
+    assert_eq!(reassembler.pending(), 1);
+
+    let mut bad_index = packets[0].clone();
+    bad_index[4] = 2;
+    assert_eq!(
+        reassembler.insert(&bad_index, 2.0).unwrap_err(),
+        CodecError::InvalidFragment(0)
+    );
+    let mut bad_count = packets[0].clone();
+    bad_count[6] = 3;
+    assert_eq!(
+        reassembler.insert(&bad_count, 2.0).unwrap_err(),
+        CodecError::InvalidFragment(0)
+    );
+    assert_eq!(
+        reassembler.insert(&packets[0][..5], 2.0).unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+    assert_eq!(
+        reassembler.insert(&packets[0], 2.0).unwrap(),
+        Some((1..=8).collect())
+    );
+
+    // Frames past max_frame_size are dropped.
+    let oversized = fragmenter.split(&[0; 12]).unwrap();
+    assert_eq!(reassembler.insert(&oversized[0], 2.0).unwrap(), None);
+    assert_eq!(reassembler.insert(&oversized[1], 2.0).unwrap(), None);
+    assert_eq!(
+        reassembler.insert(&oversized[2], 2.0).unwrap_err(),
+        CodecError::FrameTooLarge(12)
+    );
+    assert_eq!(reassembler.pending(), 0);
+
+    let no_room = FragmentConfig {
+        max_packet_size: FRAGMENT_HEADER_LEN,
+        ..config
+    };
+    assert_eq!(
+        Fragmenter::new(no_room).split(&[1]).unwrap_err(),
+        CodecError::invalid_value("max_packet_size", "a size above the fragment header")
+    );
+    let one_byte = FragmentConfig {
+        max_packet_size: FRAGMENT_HEADER_LEN + 1,
+        ..config
+    };
+    assert_eq!(
+        Fragmenter::new(one_byte).split(&[0; 70_000]).unwrap_err(),
+        CodecError::FrameTooLarge(70_000)
+    );
+}
+
+#[test]
+fn test_reassembler_bounds_flooding_peers() {
+    let config = FragmentConfig {
+        max_packet_size: FRAGMENT_HEADER_LEN + 4,
+        max_frame_size: 12,
+        max_frames: 4,
+        ..FragmentConfig::default()
+    };
+    let packet = |sequence: u32, index: u16, count: u16| {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&sequence.to_le_bytes());
+        packet.extend_from_slice(&index.to_le_bytes());
+        packet.extend_from_slice(&count.to_le_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet
+    };
+    let mut reassembler = Reassembler::new(config);
+
+    // A 12 byte frame takes at most 3 fragments of 4 bytes.
+    assert_eq!(
+        reassembler.insert(&packet(0, 0, 4), 0.0).unwrap_err(),
+        CodecError::InvalidFragment(0)
+    );
+    assert_eq!(
+        reassembler
+            .insert(&packet(1, 0, u16::MAX), 0.0)
+            .unwrap_err(),
+        CodecError::InvalidFragment(1)
+    );
+
+    // Only the newest partly received frames are kept.
+    for sequence in 0..100 {
+        let now = sequence as f64 * 0.001;
+        assert_eq!(
+            reassembler.insert(&packet(sequence, 0, 3), now).unwrap(),
+            None
+        );
+    }
+    assert_eq!(reassembler.pending(), 4);
+    assert_eq!(reassembler.insert(&packet(96, 1, 3), 0.1).unwrap(), None);
+    assert!(reassembler
+        .insert(&packet(96, 2, 3), 0.1)
+        .unwrap()
+        .is_some());
+    assert_eq!(reassembler.pending(), 3);
+    assert_eq!(reassembler.insert(&packet(0, 1, 3), 0.1).unwrap(), None);
+    assert_eq!(reassembler.pending(), 4);
+
+    // So are the frames remembered as returned.
+    for sequence in 200..210 {
+        let now = 0.2 + sequence as f64 * 0.001;
+        assert!(reassembler
+            .insert(&packet(sequence, 0, 1), now)
+            .unwrap()
+            .is_some());
+    }
+    assert_eq!(reassembler.insert(&packet(209, 0, 1), 0.5).unwrap(), None);
+    assert!(reassembler
+        .insert(&packet(200, 0, 1), 0.5)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn test_varint_and_zigzag() {
+    for value in [0u64, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+        let mut buffer = Vec::new();
+        write_varint(&mut buffer, value);
+        let mut cursor = Cursor::new(&buffer);
+        assert_eq!(cursor.read_varint().unwrap(), value);
+        assert!(cursor.is_empty());
+    }
+    let mut buffer = Vec::new();
+    write_varint(&mut buffer, 300);
+    assert_eq!(buffer, vec![0xac, 0x02]);
+
+    for value in [0i64, -1, 1, -2, i32::MIN as i64, i64::MAX, i64::MIN] {
+        assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+    }
+    assert_eq!(zigzag_encode(-1), 1);
+    assert_eq!(zigzag_encode(1), 2);
+
+    assert_eq!(
+        Cursor::new(&[0xff; 11]).read_varint().unwrap_err(),
+        CodecError::InvalidVarint
+    );
+    let mut overflow = vec![0xff; 9];
+    overflow.push(0x02);
+    assert_eq!(
+        Cursor::new(&overflow).read_varint().unwrap_err(),
+        CodecError::InvalidVarint
+    );
+}
+
+#[test]
+fn test_compact_message_layout() {
+    let schema = schema();
+    let codec = Codec::with_format(&schema, WireFormat::Compact);
+    let message = Message::new()
+        .with("id", 300u32)
+        .with("level", -3)
+        .with("name", "ab");
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![3, 1, 0xac, 0x02, 4, 2, b'a', b'b', 6, 5]);
+    assert_eq!(codec.decode_message("Entity", &buffer).unwrap(), message);
+}
+
+#[test]
+fn test_compact_message_round_trip() {
+    let schema = schema();
+    let codec = Codec::with_format(&schema, WireFormat::Compact);
+    let mut compact = Vec::new();
+    codec
+        .encode_message("Entity", &entity(), &mut compact)
+        .unwrap();
+    assert_eq!(codec.decode_message("Entity", &compact).unwrap(), entity());
+
+    let mut fixed = Vec::new();
+    Codec::new(&schema)
+        .encode_message("Entity", &entity(), &mut fixed)
+        .unwrap();
+    assert!(compact.len() < fixed.len());
+}
+
+#[test]
+fn test_compact_frame_round_trip() {
+    let schema = schema();
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    let frame = Message::new()
+        .with("sequence", 70000u32)
+        .with("complete", true)
+        .with("complete_global", false)
+        .with("reset", false)
+        .with("self_id", 4u32)
+        .with("area", Message::new().with("name", "Home"))
+        .with("y_entities", vec![9u8])
+        .with("entities", vec![Value::Message(entity())])
+        .with("global_entities", Vec::<Value>::new())
+        .with("debug_objects", Vec::<Value>::new());
+    let mut buffer = Vec::new();
+    compact.encode_frame(&frame, &mut buffer).unwrap();
+    assert_eq!(buffer[0], FRAME_VERSION_COMPACT);
+
+    assert_eq!(compact.decode_frame(&buffer).unwrap(), frame);
+    assert_eq!(Codec::new(&schema).decode_frame(&buffer).unwrap(), frame);
+}
+
+const FRAME_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Ping": {
+            "name": "Ping",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"}
+            ]
+        }
+    },
+    "frame": {
+        "version": 7,
+        "compact_version": 8,
+        "sections": [
+            {"name": "tick", "type": "uint32", "label": "required"},
+            {"name": "urgent", "type": "flag"},
+            {"name": "ping", "type": "message", "type_name": "Ping"},
+            {"name": "blob", "type": "bytes", "bit": 9},
+            {"name": "pings", "type": "message", "type_name": "Ping", "label": "repeated"},
+            {"name": "rate", "type": "float"}
+        ]
+    }
+}"#;
+
+#[test]
+fn test_schema_frame_layout() {
+    let schema = NetSchema::from_json(FRAME_SCHEMA_JSON).unwrap();
+    let layout = schema.frame_layout();
+    assert_eq!(layout.section("rate").unwrap().bit, Some(10));
+    assert_eq!(layout.presence_len(), 2);
+
+    let codec = Codec::new(&schema);
+    let ping = Message::new().with("id", 1u32);
+    let frame = Message::new()
+        .with("urgent", true)
+        .with("blob", vec![0xaau8])
+        .with("pings", vec![Value::Message(ping.clone())]);
+    let mut buffer = Vec::new();
+    codec.encode_frame(&frame, &mut buffer).unwrap();
+
+    let mut expected = vec![7, 0b1, 0b10];
+    expected.extend_from_slice(&0u32.to_le_bytes());
+    write_bytes(&mut expected, &[0xaa]);
+    expected.extend_from_slice(&1u32.to_le_bytes());
+    codec.encode_message("Ping", &ping, &mut expected).unwrap();
+    assert_eq!(buffer, expected);
+
+    assert_eq!(
+        codec.decode_frame(&buffer).unwrap(),
+        Message::new()
+            .with("tick", 0u32)
+            .with("urgent", true)
+            .with("blob", vec![0xaau8])
+            .with("pings", vec![Value::Message(ping.clone())])
+    );
+
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    let frame = Message::new()
+        .with("tick", 300u32)
+        .with("urgent", false)
+        .with("ping", ping)
+        .with("pings", Vec::<Value>::new())
+        .with("rate", 20.0f32);
+    let mut buffer = Vec::new();
+    compact.encode_frame(&frame, &mut buffer).unwrap();
+    assert_eq!(buffer[..3], [8, 0b10, 0b100]);
+    assert_eq!(codec.decode_frame(&buffer).unwrap(), frame);
+
+    let mut unknown_bit = buffer.clone();
+    unknown_bit[1] |= 1 << 3;
+    assert_eq!(
+        codec.decode_frame(&unknown_bit).unwrap_err(),
+        CodecError::InvalidPresenceBitmap
+    );
+    let mut legacy_version = buffer;
+    legacy_version[0] = FRAME_VERSION;
+    assert_eq!(
+        codec.decode_frame(&legacy_version).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(FRAME_VERSION)
+    );
+}
+
+#[test]
+fn test_invalid_frame_schemas() {
+    let invalid = |section: &str, reason: &str| SchemaError::InvalidFrameSection {
+        section: section.to_string(),
+        reason: reason.to_string(),
+    };
+    let edited = FRAME_SCHEMA_JSON
+        .replace(r#""compact_version": 8"#, r#""compact_version": 7"#)
+        .replace(
+            r#""type_name": "Ping", "label""#,
+            r#""type_name": "Pong", "label""#,
+        )
+        .replace(r#""bit": 9"#, r#""bit": 0"#)
+        .replace(r#""name": "rate""#, r#""name": "tick""#);
+    assert_eq!(
+        validate_json(&edited).unwrap_err(),
+        vec![
+            SchemaError::DuplicateFrameVersion(7),
+            invalid("blob", "bit is already used by urgent"),
+            invalid("pings", "unknown message type Pong"),
+            invalid("tick", "declared more than once"),
+            // Bits after an explicit one continue from it.
+            invalid("tick", "bit is already used by ping"),
+        ]
+    );
+
+    for (from, to, reason) in [
+        (
+            r#""type": "flag""#,
+            r#""type": "flag", "label": "required""#,
+            "required is not supported for flag sections",
+        ),
+        (
+            r#""type": "float""#,
+            r#""type": "float", "label": "repeated""#,
+            "repeated is not supported for float sections",
+        ),
+        (
+            r#""type": "float""#,
+            r#""type": "double""#,
+            "unsupported section type: double",
+        ),
+        (
+            r#""label": "required""#,
+            r#""label": "required", "bit": 3"#,
+            "only flags and optional sections have a bit",
+        ),
+        (r#""bit": 9"#, r#""bit": 255"#, "ran out of presence bits"),
+    ] {
+        let section = match reason {
+            "required is not supported for flag sections" => "urgent",
+            "only flags and optional sections have a bit" => "tick",
+            _ => "rate",
+        };
+        assert_eq!(
+            validate_json(&FRAME_SCHEMA_JSON.replace(from, to)).unwrap_err(),
+            vec![invalid(section, reason)],
+        );
+    }
+}
+
+const VERSIONED_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Ping": {
+            "name": "Ping",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "note", "number": 2, "type": "string", "label": "optional", "since": 3}
+            ]
+        },
+        "Pos": {
+            "name": "Pos",
+            "layout": "bitmap",
+            "fields": [
+                {"name": "x", "number": 1, "type": "float", "label": "optional"},
+                {"name": "z", "number": 3, "type": "float", "label": "optional", "since": 3},
+                {"name": "y", "number": 2, "type": "float", "label": "optional"}
+            ]
+        }
+    },
+    "frame": {
+        "version": 3,
+        "compact_version": 4,
+        "sections": [
+            {"name": "tick", "type": "uint32", "label": "required"},
+            {"name": "ping", "type": "message", "type_name": "Ping"},
+            {"name": "rate", "type": "float"},
+            {"name": "positions", "type": "message", "type_name": "Pos", "label": "repeated"}
+        ]
+    },
+    "legacy_frames": [
+        {
+            "version": 1,
+            "compact_version": 2,
+            "sections": [
+                {"name": "tick", "type": "uint32", "label": "required"},
+                {"name": "ping", "type": "message", "type_name": "Ping"},
+                {"name": "load", "type": "float"},
+                {"name": "positions", "type": "message", "type_name": "Pos", "label": "repeated"}
+            ]
+        }
+    ]
+}"#;
+
+/// The schema a peer that only knows protocol 1 would have.
+const PROTOCOL_1_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Ping": {
+            "name": "Ping",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"}
+            ]
+        },
+        "Pos": {
+            "name": "Pos",
+            "layout": "bitmap",
+            "fields": [
+                {"name": "x", "number": 1, "type": "float", "label": "optional"},
+                {"name": "y", "number": 2, "type": "float", "label": "optional"}
+            ]
+        }
+    },
+    "frame": {
+        "version": 1,
+        "compact_version": 2,
+        "sections": [
+            {"name": "tick", "type": "uint32", "label": "required"},
+            {"name": "ping", "type": "message", "type_name": "Ping"},
+            {"name": "load", "type": "float"},
+            {"name": "positions", "type": "message", "type_name": "Pos", "label": "repeated"}
+        ]
+    }
+}"#;
+
+#[test]
+fn test_frames_in_older_protocols() {
+    let schema = validate_json(VERSIONED_SCHEMA_JSON).unwrap();
+    let old_schema = validate_json(PROTOCOL_1_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    assert_eq!(codec.protocol(), 3);
+    let old_peer = Codec::new(&old_schema);
+
+    let frame = Message::new()
+        .with("tick", 9u32)
+        .with("ping", Message::new().with("id", 1u32).with("note", "hi"))
+        .with("rate", 30.0f32)
+        .with(
+            "positions",
+            vec![Value::Message(
+                Message::new()
+                    .with("x", 1.0f32)
+                    .with("y", 2.0f32)
+                    .with("z", 3.0f32),
+            )],
+        );
+    // "load" was dropped from the current protocol.
+    let old_frame = Message::new()
+        .with("tick", 9u32)
+        .with("ping", Message::new().with("id", 1u32))
+        .with("load", 0.5f32)
+        .with(
+            "positions",
+            vec![Value::Message(
+                Message::new().with("x", 1.0f32).with("y", 2.0f32),
+            )],
+        );
+
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let mut buffer = Vec::new();
+        Codec::with_format(&schema, format)
+            .with_protocol(1)
+            .unwrap()
+            .encode_frame(&frame.clone().with("load", 0.5f32), &mut buffer)
+            .unwrap();
+        let mut expected = Vec::new();
+        Codec::with_format(&old_schema, format)
+            .encode_frame(&old_frame, &mut expected)
+            .unwrap();
+        assert_eq!(buffer, expected);
+        assert_eq!(old_peer.decode_frame(&buffer).unwrap(), old_frame);
+        assert_eq!(codec.decode_frame(&buffer).unwrap(), old_frame);
+    }
+
+    let mut buffer = Vec::new();
+    codec.encode_frame(&frame, &mut buffer).unwrap();
+    assert_eq!(buffer[0], 3);
+    assert_eq!(codec.decode_frame(&buffer).unwrap(), frame);
+    assert_eq!(
+        old_peer.decode_frame(&buffer).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(3)
+    );
+
+    assert_eq!(
+        codec.with_protocol(5).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(5)
+    );
+    assert_eq!(
+        codec.with_protocol(2).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(2)
+    );
+}
+
+#[test]
+fn test_newer_fields_are_skipped_by_older_protocols() {
+    let schema = validate_json(VERSIONED_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let legacy = codec.with_protocol(1).unwrap();
+
+    let ping = Message::new().with("id", 1u32).with("note", "hi");
+    let mut buffer = Vec::new();
+    codec.encode_message("Ping", &ping, &mut buffer).unwrap();
+    assert_eq!(codec.decode_message("Ping", &buffer).unwrap(), ping);
+    assert_eq!(
+        legacy.decode_message("Ping", &buffer).unwrap(),
+        Message::new().with("id", 1u32)
+    );
+
+    // Bitmap messages only have presence bits for the protocol's fields.
+    let pos = Message::new().with("y", 2.0f32).with("z", 3.0f32);
+    let mut buffer = Vec::new();
+    legacy.encode_message("Pos", &pos, &mut buffer).unwrap();
+    let mut expected = vec![0b10];
+    expected.extend_from_slice(&2.0f32.to_le_bytes());
+    assert_eq!(buffer, expected);
+    assert_eq!(
+        legacy.decode_message("Pos", &buffer).unwrap(),
+        Message::new().with("y", 2.0f32)
+    );
+}
+
+#[test]
+fn test_invalid_protocol_versions() {
+    let edited = VERSIONED_SCHEMA_JSON
+        .replace(r#""since": 3"#, r#""since": 4"#)
+        .replace(r#""compact_version": 2"#, r#""compact_version": 4"#);
+    assert_eq!(
+        validate_json(&edited).unwrap_err(),
+        vec![
+            SchemaError::InvalidFieldVersion {
+                message: "Ping".to_string(),
+                field: "note".to_string(),
+                since: 4,
+            },
+            SchemaError::InvalidFieldVersion {
+                message: "Pos".to_string(),
+                field: "z".to_string(),
+                since: 4,
+            },
+            SchemaError::DuplicateFrameVersion(4),
+        ]
+    );
+
+    let edited = VERSIONED_SCHEMA_JSON
+        .replace(r#""version": 1"#, r#""version": 5"#)
+        .replace(r#""compact_version": 2"#, r#""compact_version": 6"#);
+    assert_eq!(
+        validate_json(&edited).unwrap_err(),
+        vec![SchemaError::LegacyFrameVersion {
+            version: 5,
+            current: 3,
+        }]
+    );
+
+    let edited =
+        VERSIONED_SCHEMA_JSON.replace(r#""compact_version": 2"#, r#""compact_version": 130"#);
+    assert_eq!(
+        validate_json(&edited).unwrap_err(),
+        vec![SchemaError::ReservedFrameVersion(130)]
+    );
+}
+
+const WIDE_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Account": {
+            "name": "Account",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint64", "label": "optional"},
+                {"name": "balance", "number": 2, "type": "sint64", "label": "optional"},
+                {"name": "created_at", "number": 3, "type": "fixed64", "label": "optional"},
+                {"name": "offset", "number": 4, "type": "sfixed64", "label": "optional"},
+                {"name": "rating", "number": 5, "type": "double", "label": "optional"},
+                {"name": "history", "number": 6, "type": "int64", "label": "repeated"}
+            ]
+        }
+    }
+}"#;
+
+fn account() -> Message {
+    Message::new()
+        .with("id", u64::MAX)
+        .with("balance", -5_000_000_000i64)
+        .with("created_at", 1_700_000_000_000u64)
+        .with("offset", i64::MIN)
+        .with("rating", 0.1f64)
+        .with("history", vec![Value::Int64(-1), Value::Int64(i64::MAX)])
+}
+
+#[test]
+fn test_64_bit_field_layout() {
+    let schema = NetSchema::from_json(WIDE_SCHEMA_JSON).unwrap();
+    let message = Message::new()
+        .with("id", 1u64 << 32)
+        .with("balance", -1i64)
+        .with("rating", 1.5f64);
+
+    let mut buffer = Vec::new();
+    Codec::new(&schema)
+        .encode_message("Account", &message, &mut buffer)
+        .unwrap();
+    let mut expected = vec![3, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0];
+    expected.extend_from_slice(&(-1i64).to_le_bytes());
+    expected.extend_from_slice(&[5, 0]);
+    expected.extend_from_slice(&1.5f64.to_le_bytes());
+    assert_eq!(buffer, expected);
+
+    let mut compact = Vec::new();
+    Codec::with_format(&schema, WireFormat::Compact)
+        .encode_message("Account", &message, &mut compact)
+        .unwrap();
+    let mut expected = vec![3, 1, 0x80, 0x80, 0x80, 0x80, 0x10, 2, 1, 5];
+    expected.extend_from_slice(&1.5f64.to_le_bytes());
+    assert_eq!(compact, expected);
+}
+
+#[test]
+fn test_64_bit_round_trip() {
+    let schema = NetSchema::from_json(WIDE_SCHEMA_JSON).unwrap();
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let codec = Codec::with_format(&schema, format);
+        let mut buffer = Vec::new();
+        codec
+            .encode_message("Account", &account(), &mut buffer)
+            .unwrap();
+        assert_eq!(codec.decode_message("Account", &buffer).unwrap(), account());
+    }
+
+    // Narrower values are widened, but never wrapped.
+    let codec = Codec::new(&schema);
+    let message = Message::new().with("id", 7u32).with("rating", 2.5f32);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Account", &message, &mut buffer)
+        .unwrap();
+    let decoded = codec.decode_message("Account", &buffer).unwrap();
+    assert_eq!(decoded.get("id"), Some(&Value::UInt64(7)));
+    assert_eq!(decoded.get("rating"), Some(&Value::Double(2.5)));
+
+    for message in [
+        Message::new().with("id", -1),
+        Message::new().with("id", -1i64),
+        Message::new().with("balance", u64::MAX),
+        Message::new().with("rating", "fast"),
+    ] {
+        assert!(matches!(
+            codec.encode_message("Account", &message, &mut Vec::new()),
+            Err(CodecError::InvalidValue { .. })
+        ));
+    }
+}
+
+const MAP_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Player": {
+            "name": "Player",
+            "fields": [
+                {"name": "scores", "number": 1, "type": "map", "label": "optional",
+                 "key_type": "string", "value_type": "sint32"},
+                {"name": "items", "number": 2, "type": "map", "label": "optional",
+                 "key_type": "uint32", "value_type": "message", "type_name": "Item"},
+                {"name": "target_id", "number": 3, "type": "uint32", "label": "optional",
+                 "oneof": "target"},
+                {"name": "target_name", "number": 4, "type": "string", "label": "optional",
+                 "oneof": "target"}
+            ]
+        },
+        "Item": {
+            "name": "Item",
+            "fields": [
+                {"name": "count", "number": 1, "type": "uint32", "label": "optional"}
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn test_map_field_layout() {
+    let schema = NetSchema::from_json(MAP_SCHEMA_JSON).unwrap();
+    let message = Message::new().with(
+        "scores",
+        vec![
+            (Value::from("a"), Value::Int32(-1)),
+            (Value::from("b"), Value::Int32(2)),
+        ],
+    );
+    let mut buffer = Vec::new();
+    Codec::with_format(&schema, WireFormat::Compact)
+        .encode_message("Player", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![1, 1, 2, 1, b'a', 1, 1, b'b', 4]);
+
+    let message = Message::new()
+        .with("scores", Vec::<(Value, Value)>::new())
+        .with(
+            "items",
+            vec![(
+                Value::UInt32(7),
+                Value::Message(Message::new().with("count", 3u32)),
+            )],
+        )
+        .with("target_name", "boss");
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let codec = Codec::with_format(&schema, format);
+        let mut buffer = Vec::new();
+        codec
+            .encode_message("Player", &message, &mut buffer)
+            .unwrap();
+        assert_eq!(codec.decode_message("Player", &buffer).unwrap(), message);
+    }
+
+    let codec = Codec::new(&schema);
+    for message in [
+        Message::new().with("scores", vec![Value::Int32(1)]),
+        Message::new().with("scores", vec![(Value::Int32(1), Value::Int32(1))]),
+        Message::new().with("items", vec![(Value::UInt32(1), Value::UInt32(1))]),
+    ] {
+        assert!(matches!(
+            codec.encode_message("Player", &message, &mut Vec::new()),
+            Err(CodecError::InvalidValue { .. })
+        ));
+    }
+
+    let entries = (0..=u16::MAX as u32 + 1)
+        .map(|key| (Value::UInt32(key), Value::Message(Message::new())))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        codec.encode_message(
+            "Player",
+            &Message::new().with("items", entries),
+            &mut Vec::new()
+        ),
+        Err(CodecError::invalid_value(
+            "items",
+            "at most 65535 map entries"
+        ))
+    );
+}
+
+#[test]
+fn test_oneof_allows_at_most_one_member() {
+    let schema = NetSchema::from_json(MAP_SCHEMA_JSON).unwrap();
+    let player = &schema.messages["Player"];
+    assert_eq!(player.oneofs.len(), 1);
+    assert_eq!(player.oneofs[0].fields, vec!["target_id", "target_name"]);
+
+    let codec = Codec::new(&schema);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Player", &Message::new(), &mut buffer)
+        .unwrap();
+    assert_eq!(
+        codec.decode_message("Player", &buffer).unwrap(),
+        Message::new()
+    );
+
+    let conflict = Message::new()
+        .with("target_id", 1u32)
+        .with("hero", Message::new().with("target_name", "boss"));
+    assert_eq!(
+        codec.encode_message("Player", &conflict, &mut Vec::new()),
+        Err(CodecError::OneofConflict("target".to_string()))
+    );
+
+    let mut buffer = vec![2, 0, 3, 0, 1, 0, 0, 0, 4, 0];
+    write_bytes(&mut buffer, b"boss");
+    assert_eq!(
+        codec.decode_message("Player", &buffer),
+        Err(CodecError::OneofConflict("target".to_string()))
+    );
+}
+
+#[test]
+fn test_invalid_map_and_oneof_schemas() {
+    let field = |extra: &str| {
+        format!(
+            r#"{{"messages": {{"A": {{"name": "A", "fields": [
+                {{"name": "a", "number": 1, {extra}}}
+            ]}}}}}}"#
+        )
+    };
+    let map_error = |raw: &str| match validate_json(raw).unwrap_err().remove(0) {
+        SchemaError::InvalidMap { reason, .. } => reason,
+        other => panic!("unexpected error: {other}"),
+    };
+    assert_eq!(
+        map_error(&field(
+            r#""type": "map", "label": "optional", "key_type": "string""#
+        )),
+        "map fields need a key_type and a value_type"
+    );
+    assert_eq!(
+        map_error(&field(
+            r#""type": "map", "label": "optional", "key_type": "float", "value_type": "bool""#
+        )),
+        "unsupported map key type: float"
+    );
+    assert_eq!(
+        map_error(&field(
+            r#""type": "map", "label": "repeated", "key_type": "bool", "value_type": "bool""#
+        )),
+        "map fields cannot be repeated"
+    );
+    assert_eq!(
+        validate_json(&field(
+            r#""type": "map", "label": "optional", "key_type": "bool", "value_type": "message", "type_name": "B""#
+        ))
+        .unwrap_err(),
+        vec![SchemaError::UnknownMessageType {
+            message: "A".to_string(),
+            field: "a".to_string(),
+            type_name: "B".to_string(),
+        }]
+    );
+    assert_eq!(
+        validate_json(&field(
+            r#""type": "uint32", "label": "repeated", "oneof": "o""#
+        ))
+        .unwrap_err(),
+        vec![SchemaError::InvalidOneof {
+            message: "A".to_string(),
+            field: "a".to_string(),
+            oneof: "o".to_string(),
+        }]
+    );
+
+    let old = NetSchema::from_json(MAP_SCHEMA_JSON).unwrap();
+    let new = NetSchema::from_json(
+        &MAP_SCHEMA_JSON
+            .replace(r#""value_type": "sint32""#, r#""value_type": "uint32""#)
+            .replace(
+                r#""type": "string", "label": "optional",
+                 "oneof": "target""#,
+                r#""type": "string", "label": "optional""#,
+            ),
+    )
+    .unwrap();
+    assert_eq!(
+        compare(&old, &new),
+        vec![
+            BreakingChange::ChangedMapTypes {
+                message: "Player".to_string(),
+                field: "scores".to_string(),
+                old: (FieldKind::String, FieldKind::Int32),
+                new: (FieldKind::String, FieldKind::UInt32),
+            },
+            BreakingChange::ChangedOneof {
+                message: "Player".to_string(),
+                field: "target_name".to_string(),
+                old: Some("target".to_string()),
+                new: None,
+            },
+        ]
+    );
+}
+
+const QUANTIZED_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Sample": {
+            "name": "Sample",
+            "fields": [
+                {"name": "health", "number": 1, "type": "float", "label": "optional",
+                 "quantize": {"min": 0, "max": 100, "bits": 8}},
+                {"name": "angle", "number": 2, "type": "float", "label": "optional",
+                 "quantize": {"scale": 10, "bits": 16}},
+                {"name": "path", "number": 3, "type": "float", "label": "repeated",
+                 "quantize": {"scale": 256, "bits": 24}}
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn test_quantized_field_layout() {
+    let schema = NetSchema::from_json(QUANTIZED_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let message = Message::new()
+        .with("health", 50.0f32)
+        .with("angle", -1.25f32)
+        .with("path", vec![Value::Float(1.5), Value::Float(-0.5)]);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Sample", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        vec![
+            3, 0, // field count
+            1, 0, 128, // 50 / 100 * 255 = 127.5, rounded away from zero
+            2, 0, 0xf3, 0xff, // -12.5 -> -13
+            3, 0, 2, 0, 0x80, 0x01, 0x00, 0x80, 0xff, 0xff,
+        ]
+    );
+
+    let decoded = codec.decode_message("Sample", &buffer).unwrap();
+    assert!((decoded.get("health").unwrap().as_f32().unwrap() - 50.0).abs() < 0.2);
+    assert_eq!(decoded.get("angle"), Some(&Value::Float(-1.3)));
+    assert_eq!(
+        decoded.get("path"),
+        Some(&Value::List(vec![Value::Float(1.5), Value::Float(-0.5)]))
+    );
+
+    let mut compact = Vec::new();
+    Codec::with_format(&schema, WireFormat::Compact)
+        .encode_message("Sample", &message, &mut compact)
+        .unwrap();
+    assert_eq!(&compact[2..3], &[128]);
+}
+
+#[test]
+fn test_quantized_values_saturate() {
+    let schema = NetSchema::from_json(QUANTIZED_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let message = Message::new()
+        .with("health", 250.0f32)
+        .with("angle", -5000.0f32);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Sample", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![2, 0, 1, 0, 255, 2, 0, 0x00, 0x80]);
+
+    let decoded = codec.decode_message("Sample", &buffer).unwrap();
+    assert_eq!(decoded.get("health"), Some(&Value::Float(100.0)));
+    assert_eq!(decoded.get("angle"), Some(&Value::Float(-3276.8)));
+
+    let message = Message::new().with("health", -1.0f32);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Sample", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![1, 0, 1, 0, 0]);
+    assert_eq!(
+        codec.decode_message("Sample", &buffer[..4]),
+        Err(CodecError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_invalid_quantization_is_rejected() {
+    let field = |field_type: &str, quantize: &str| {
+        format!(
+            r#"{{"messages": {{"A": {{"name": "A", "fields": [
+                {{"name": "a", "number": 1, "type": "{field_type}", "label": "optional", "quantize": {quantize}}}
+            ]}}}}}}"#
+        )
+    };
+    assert!(NetSchema::from_json(&field("float", r#"{"min": 0, "max": 1, "bits": 8}"#)).is_ok());
+    assert!(NetSchema::from_json(&field("float", r#"{"min": 0, "max": 1, "bits": 12}"#)).is_err());
+    assert!(NetSchema::from_json(&field("float", r#"{"min": 1, "max": 1, "bits": 8}"#)).is_err());
+    assert!(NetSchema::from_json(&field("float", r#"{"scale": 0, "bits": 16}"#)).is_err());
+    assert!(NetSchema::from_json(&field("int32", r#"{"scale": 10, "bits": 16}"#)).is_err());
+}
+
+const BITMAP_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Entity": {
+            "name": "Entity",
+            "layout": "bitmap",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "name", "number": 9, "type": "string", "label": "optional"},
+                {"name": "x", "number": 2, "type": "float", "label": "optional"},
+                {"name": "f3", "number": 3, "type": "bool", "label": "optional"},
+                {"name": "f4", "number": 4, "type": "bool", "label": "optional"},
+                {"name": "f5", "number": 5, "type": "bool", "label": "optional"},
+                {"name": "f6", "number": 6, "type": "bool", "label": "optional"},
+                {"name": "f7", "number": 7, "type": "bool", "label": "optional"},
+                {"name": "effects", "number": 8, "type": "uint32", "label": "repeated"}
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn test_bitmap_message_layout() {
+    let schema = NetSchema::from_json(BITMAP_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let message = Message::new()
+        .with("effects", vec![Value::UInt32(5)])
+        .with("name", "a")
+        .with("hero", Message::new().with("id", 7u32));
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        vec![
+            0x03, 0x01, // id, name, effects
+            7, 0, 0, 0, // id
+            1, 0, 0, 0, b'a', // name
+            1, 0, 5, 0, 0, 0, // effects
+        ]
+    );
+
+    let decoded = codec.decode_message("Entity", &buffer).unwrap();
+    assert_eq!(
+        decoded,
+        Message::new()
+            .with("id", 7u32)
+            .with("name", "a")
+            .with("effects", vec![Value::UInt32(5)])
+    );
+
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    let mut buffer = Vec::new();
+    compact
+        .encode_message("Entity", &decoded, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, vec![0x03, 0x01, 7, 1, b'a', 1, 5]);
+    assert_eq!(compact.decode_message("Entity", &buffer).unwrap(), decoded);
+
+    let mut empty = Vec::new();
+    codec
+        .encode_message("Entity", &Message::new(), &mut empty)
+        .unwrap();
+    assert_eq!(empty, vec![0, 0]);
+}
+
+#[test]
+fn test_bitmap_decode_errors() {
+    let schema = NetSchema::from_json(BITMAP_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    assert_eq!(
+        codec.decode_message("Entity", &[0x00, 0x02]),
+        Err(CodecError::InvalidPresenceBitmap)
+    );
+    assert_eq!(
+        codec.decode_message("Entity", &[0x00]),
+        Err(CodecError::UnexpectedEnd)
+    );
+    assert_eq!(
+        codec.decode_message("Entity", &[0x01, 0x00, 7]),
+        Err(CodecError::UnexpectedEnd)
+    );
+
+    let unknown_layout = BITMAP_SCHEMA_JSON.replace("\"bitmap\"", "\"packed\"");
+    assert!(NetSchema::from_json(&unknown_layout).is_err());
+}
+
+fn positioned(id: u32, x: f32) -> Message {
+    Message::new().with("id", id).with("x", x).with("y", 0.0f32)
+}
+
+const TYPED_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Event": {
+            "name": "Event",
+            "layout": "typed",
+            "fields": [
+                {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "name", "number": 2, "type": "string", "label": "optional"},
+                {"name": "tags", "number": 3, "type": "uint32", "label": "repeated"}
+            ]
+        }
+    }
+}"#;
+
+fn typed_event() -> Message {
+    Message::new()
+        .with("id", 7u32)
+        .with("name", "a")
+        .with("tags", vec![Value::UInt32(1)])
+}
+
+#[test]
+fn test_typed_message_layout() {
+    let schema = NetSchema::from_json(TYPED_SCHEMA_JSON).unwrap();
+    let mut buffer = Vec::new();
+    Codec::new(&schema)
+        .encode_message("Event", &typed_event(), &mut buffer)
+        .unwrap();
+    let mut expected = vec![3, 0];
+    expected.extend_from_slice(&(1 << 3 | WireType::Fixed32 as u32).to_le_bytes());
+    expected.extend_from_slice(&7u32.to_le_bytes());
+    expected.extend_from_slice(&(2 << 3 | WireType::Len as u32).to_le_bytes());
+    write_bytes(&mut expected, b"a");
+    expected.extend_from_slice(&(3 << 3 | WireType::Len as u32).to_le_bytes());
+    write_bytes(&mut expected, &[1, 0, 1, 0, 0, 0]);
+    assert_eq!(buffer, expected);
+
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    let mut buffer = Vec::new();
+    compact
+        .encode_message("Event", &typed_event(), &mut buffer)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        vec![3, 1 << 3, 7, 2 << 3 | 2, 1, b'a', 3 << 3 | 2, 2, 1, 1]
+    );
+    assert_eq!(
+        compact.decode_message("Event", &buffer).unwrap(),
+        typed_event()
+    );
+}
+
+#[test]
+fn test_typed_decode_skips_unknown_fields() {
+    let schema = NetSchema::from_json(TYPED_SCHEMA_JSON).unwrap();
+    let older = NetSchema::from_json(&TYPED_SCHEMA_JSON.replace(
+        r#"},
+                {"name": "name", "number": 2, "type": "string", "label": "optional"},
+                {"name": "tags", "number": 3, "type": "uint32", "label": "repeated"}"#,
+        "}",
+    ))
+    .unwrap();
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let mut buffer = Vec::new();
+        Codec::with_format(&schema, format)
+            .encode_message("Event", &typed_event(), &mut buffer)
+            .unwrap();
+        assert_eq!(
+            Codec::with_format(&older, format)
+                .decode_message("Event", &buffer)
+                .unwrap(),
+            Message::new().with("id", 7u32)
+        );
+    }
+}
+
+#[test]
+fn test_typed_decode_errors() {
+    let schema = NetSchema::from_json(TYPED_SCHEMA_JSON).unwrap();
+    let compact = Codec::with_format(&schema, WireFormat::Compact);
+    let mut buffer = Vec::new();
+    compact
+        .encode_message("Event", &Message::new().with("name", "a"), &mut buffer)
+        .unwrap();
+    let changed = NetSchema::from_json(&TYPED_SCHEMA_JSON.replace(
+        r#""name": "name", "number": 2, "type": "string""#,
+        r#""name": "name", "number": 2, "type": "uint32""#,
+    ))
+    .unwrap();
+    assert_eq!(
+        Codec::with_format(&changed, WireFormat::Compact).decode_message("Event", &buffer),
+        Err(CodecError::WireTypeMismatch("name".to_string()))
+    );
+
+    assert_eq!(
+        compact.decode_message("Event", &[1, 4 << 3 | 3]),
+        Err(CodecError::InvalidFieldKey(4 << 3 | 3))
+    );
+    // The nested list claims more bytes than its length prefix holds.
+    assert_eq!(
+        compact.decode_message("Event", &[1, 3 << 3 | 2, 1, 1]),
+        Err(CodecError::UnexpectedEnd)
+    );
+    assert_eq!(
+        compact.decode_message("Event", &[1, 3 << 3 | 2, 3, 1, 1, 0]),
+        Err(CodecError::TrailingBytes(1))
+    );
+}
+
+#[test]
+fn test_message_ref_borrows_from_the_buffer() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let area = Message::new()
+        .with("name", "Central Core")
+        .with("data", vec![1u8, 2, 3]);
+    let message = entity().with("area", area);
+    let mut buffer = Vec::new();
+    codec
+        .encode_message("Entity", &message, &mut buffer)
+        .unwrap();
+
+    let view = codec.decode_message_ref("Entity", &buffer).unwrap();
+    assert_eq!(view.len(), message.len());
+    assert_eq!(view.as_bytes(), &buffer[..]);
+    let name = view.get("name").unwrap().unwrap().as_str().unwrap();
+    assert_eq!(name, "hero");
+    assert!(buffer.as_ptr_range().contains(&name.as_ptr()));
+    let area = view.get("area").unwrap().unwrap();
+    let data = area.as_message().unwrap().get("data").unwrap().unwrap();
+    let data = data.as_bytes().unwrap();
+    assert_eq!(data, &[1, 2, 3]);
+    assert!(buffer.as_ptr_range().contains(&data.as_ptr()));
+    assert!(view.get("hero").unwrap().is_none());
+
+    assert_eq!(view.to_message().unwrap(), message);
+    let fields: Message = view
+        .iter()
+        .map(|entry| {
+            let (name, value) = entry.unwrap();
+            (name, value.to_value().unwrap())
+        })
+        .collect();
+    assert_eq!(fields, message);
+}
+
+#[test]
+fn test_message_ref_reads_every_layout() {
+    let bitmap = NetSchema::from_json(BITMAP_SCHEMA_JSON).unwrap();
+    let typed = NetSchema::from_json(TYPED_SCHEMA_JSON).unwrap();
+    let bitmap_entity = Message::new()
+        .with("id", 7u32)
+        .with("name", "a")
+        .with("effects", vec![Value::UInt32(5)]);
+    let cases = [
+        (&bitmap, "Entity", bitmap_entity),
+        (&typed, "Event", typed_event()),
+    ];
+    for (schema, name, message) in cases {
+        for format in [WireFormat::Fixed, WireFormat::Compact] {
+            let codec = Codec::with_format(schema, format);
+            let mut buffer = Vec::new();
+            codec.encode_message(name, &message, &mut buffer).unwrap();
+            let view = codec.decode_message_ref(name, &buffer).unwrap();
+            assert_eq!(view.to_message().unwrap(), message);
+            assert_eq!(view.get("name").unwrap().unwrap().as_str(), Some("a"));
+        }
+    }
+
+    let codec = Codec::new(&bitmap);
+    assert_eq!(
+        codec.decode_message_ref("Entity", &[0x00, 0x02]).err(),
+        Some(CodecError::InvalidPresenceBitmap)
+    );
+    assert_eq!(
+        codec.decode_message_ref("Entity", &[0x01, 0x00, 7]).err(),
+        Some(CodecError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_message_ref_checks_nested_oneofs() {
+    let schema = NetSchema::from_json(&MAP_SCHEMA_JSON.replace(
+        r#"{"name": "count", "number": 1, "type": "uint32", "label": "optional"}"#,
+        r#"{"name": "count", "number": 1, "type": "uint32", "label": "optional"},
+                {"name": "owner", "number": 2, "type": "message", "label": "optional", "type_name": "Player"}"#,
+    ))
+    .unwrap();
+    let codec = Codec::new(&schema);
+
+    // An Item whose owner sets target_id only, then both target members.
+    let buffer = [1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 0, 0];
+    let item = codec.decode_message_ref("Item", &buffer).unwrap();
+    assert_eq!(
+        item.to_message().unwrap(),
+        Message::new().with("owner", Message::new().with("target_id", 1u32))
+    );
+
+    let mut buffer = vec![1, 0, 2, 0, 2, 0, 3, 0, 1, 0, 0, 0, 4, 0];
+    write_bytes(&mut buffer, b"boss");
+    assert_eq!(
+        codec.decode_message_ref("Item", &buffer).err(),
+        Some(CodecError::OneofConflict("target".to_string()))
+    );
+}
+
+#[test]
+fn test_frame_ref_borrows_entity_blobs() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let frame = Message::new()
+        .with("sequence", 12u32)
+        .with("reset", true)
+        .with("area", Message::new().with("name", "Home"))
+        .with("x_entities", vec![1u8, 2])
+        .with("xy_entities", vec![3u8, 4, 5])
+        .with("entities", vec![Value::Message(entity())]);
+    let mut buffer = Vec::new();
+    codec.encode_frame(&frame, &mut buffer).unwrap();
+
+    let view = codec.decode_frame_ref(&buffer).unwrap();
+    let xy = view.get("xy_entities").unwrap().as_bytes().unwrap();
+    assert_eq!(xy, &[3, 4, 5]);
+    assert!(buffer.as_ptr_range().contains(&xy.as_ptr()));
+    assert!(view.get("self_id").is_none());
+    let entities = view.get("entities").unwrap().as_list().unwrap();
+    assert_eq!(
+        entities[0].as_message().unwrap().to_message().unwrap(),
+        entity()
+    );
+    assert_eq!(
+        view.to_message().unwrap(),
+        codec.decode_frame(&buffer).unwrap()
+    );
+
+    assert_eq!(
+        codec.decode_frame_ref(&buffer[..buffer.len() - 1]).err(),
+        Some(CodecError::UnexpectedEnd)
+    );
+    buffer.push(0);
+    assert_eq!(
+        codec.decode_frame_ref(&buffer).err(),
+        Some(CodecError::TrailingBytes(1))
+    );
+}
+
+fn position(id: u32, x: f32, y: f32, radius: f32) -> PositionUpdate {
+    PositionUpdate { id, x, y, radius }
+}
+
+#[test]
+fn test_position_streams_layout() {
+    let mut encoder = PositionEncoder::default();
+    let streams = encoder.encode(&[position(5, 2.5, -1.0, 3.0), position(1, 0.0, 0.0, 1.0)]);
+    assert_eq!(
+        streams,
+        PositionStreams {
+            // count, ids 1 and 5, then the x, y and radius columns.
+            xy_radius: vec![2, 1, 3, 0, 80, 0, 31, 32, 96],
+            ..PositionStreams::default()
+        }
+    );
+
+    let streams = encoder.encode(&[
+        position(1, 0.5, 0.0, 1.0),
+        position(5, 2.5, -1.0, 3.0),
+        position(9, 0.0, 0.0, 0.0),
+    ]);
+    assert_eq!(streams.x, vec![1, 1, 16]);
+    assert!(streams.y.is_empty() && streams.xy.is_empty());
+    assert_eq!(streams.xy_radius, vec![1, 9, 0, 0, 0]);
+}
+
+#[test]
+fn test_position_streams_round_trip() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut encoder = PositionEncoder::default();
+    let mut decoder = PositionDecoder::default();
+    let ticks = [
+        vec![position(1, 10.0, 20.0, 5.0), position(2, -3.0, 4.0, 1.0)],
+        vec![position(1, 10.5, 20.0, 5.0), position(2, -3.0, 4.0, 1.0)],
+        vec![position(1, 10.5, 19.0, 5.0), position(2, -2.0, 5.0, 1.0)],
+        vec![position(1, 10.5, 19.0, 6.0), position(3, 1.0, 1.0, 1.0)],
+    ];
+    let expected = [
+        vec![position(1, 10.0, 20.0, 5.0), position(2, -3.0, 4.0, 1.0)],
+        vec![position(1, 10.5, 20.0, 5.0)],
+        vec![position(1, 10.5, 19.0, 5.0), position(2, -2.0, 5.0, 1.0)],
+        vec![position(1, 10.5, 19.0, 6.0), position(3, 1.0, 1.0, 1.0)],
+    ];
+    for (updates, expected) in ticks.iter().zip(expected) {
+        let mut frame = Message::new();
+        encoder.encode(updates).write_to_frame(&mut frame);
+        let mut buffer = Vec::new();
+        codec.encode_frame(&frame, &mut buffer).unwrap();
+        let decoded = codec.decode_frame(&buffer).unwrap();
+        let streams = PositionStreams::from_frame(&decoded).unwrap();
+        assert_eq!(decoder.decode(&streams).unwrap(), expected);
+    }
+    assert_eq!(decoder.get(2), Some(position(2, -2.0, 5.0, 1.0)));
+
+    // Positions are quantized to the configured scale.
+    let mut encoder = PositionEncoder::new(PositionConfig {
+        position_scale: 2.0,
+        radius_scale: 1.0,
+    });
+    let mut decoder = PositionDecoder::new(encoder.config());
+    let streams = encoder.encode(&[position(4, 1.3, -0.2, 2.6)]);
+    assert_eq!(
+        decoder.decode(&streams).unwrap(),
+        vec![position(4, 1.5, 0.0, 3.0)]
+    );
+}
+
+#[test]
+fn test_position_stream_errors() {
+    let mut encoder = PositionEncoder::default();
+    encoder.encode(&[position(3, 0.0, 0.0, 1.0)]);
+    let moved = encoder.encode(&[position(3, 1.0, 0.0, 1.0)]);
+    assert_eq!(
+        PositionDecoder::default().decode(&moved),
+        Err(CodecError::UnknownEntity(3))
+    );
+
+    let xy_radius = |bytes: Vec<u8>| PositionStreams {
+        xy_radius: bytes,
+        ..PositionStreams::default()
+    };
+    let mut decoder = PositionDecoder::default();
+    assert_eq!(
+        decoder.decode(&xy_radius(vec![1, 3, 0, 0])),
+        Err(CodecError::UnexpectedEnd)
+    );
+    assert_eq!(
+        decoder.decode(&xy_radius(vec![1, 3, 0, 0, 2, 0])),
+        Err(CodecError::TrailingBytes(1))
+    );
+    assert_eq!(
+        decoder.decode(&xy_radius(vec![
+            2, 0xff, 0xff, 0xff, 0xff, 0x0f, 0, 0, 0, 0, 0, 0, 0
+        ])),
+        Err(CodecError::InvalidVarint)
+    );
+
+    // Entity 1 moves but entity 5 is unknown, so neither is applied.
+    let mut decoder = PositionDecoder::default();
+    decoder.decode(&xy_radius(vec![1, 1, 0, 0, 32])).unwrap();
+    let x = PositionStreams {
+        x: vec![2, 1, 3, 32, 0],
+        ..PositionStreams::default()
+    };
+    assert_eq!(decoder.decode(&x), Err(CodecError::UnknownEntity(5)));
+    assert_eq!(decoder.get(1), Some(position(1, 0.0, 0.0, 1.0)));
+
+    let frame = Message::new().with("x_entities", 1u32);
+    assert!(PositionStreams::from_frame(&frame).is_err());
+}
+
+#[test]
+fn test_snapshot_delta_against_acked_baseline() {
+    let schema = schema();
+    let codec = Codec::with_format(&schema, WireFormat::Compact);
+    let mut server = SnapshotStore::new("Entity", 8);
+    let mut client = SnapshotStore::new("Entity", 8);
+
+    let first = vec![positioned(1, 1.0).with("name", "a"), positioned(2, 2.0)];
+    let mut full = Vec::new();
+    assert_eq!(
+        server.encode_entities(&codec, 9, 1, &first, &mut full),
+        Ok(None)
+    );
+    assert_eq!(client.decode_entities(&codec, 0, 1, &full), Ok(first));
+
+    server.ack(9, 1);
+    assert_eq!(server.acked(9), Some(1));
+    let second = vec![
+        positioned(3, 3.0),
+        Message::new()
+            .with("hero", positioned(1, 1.5))
+            .with("unknown", 1),
+    ];
+    let mut delta = Vec::new();
+    assert_eq!(
+        server.encode_entities(&codec, 9, 2, &second, &mut delta),
+        Ok(Some(1))
+    );
+    let expected = vec![positioned(1, 1.5), positioned(3, 3.0)];
+    assert_eq!(client.decode_entities(&codec, 0, 2, &delta), Ok(expected));
+
+    // Frame 2 was never acked, so frame 3 is still relative to frame 1 and
+    // unchanged entities are not resent.
+    let mut unchanged = Vec::new();
+    assert_eq!(
+        server.encode_entities(&codec, 9, 3, &second, &mut unchanged),
+        Ok(Some(1))
+    );
+    assert_eq!(
+        client
+            .decode_entities(&codec, 0, 3, &unchanged)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    server.ack(9, 3);
+    let mut empty = Vec::new();
+    server
+        .encode_entities(&codec, 9, 4, &second, &mut empty)
+        .unwrap();
+    assert!(empty.len() < unchanged.len());
+    assert_eq!(
+        client.decode_entities(&codec, 0, 4, &empty),
+        Ok(vec![positioned(1, 1.5), positioned(3, 3.0)])
+    );
+}
+
+#[test]
+fn test_snapshot_falls_back_to_full_state() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut server = SnapshotStore::new("Entity", 2);
+    let entities = vec![positioned(1, 1.0)];
+    let mut buffer = Vec::new();
+    for sequence in 1..=3 {
+        server
+            .encode_entities(&codec, 1, sequence, &entities, &mut buffer)
+            .unwrap();
+    }
+
+    // Frame 1 has already left the history window.
+    server.ack(1, 1);
+    buffer.clear();
+    assert_eq!(
+        server.encode_entities(&codec, 1, 4, &entities, &mut buffer),
+        Ok(None)
+    );
+
+    server.ack(1, 4);
+    server.ack(1, 2);
+    assert_eq!(server.acked(1), Some(4));
+    buffer.clear();
+    assert_eq!(
+        server.encode_entities(&codec, 1, 5, &entities, &mut buffer),
+        Ok(Some(4))
+    );
+
+    let mut client = SnapshotStore::new("Entity", 2);
+    assert_eq!(
+        client.decode_entities(&codec, 0, 5, &buffer),
+        Err(CodecError::MissingBaseline(4))
+    );
+
+    server.remove_client(1);
+    assert_eq!(server.acked(1), None);
+    assert_eq!(
+        server.encode_entities(
+            &codec,
+            1,
+            6,
+            &[Message::new().with("x", 1.0f32)],
+            &mut buffer
+        ),
+        Err(CodecError::invalid_value("id", "uint32"))
+    );
+}
+
+fn buffered(id: u32, x: f32, y: f32) -> Message {
+    Message::new().with("id", id).with("x", x).with("y", y)
+}
+
+fn state(id: u32, x: f32, y: f32) -> EntityState {
+    EntityState {
+        id,
+        x,
+        y,
+        radius: None,
+    }
+}
+
+#[test]
+fn test_snapshot_buffer_interpolates_between_frames() {
+    let mut buffer = SnapshotBuffer::new(BufferConfig {
+        tick_rate: 10.0,
+        jitter_window: 0.2,
+        max_extrapolation: 0.1,
+    })
+    .unwrap();
+    assert!(buffer.sample(0.0).is_empty());
+
+    let frame = |sequence: u32, x: f32| {
+        Message::new()
+            .with("sequence", sequence)
+            .with("entities", vec![Value::Message(buffered(1, x, 0.0))])
+            .with(
+                "global_entities",
+                vec![Value::Message(buffered(2, 0.0, x).with("radius", x * 2.0))],
+            )
+    };
+    assert!(buffer.push_frame(&frame(10, 0.0).with("tick_rate", 20.0f32)));
+    assert!(buffer.push_frame(&frame(12, 10.0)));
+    assert!(!buffer.push_frame(&frame(12, 99.0)));
+    assert_eq!(buffer.latest_time(), Some(0.6));
+
+    let sampled = buffer.sample(0.525);
+    assert_eq!(sampled[0], state(1, 2.5, 0.0));
+    assert_eq!(
+        sampled[1],
+        EntityState {
+            radius: Some(5.0),
+            ..state(2, 0.0, 2.5)
+        }
+    );
+    assert_eq!(buffer.sample(0.0)[0], state(1, 0.0, 0.0));
+
+    // Late frames are slotted in by sequence.
+    assert!(buffer.push_frame(&frame(11, 4.0)));
+    assert_eq!(buffer.sample(0.55)[0], state(1, 4.0, 0.0));
+    assert_eq!(buffer.sample(0.575)[0], state(1, 7.0, 0.0));
+}
+
+#[test]
+fn test_snapshot_buffer_extrapolation_and_pruning() {
+    let mut buffer = SnapshotBuffer::new(BufferConfig {
+        tick_rate: 10.0,
+        jitter_window: 0.2,
+        max_extrapolation: 0.1,
+    })
+    .unwrap();
+    for sequence in 0..5u32 {
+        let entities = vec![state(1, sequence as f32, 0.0)];
+        assert!(buffer.push(sequence, None, false, entities));
+    }
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(buffer.render_time(), Some(0.2));
+    assert!(!buffer.push(1, None, false, Vec::new()));
+
+    let extrapolated = buffer.sample(0.45)[0];
+    assert!((extrapolated.x - 4.5).abs() < 1e-5);
+    let capped = buffer.sample(10.0)[0];
+    assert!((capped.x - 5.0).abs() < 1e-5);
+
+    // Entities that only appear in the newest frame are held in place.
+    assert!(buffer.push(5, None, false, vec![state(2, 1.0, 1.0)]));
+    assert_eq!(buffer.sample(0.6), vec![state(2, 1.0, 1.0)]);
+
+    assert!(buffer.push(100, None, true, vec![state(3, 0.0, 0.0)]));
+    assert_eq!(buffer.len(), 1);
+    assert_eq!(buffer.latest_sequence(), Some(100));
+
+    for tick_rate in [0.0, -20.0, f32::NAN] {
+        let config = BufferConfig {
+            tick_rate,
+            ..BufferConfig::default()
+        };
+        assert_eq!(
+            SnapshotBuffer::new(config).err(),
+            Some(CodecError::invalid_value(
+                "tick_rate",
+                "a positive tick rate"
+            ))
+        );
+    }
+    for seconds in [-0.1, f64::NAN, f64::INFINITY] {
+        let config = BufferConfig {
+            jitter_window: seconds,
+            ..BufferConfig::default()
+        };
+        assert_eq!(
+            SnapshotBuffer::new(config).err(),
+            Some(CodecError::invalid_value(
+                "jitter_window",
+                "a non-negative duration"
+            ))
+        );
+        let config = BufferConfig {
+            max_extrapolation: seconds,
+            ..BufferConfig::default()
+        };
+        assert_eq!(
+            SnapshotBuffer::new(config).err(),
+            Some(CodecError::invalid_value(
+                "max_extrapolation",
+                "a non-negative duration"
+            ))
+        );
+    }
+}
+
+#[test]
+fn test_validate_reports_structured_errors() {
+    assert_eq!(schema().validate(), Ok(()));
+
+    let errors = NetSchema::from_json(
+        r#"{
+            "messages": {
+                "Entity": {"name": "Entity", "fields": [
+                    {"name": "id", "number": 1, "type": "uint32", "label": "optional"},
+                    {"name": "x", "number": 1, "type": "float", "label": "optional"},
+                    {"name": "x", "number": 2, "type": "float", "label": "optional"},
+                    {"name": "area", "number": 3, "type": "message", "label": "optional", "type_name": "Area"},
+                    {"name": "owner", "number": 4, "type": "message", "label": "optional"}
+                ]}
+            },
+            "profiles": {
+                "Position": {"message": "Entity", "fields": ["x", "z"]},
+                "Missing": {"message": "Area", "fields": []}
+            }
+        }"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            SchemaError::DuplicateFieldNumber {
+                message: "Entity".to_string(),
+                number: 1,
+                first: "id".to_string(),
+                second: "x".to_string(),
+            },
+            SchemaError::DuplicateFieldName {
+                message: "Entity".to_string(),
+                field: "x".to_string(),
+            },
+            SchemaError::UnknownMessageType {
+                message: "Entity".to_string(),
+                field: "area".to_string(),
+                type_name: "Area".to_string(),
+            },
+            SchemaError::MissingMessageType {
+                message: "Entity".to_string(),
+                field: "owner".to_string(),
+            },
+            SchemaError::UnknownProfileMessage {
+                profile: "Missing".to_string(),
+                message: "Area".to_string(),
+            },
+            SchemaError::UnknownProfileField {
+                profile: "Position".to_string(),
+                message: "Entity".to_string(),
+                field: "z".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "Entity: field number 1 is used by both id and x"
+    );
+}
+
+#[test]
+fn test_validate_json_rejects_unknown_types() {
+    let raw = r#"{"messages": {"A": {"name": "A", "fields": [
+        {"name": "a", "number": 1, "type": "int128", "label": "optional"}
+    ]}}}"#;
+    assert_eq!(
+        validate_json(raw).unwrap_err(),
+        vec![SchemaError::UnknownFieldType {
+            message: "A".to_string(),
+            field: "a".to_string(),
+            field_type: "int128".to_string(),
+        }]
+    );
+    assert!(NetSchema::from_json(raw).is_err());
+    assert!(matches!(
+        validate_json("{").unwrap_err()[..],
+        [SchemaError::Json(_)]
+    ));
+    assert!(validate_json(SCHEMA_JSON).is_ok());
+}
+
+#[test]
+fn test_compare_flags_breaking_changes() {
+    let old = schema();
+    assert!(compare(&old, &old).is_empty());
+
+    let edited = SCHEMA_JSON
+        .replace(
+            r#""name": "y", "number": 3, "type": "float""#,
+            r#""name": "y", "number": 30, "type": "float""#,
+        )
+        .replace(
+            r#""name": "dead", "number": 5, "type": "bool""#,
+            r#""name": "dead", "number": 5, "type": "uint32""#,
+        )
+        .replace(
+            r#"{"name": "data", "number": 2, "type": "bytes", "label": "optional"}"#,
+            r#"{"name": "blob", "number": 2, "type": "bytes", "label": "optional"},
+               {"name": "extra", "number": 3, "type": "bytes", "label": "optional"}"#,
+        )
+        .replace(
+            r#"{"name": "effects", "number": 7, "type": "uint32", "label": "repeated"},"#,
+            "",
+        );
+    let new = NetSchema::from_json(&edited).unwrap();
+    assert_eq!(
+        compare(&old, &new),
+        vec![
+            BreakingChange::RenamedField {
+                message: "Area".to_string(),
+                number: 2,
+                old: "data".to_string(),
+                new: "blob".to_string(),
+            },
+            BreakingChange::AddedField {
+                message: "Area".to_string(),
+                field: "extra".to_string(),
+                number: 3,
+            },
+            BreakingChange::RenumberedField {
+                message: "Entity".to_string(),
+                field: "y".to_string(),
+                old: 3,
+                new: 30,
+            },
+            BreakingChange::ChangedKind {
+                message: "Entity".to_string(),
+                field: "dead".to_string(),
+                old: FieldKind::Bool,
+                new: FieldKind::UInt32,
+            },
+            BreakingChange::RemovedField {
+                message: "Entity".to_string(),
+                field: "effects".to_string(),
+                number: 7,
+            },
+        ]
+    );
+
+    let mut without_area = schema();
+    without_area.messages.remove("Area");
+    assert_eq!(
+        compare(&old, &without_area),
+        vec![BreakingChange::RemovedMessage {
+            message: "Area".to_string()
+        }]
+    );
+    assert!(compare(&without_area, &old).is_empty());
+
+    let framed = NetSchema::from_json(FRAME_SCHEMA_JSON).unwrap();
+    let reordered = FRAME_SCHEMA_JSON.replace(r#""bit": 9"#, r#""bit": 12"#);
+    let changes = compare(&framed, &NetSchema::from_json(&reordered).unwrap());
+    assert_eq!(changes, vec![BreakingChange::ChangedFrame { version: 7 }]);
+    assert_eq!(
+        changes[0].to_string(),
+        "Frame layout changed without bumping version 7"
+    );
+    assert!(compare(&framed, &framed).is_empty());
+
+    let versioned = NetSchema::from_json(VERSIONED_SCHEMA_JSON).unwrap();
+    let protocol_1 = NetSchema::from_json(PROTOCOL_1_SCHEMA_JSON).unwrap();
+    assert!(compare(&protocol_1, &versioned).is_empty());
+    assert_eq!(
+        compare(&versioned, &protocol_1),
+        vec![
+            BreakingChange::RemovedField {
+                message: "Ping".to_string(),
+                field: "note".to_string(),
+                number: 2,
+            },
+            BreakingChange::RemovedField {
+                message: "Pos".to_string(),
+                field: "z".to_string(),
+                number: 3,
+            },
+            BreakingChange::RemovedProtocol { version: 3 },
+        ]
+    );
+    let edited = VERSIONED_SCHEMA_JSON.replacen(r#""since": 3"#, r#""since": 1"#, 1);
+    assert_eq!(
+        compare(&versioned, &NetSchema::from_json(&edited).unwrap()),
+        vec![BreakingChange::ChangedSince {
+            message: "Ping".to_string(),
+            field: "note".to_string(),
+            old: Some(3),
+            new: Some(1),
+        }]
+    );
+
+    // Bitmap presence bits follow declaration order within each protocol.
+    let swapped = PROTOCOL_1_SCHEMA_JSON.replace(
+        r#"{"name": "x", "number": 1, "type": "float", "label": "optional"},
+                {"name": "y", "number": 2, "type": "float", "label": "optional"}"#,
+        r#"{"name": "y", "number": 2, "type": "float", "label": "optional"},
+                {"name": "x", "number": 1, "type": "float", "label": "optional"},
+                {"name": "w", "number": 4, "type": "float", "label": "optional"}"#,
+    );
+    let changes = compare(&protocol_1, &NetSchema::from_json(&swapped).unwrap());
+    assert_eq!(
+        changes,
+        vec![
+            BreakingChange::AddedField {
+                message: "Pos".to_string(),
+                field: "w".to_string(),
+                number: 4,
+            },
+            BreakingChange::ReorderedFields {
+                message: "Pos".to_string(),
+            },
+        ]
+    );
+    assert_eq!(changes[1].to_string(), "Pos: presence bit order changed");
+
+    // Typed readers skip fields they do not know.
+    let typed = NetSchema::from_json(TYPED_SCHEMA_JSON).unwrap();
+    let extended = TYPED_SCHEMA_JSON.replace(
+        r#"{"name": "tags", "number": 3, "type": "uint32", "label": "repeated"}"#,
+        r#"{"name": "tags", "number": 3, "type": "uint32", "label": "repeated"},
+                {"name": "extra", "number": 4, "type": "bool", "label": "optional"}"#,
+    );
+    assert!(compare(&typed, &NetSchema::from_json(&extended).unwrap()).is_empty());
+}
+
+#[test]
+fn test_runtime_schema_loading() {
+    let path = std::env::temp_dir().join(format!("net_schema_{}.json", std::process::id()));
+    std::fs::write(&path, SCHEMA_JSON).unwrap();
+    let loaded = NetSchema::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(compare(&schema(), &loaded).is_empty());
+
+    assert!(matches!(
+        NetSchema::load(&path).unwrap_err()[..],
+        [SchemaError::Io { .. }]
+    ));
+
+    let current = set_net_schema(loaded);
+    assert!(Arc::ptr_eq(&net_schema().unwrap(), &current));
+    let mut buffer = Vec::new();
+    Codec::new(&net_schema().unwrap())
+        .encode_message("Entity", &entity(), &mut buffer)
+        .unwrap();
+    #[allow(deprecated)]
+    let legacy = &*netcode::NET_SCHEMA;
+    assert!(compare(&current, legacy).is_empty());
+
+    let swapped = set_net_schema(NetSchema::from_json(r#"{"messages": {}}"#).unwrap());
+    assert!(Arc::ptr_eq(&net_schema().unwrap(), &swapped));
+    assert!(current.messages.contains_key("Entity"));
+    assert!(legacy.messages.contains_key("Entity"));
+}
+
+#[test]
+fn test_channel_acks_and_rtt() {
+    let mut client = Channel::new(ChannelConfig::default());
+    let mut server = Channel::new(ChannelConfig::default());
+    let mut packets = Vec::new();
+    for payload in [[1u8], [2], [3]] {
+        let mut packet = Vec::new();
+        client.send(&payload, 0.0, &mut packet);
+        packets.push(packet);
+    }
+    // Nothing received yet: ack 0 with no bits set, then no reliable messages.
+    assert_eq!(packets[0], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(packets[0].len(), CHANNEL_HEADER_LEN + 1);
+
+    // The second packet is lost and the third overtakes the first.
+    let received = server.receive(&packets[2], 0.05).unwrap();
+    assert_eq!((received.payload, received.stale), (&[3u8][..], false));
+    assert!(!server.receive(&packets[0], 0.05).unwrap().stale);
+    assert!(server.receive(&packets[0], 0.05).unwrap().stale);
+    assert_eq!(server.remote_sequence(), Some(2));
+
+    let mut reply = Vec::new();
+    server.send(&[], 0.1, &mut reply);
+    assert_eq!(reply[2..CHANNEL_HEADER_LEN], [3, 0, 0b101, 0, 0, 0, 0, 0]);
+    client.receive(&reply, 0.1).unwrap();
+    assert_eq!(client.rtt(), Some(0.1));
+    assert_eq!(client.packet_loss(), 0.0);
+    // Acks are only counted once.
+    client.receive(&reply, 0.2).unwrap();
+    assert_eq!(client.rtt(), Some(0.1));
+
+    // Packets that fall out of the ack window unacked count as lost.
+    let mut last = Vec::new();
+    for _ in 3..=40 {
+        last.clear();
+        client.send(&[], 0.3, &mut last);
+    }
+    server.receive(&last, 0.35).unwrap();
+    reply.clear();
+    server.send(&[], 0.4, &mut reply);
+    client.receive(&reply, 0.4).unwrap();
+    // Sequence 40 is acked, then 1 and 3 to 8 are lost.
+    let mut loss = 0.0;
+    for _ in 0..7 {
+        loss += (1.0 - loss) * 0.1;
+    }
+    assert!((client.packet_loss() - loss).abs() < 1e-12);
+    assert!((client.rtt().unwrap() - 0.1).abs() < 1e-12);
+
+    // Packets older than the ack window cannot be told apart from
+    // duplicates.
+    assert!(server.receive(&packets[1], 0.5).unwrap().stale);
+}
+
+#[test]
+fn test_channel_reliable_delivery() {
+    let mut client = Channel::new(ChannelConfig::default());
+    let mut server = Channel::new(ChannelConfig::default());
+    assert_eq!(client.send_reliable(1, b"one".to_vec()), 0);
+    assert_eq!(client.send_reliable(2, b"two".to_vec()), 1);
+
+    let mut lost = Vec::new();
+    client.send(&[], 0.0, &mut lost);
+    // Nothing is resent before one and a half round trips.
+    let mut early = Vec::new();
+    client.send(&[], 0.1, &mut early);
+    assert_eq!(early.len(), CHANNEL_HEADER_LEN);
+    let mut resent = Vec::new();
+    client.send(&[], 0.35, &mut resent);
+    client.send_reliable(3, b"three".to_vec());
+    let mut third = Vec::new();
+    client.send(&[], 0.4, &mut third);
+    assert_eq!(client.unacked(), 3);
+
+    // Later messages wait for earlier ones.
+    assert!(server.receive(&early, 0.45).unwrap().reliable.is_empty());
+    assert!(server.receive(&third, 0.45).unwrap().reliable.is_empty());
+    assert_eq!(
+        server.receive(&resent, 0.5).unwrap().reliable,
+        vec![
+            (1, b"one".to_vec()),
+            (2, b"two".to_vec()),
+            (3, b"three".to_vec())
+        ]
+    );
+    // Each message is delivered once, even when its packet arrives again.
+    let lost = server.receive(&lost, 0.5).unwrap();
+    assert!(!lost.stale);
+    assert!(lost.reliable.is_empty());
+
+    let mut ack = Vec::new();
+    server.send(&[], 0.6, &mut ack);
+    client.receive(&ack, 0.6).unwrap();
+    assert_eq!(client.unacked(), 0);
+
+    // A small budget spreads messages over packets, but a message larger
+    // than the budget still goes out.
+    let config = ChannelConfig {
+        max_reliable_bytes: 4,
+        ..ChannelConfig::default()
+    };
+    let mut client = Channel::new(config.clone());
+    let mut server = Channel::new(config);
+    client.send_reliable(0, b"large message".to_vec());
+    client.send_reliable(0, b"a".to_vec());
+    let mut first = Vec::new();
+    client.send(&[], 0.0, &mut first);
+    let mut second = Vec::new();
+    client.send(&[], 0.0, &mut second);
+    assert_eq!(
+        server.receive(&first, 0.1).unwrap().reliable,
+        vec![(0, b"large message".to_vec())]
+    );
+    assert_eq!(
+        server.receive(&second, 0.1).unwrap().reliable,
+        vec![(0, b"a".to_vec())]
+    );
+
+    // Only a window of messages is in flight, so the receiver never buffers
+    // more than that.
+    let config = ChannelConfig {
+        max_reliable_bytes: usize::MAX,
+        ..ChannelConfig::default()
+    };
+    let mut client = Channel::new(config.clone());
+    let mut server = Channel::new(config);
+    for _ in 0..1030 {
+        client.send_reliable(0, vec![1]);
+    }
+    let mut first = Vec::new();
+    client.send(&[], 0.0, &mut first);
+    assert_eq!(server.receive(&first, 0.1).unwrap().reliable.len(), 1024);
+    let mut ack = Vec::new();
+    server.send(&[], 0.1, &mut ack);
+    client.receive(&ack, 0.2).unwrap();
+    let mut rest = Vec::new();
+    client.send(&[], 0.2, &mut rest);
+    assert_eq!(server.receive(&rest, 0.3).unwrap().reliable.len(), 6);
+}
+
+#[test]
+fn test_channel_frames() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let config = ChannelConfig {
+        reliable_sections: vec!["area".to_string()],
+        ..ChannelConfig::default()
+    };
+    let mut client = Channel::new(config.clone());
+    let mut server = Channel::new(config);
+    let area = Message::new().with("name", "Central Core");
+    let frame = Message::new()
+        .with("sequence", 7u32)
+        .with("complete", true)
+        .with("area", area.clone());
+
+    let mut lost = Vec::new();
+    server.send_frame(&codec, &frame, 0.0, &mut lost).unwrap();
+    let mut packet = Vec::new();
+    server
+        .send_frame(
+            &codec,
+            &frame.clone().with("sequence", 8u32),
+            0.5,
+            &mut packet,
+        )
+        .unwrap();
+    let received = client.receive_frame(&codec, &packet, 0.55).unwrap();
+    let decoded = received.frame.unwrap();
+    assert_eq!(decoded.get("sequence"), Some(&Value::UInt32(8)));
+    assert_eq!(decoded.get("complete"), Some(&Value::Bool(true)));
+    assert_eq!(decoded.get("area"), None);
+    assert_eq!(
+        received.reliable,
+        vec![
+            ("area".to_string(), area.clone()),
+            ("area".to_string(), area)
+        ]
+    );
+    let stale = client.receive_frame(&codec, &packet, 0.6).unwrap();
+    assert_eq!(stale.frame, None);
+    assert!(stale.reliable.is_empty());
+
+    let mut repeated = Channel::new(ChannelConfig {
+        reliable_sections: vec!["entities".to_string()],
+        ..ChannelConfig::default()
+    });
+    let entities = Message::new().with("entities", Value::List(vec![entity().into()]));
+    assert_eq!(
+        repeated
+            .send_frame(&codec, &entities, 0.0, &mut Vec::new())
+            .unwrap_err(),
+        CodecError::invalid_value("entities", "an optional message section")
+    );
+
+    // A packet whose frame or sections fail to decode is still acked, so
+    // nothing is resent, and the sections that decode are delivered.
+    let config = ChannelConfig {
+        reliable_sections: vec!["area".to_string()],
+        ..ChannelConfig::default()
+    };
+    let mut client = Channel::new(config.clone());
+    let mut server = Channel::new(config);
+    let area = Message::new().with("name", "Central Core");
+    let mut encoded_area = Vec::new();
+    codec
+        .encode_message("Area", &area, &mut encoded_area)
+        .unwrap();
+    let kind = codec
+        .frame_layout()
+        .sections
+        .iter()
+        .position(|section| section.name == "area")
+        .unwrap() as u16;
+    server.send_reliable(kind, encoded_area.clone());
+    let mut bad_version = Vec::new();
+    server.send(&[99], 0.0, &mut bad_version);
+    let received = client.receive_frame(&codec, &bad_version, 0.1).unwrap();
+    assert_eq!(received.frame, None);
+    assert!(received.reliable.is_empty());
+    assert_eq!(
+        received.errors,
+        vec![CodecError::UnsupportedFrameVersion(99); 2]
+    );
+    let mut ack = Vec::new();
+    client.send(&[], 0.1, &mut ack);
+    server.receive(&ack, 0.2).unwrap();
+    assert_eq!(server.unacked(), 0);
+
+    server.send_reliable(kind, vec![0xff]);
+    server.send_reliable(kind, encoded_area);
+    let mut good = Vec::new();
+    let frame = Message::new().with("sequence", 9u32);
+    server.send_frame(&codec, &frame, 1.0, &mut good).unwrap();
+    let received = client.receive_frame(&codec, &good, 1.1).unwrap();
+    assert!(received.frame.is_some());
+    assert_eq!(received.reliable, vec![("area".to_string(), area)]);
+    assert_eq!(received.errors, vec![CodecError::UnexpectedEnd]);
+
+    let mut fresh = Channel::new(ChannelConfig::default());
+    assert_eq!(
+        fresh
+            .receive(&packet[..CHANNEL_HEADER_LEN + 2], 0.0)
+            .unwrap_err(),
+        CodecError::UnexpectedEnd
+    );
+    assert_eq!(fresh.remote_sequence(), None);
+}
+
+#[test]
+fn test_channel_sections_keep_their_protocol() {
+    let schema = validate_json(VERSIONED_SCHEMA_JSON).unwrap();
+    let codec = Codec::new(&schema);
+    let legacy = codec.with_protocol(1).unwrap();
+    let config = ChannelConfig {
+        reliable_sections: vec!["ping".to_string()],
+        ..ChannelConfig::default()
+    };
+    let mut client = Channel::new(config.clone());
+    let mut server = Channel::new(config);
+    let old_ping = Message::new().with("id", 1u32);
+    let new_ping = Message::new().with("id", 2u32).with("note", "hi");
+    let frame = |tick: u32| Message::new().with("tick", tick);
+
+    let mut lost = Vec::new();
+    server
+        .send_frame(
+            &legacy,
+            &frame(1).with("ping", old_ping.clone()),
+            0.0,
+            &mut lost,
+        )
+        .unwrap();
+    let mut newer = Vec::new();
+    server
+        .send_frame(
+            &codec,
+            &frame(2).with("ping", new_ping.clone()),
+            0.0,
+            &mut newer,
+        )
+        .unwrap();
+    let received = client.receive_frame(&codec, &newer, 0.1).unwrap();
+    assert!(received.reliable.is_empty());
+
+    // Both pings are resent in a protocol 1 packet; the second still decodes
+    // with the protocol it first arrived in.
+    let mut resent = Vec::new();
+    server
+        .send_frame(&legacy, &frame(3), 1.0, &mut resent)
+        .unwrap();
+    let received = client.receive_frame(&codec, &resent, 1.1).unwrap();
+    assert_eq!(
+        received.reliable,
+        vec![
+            ("ping".to_string(), old_ping),
+            ("ping".to_string(), new_ping)
+        ]
+    );
+    assert!(received.errors.is_empty());
+}
+
+const INPUT_SCHEMA_JSON: &str = r#"{
+    "messages": {
+        "Say": {
+            "name": "Say",
+            "fields": [
+                {"name": "text", "number": 1, "type": "string", "label": "optional"}
+            ]
+        }
+    },
+    "input": {
+        "version": 9,
+        "compact_version": 10,
+        "sections": [
+            {"name": "tick", "type": "uint32", "label": "required"},
+            {"name": "firing", "type": "flag"},
+            {"name": "aim", "type": "float"},
+            {"name": "say", "type": "message", "type_name": "Say"}
+        ]
+    }
+}"#;
+
+#[test]
+fn test_input_frame_round_trip() {
+    let schema = schema();
+    let input = Message::new()
+        .with("tick", 12u32)
+        .with("move_x", 0.5f32)
+        .with("move_y", -1.0f32)
+        .with("abilities", 0b101u32);
+    for format in [WireFormat::Fixed, WireFormat::Compact] {
+        let codec = Codec::with_format(&schema, format);
+        let mut encoded = Vec::new();
+        codec.encode_input(&input, &mut encoded).unwrap();
+        assert_eq!(encoded[0], codec.input_layout().version(format));
+        // Any codec decodes both formats.
+        assert_eq!(Codec::new(&schema).decode_input(&encoded).unwrap(), input);
+    }
+
+    let mut encoded = Vec::new();
+    Codec::new(&schema)
+        .encode_input(&Message::new().with("tick", 3u32), &mut encoded)
+        .unwrap();
+    // Version, presence bits, then the tick.
+    assert_eq!(encoded, [1, 0, 3, 0, 0, 0]);
+
+    let declared = NetSchema::from_json(INPUT_SCHEMA_JSON).unwrap();
+    let codec = Codec::with_format(&declared, WireFormat::Compact);
+    let input = Message::new()
+        .with("tick", 300u32)
+        .with("firing", true)
+        .with("say", Message::new().with("text", "gg"));
+    let mut encoded = Vec::new();
+    codec.encode_input(&input, &mut encoded).unwrap();
+    assert_eq!(encoded[0], 10);
+    assert_eq!(codec.decode_input(&encoded).unwrap(), input);
+
+    assert_eq!(
+        codec.decode_input(&[1, 0, 0]).unwrap_err(),
+        CodecError::UnsupportedFrameVersion(1)
+    );
+    encoded.push(0);
+    assert_eq!(
+        codec.decode_input(&encoded).unwrap_err(),
+        CodecError::TrailingBytes(1)
+    );
+    assert_eq!(
+        codec.decode_input(&[9, 0b1000]).unwrap_err(),
+        CodecError::InvalidPresenceBitmap
+    );
+
+    let same_versions =
+        INPUT_SCHEMA_JSON.replace(r#""compact_version": 10"#, r#""compact_version": 9"#);
+    assert_eq!(
+        validate_json(&same_versions).unwrap_err(),
+        vec![SchemaError::DuplicateFrameVersion(9)]
+    );
+    let mut without_input = declared.clone();
+    without_input.input = None;
+    assert_eq!(
+        compare(&declared, &without_input),
+        vec![BreakingChange::ChangedInput]
+    );
+    assert!(compare(&declared, &declared).is_empty());
+}
+
+#[test]
+fn test_input_buffer_orders_and_drops_inputs() {
+    let mut buffer = InputBuffer::new(InputConfig {
+        capacity: 4,
+        ..InputConfig::default()
+    });
+    let input = |tick: u32| {
+        Message::new()
+            .with("tick", tick)
+            .with("move_x", tick as f32)
+    };
+    assert_eq!(buffer.timing(0), None);
+    for tick in [3, 1, 2] {
+        assert_eq!(buffer.push(input(tick)).unwrap(), InputStatus::Buffered);
+    }
+    assert_eq!(buffer.push(input(2)).unwrap(), InputStatus::Duplicate);
+    assert_eq!(buffer.latest_tick(), Some(3));
+
+    // Consuming a tick drops older inputs that were never consumed.
+    assert_eq!(buffer.pop(2), Some(input(2)));
+    assert_eq!(buffer.len(), 1);
+    assert_eq!(buffer.consumed_tick(), Some(2));
+    assert_eq!(buffer.push(input(1)).unwrap(), InputStatus::Stale);
+    assert_eq!(buffer.push(input(2)).unwrap(), InputStatus::Stale);
+
+    // A missing input stays missing once its tick is consumed.
+    assert_eq!(buffer.pop(3), Some(input(3)));
+    assert_eq!(buffer.pop(4), None);
+    assert_eq!(buffer.push(input(4)).unwrap(), InputStatus::Stale);
+
+    for tick in 5..=8 {
+        assert_eq!(buffer.insert(tick, input(tick)), InputStatus::Buffered);
+    }
+    assert_eq!(buffer.insert(9, input(9)), InputStatus::Full);
+    assert_eq!(
+        buffer.push(Message::new()).unwrap_err(),
+        CodecError::invalid_value("tick", "uint32")
+    );
+
+    // The newest input is tick 8: two ticks of lead, give or take one, is
+    // on time.
+    assert_eq!(buffer.timing(6), Some(InputTiming::OnTime));
+    assert_eq!(buffer.timing(5), Some(InputTiming::OnTime));
+    assert_eq!(buffer.timing(7), Some(InputTiming::OnTime));
+    assert_eq!(buffer.timing(4), Some(InputTiming::Ahead(2)));
+    assert_eq!(buffer.timing(9), Some(InputTiming::Behind(3)));
+
+    buffer.clear();
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.push(input(1)).unwrap(), InputStatus::Buffered);
+    assert_eq!(buffer.timing(u32::MAX), Some(InputTiming::Behind(u32::MAX)));
+}
+
+fn scheduled(id: u32, x: f32, y: f32) -> Message {
+    Message::new().with("id", id).with("x", x).with("y", y)
+}
+
+fn entity_ids(frame: &Message, key: &str) -> Vec<u32> {
+    frame
+        .get(key)
+        .and_then(Value::as_list)
+        .unwrap()
+        .iter()
+        .map(|entity| {
+            entity
+                .as_message()
+                .unwrap()
+                .get("id")
+                .unwrap()
+                .as_u32()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_entity_scheduler_round_robins_under_budget() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let mut encoded = Vec::new();
+    codec
+        .encode_message("Entity", &scheduled(1, 0.0, 0.0), &mut encoded)
+        .unwrap();
+    let size = encoded.len();
+    let view = ViewRect {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+    let frame = Message::new().with(
+        "entities",
+        Value::List((1..=4).map(|id| scheduled(id, 10.0, 10.0).into()).collect()),
+    );
+
+    let mut scheduler = EntityScheduler::new(SchedulerConfig::default());
+    let selection = scheduler.select(&codec, &frame, &view, 2 * size).unwrap();
+    assert_eq!(selection.entities, vec![0, 1]);
+    assert_eq!(selection.deferred, vec![3, 4]);
+    assert_eq!(selection.bytes, 2 * size);
+    assert_eq!(scheduler.accumulator(1), Some(0.0));
+    assert_eq!(scheduler.accumulator(4), Some(1.0));
+    // Deferred entities go first next time.
+    let selection = scheduler.select(&codec, &frame, &view, 2 * size).unwrap();
+    assert_eq!(selection.entities, vec![2, 3]);
+
+    // Higher priorities accumulate faster.
+    scheduler.set_priority(4, 5.0);
+    let mut next = frame.clone();
+    let selection = scheduler
+        .select_frame(&codec, &mut next, &view, 2 * size)
+        .unwrap();
+    assert_eq!(entity_ids(&next, "entities"), vec![1, 4]);
+    assert_eq!(selection.deferred, vec![2, 3]);
+    let mut sent = Vec::new();
+    codec.encode_frame(&next, &mut sent).unwrap();
+    let mut full = Vec::new();
+    codec.encode_frame(&frame, &mut full).unwrap();
+    assert_eq!(full.len() - sent.len(), 2 * size);
+
+    // Entities missing from a frame keep their accumulators until removed.
+    let fewer = Message::new().with("entities", Value::List(vec![scheduled(1, 0.0, 0.0).into()]));
+    scheduler.select(&codec, &fewer, &view, 0).unwrap();
+    assert_eq!(scheduler.accumulator(2), Some(2.0));
+    assert_eq!(scheduler.accumulator(1), Some(1.0));
+    scheduler.remove(2);
+    assert_eq!(scheduler.accumulator(2), None);
+
+    // Deferred updates are dropped, so the next frame carries the full
+    // state of deferred entities, and they go first.
+    let mut scheduler = EntityScheduler::new(SchedulerConfig::default());
+    let both = Message::new().with(
+        "entities",
+        Value::List(vec![
+            scheduled(1, 10.0, 10.0).into(),
+            scheduled(2, 10.0, 10.0).into(),
+        ]),
+    );
+    let selection = scheduler.select(&codec, &both, &view, size).unwrap();
+    assert_eq!(selection.deferred, vec![2]);
+    let mut next = Message::new().with(
+        "entities",
+        Value::List(vec![
+            Message::new().with("id", 1u32).into(),
+            scheduled(2, 10.0, 10.0).into(),
+        ]),
+    );
+    scheduler
+        .select_frame(&codec, &mut next, &view, size)
+        .unwrap();
+    assert_eq!(
+        next.get("entities"),
+        Some(&Value::List(vec![scheduled(2, 10.0, 10.0).into()]))
+    );
+}
+
+#[test]
+fn test_entity_scheduler_view_and_untracked_entities() {
+    let schema = schema();
+    let codec = Codec::new(&schema);
+    let view = ViewRect {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+    assert!(view.overlaps(103.0, 50.0, 4.0));
+    assert!(!view.overlaps(103.0, 50.0, 2.0));
+    let untracked = Message::new().with("x", 1.0f32);
+    let frame = Message::new()
+        .with(
+            "entities",
+            Value::List(vec![
+                scheduled(1, 500.0, 50.0).into(),
+                scheduled(2, 99.0, 50.0).into(),
+                untracked.into(),
+            ]),
+        )
+        .with(
+            "global_entities",
+            Value::List(vec![scheduled(3, 500.0, 500.0).into()]),
+        );
+
+    // Entities outside the view are never sent by default, but those
+    // overlapping it and global ones are, and untracked ones always are.
+    let mut scheduler = EntityScheduler::new(SchedulerConfig::default());
+    for _ in 0..3 {
+        let selection = scheduler.select(&codec, &frame, &view, 1000).unwrap();
+        assert_eq!(selection.entities, vec![1, 2]);
+        assert_eq!(selection.global_entities, vec![0]);
+        assert_eq!(selection.deferred, vec![1]);
+    }
+    // Untracked entities count against the budget even when it is spent.
+    assert_eq!(
+        scheduler.select(&codec, &frame, &view, 0).unwrap().entities,
+        vec![2]
+    );
+
+    let mut scheduler = EntityScheduler::new(SchedulerConfig {
+        outside_view: 0.5,
+        ..SchedulerConfig::default()
+    });
+    let selection = scheduler.select(&codec, &frame, &view, 1000).unwrap();
+    assert_eq!(selection.entities, vec![0, 1, 2]);
+    assert!(selection.deferred.is_empty());
+
+    // Entities without a position were placed before and count as in view.
+    let mut scheduler = EntityScheduler::new(SchedulerConfig::default());
+    let unplaced = Message::new().with(
+        "entities",
+        Value::List(vec![Message::new().with("id", 5u32).into()]),
+    );
+    let selection = scheduler.select(&codec, &unplaced, &view, 1000).unwrap();
+    assert_eq!(selection.entities, vec![0]);
+
+    let duplicated = Message::new()
+        .with("entities", Value::List(vec![scheduled(1, 0.0, 0.0).into()]))
+        .with(
+            "global_entities",
+            Value::List(vec![scheduled(1, 0.0, 0.0).into()]),
+        );
+    assert_eq!(
+        scheduler
+            .select(&codec, &duplicated, &view, 1000)
+            .unwrap_err(),
+        CodecError::invalid_value("id", "unique entity ids")
+    );
+    assert_eq!(scheduler.accumulator(5), Some(0.0));
+
+    let invalid = Message::new().with("entities", 1u32);
+    assert_eq!(
+        scheduler.select(&codec, &invalid, &view, 1000).unwrap_err(),
+        CodecError::invalid_value("entities", "list")
+    );
+}
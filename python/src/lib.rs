@@ -16,7 +16,11 @@ mod quadtree;
 mod serialization;
 
 use crate::collisions::get_mtv;
-use crate::netcode::NetCodec;
+use crate::netcode::{
+    NetCodec, PyChannel, PyEntityScheduler, PyFragmenter, PyFrameCompressor, PyFrameWriter,
+    PyInputBuffer, PyPositionDecoder, PyPositionEncoder, PyReassembler, PySnapshotBuffer,
+    PySnapshotStore,
+};
 use crate::quadtree::{PyConfig, QuadTreeWrapper};
 use crate::serialization::DiffFieldSetWrapper;
 
@@ -49,6 +53,17 @@ fn bolt(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyConfig>()?;
     m.add_class::<DiffFieldSetWrapper>()?;
     m.add_class::<NetCodec>()?;
+    m.add_class::<PyFrameWriter>()?;
+    m.add_class::<PyFrameCompressor>()?;
+    m.add_class::<PyFragmenter>()?;
+    m.add_class::<PyReassembler>()?;
+    m.add_class::<PyChannel>()?;
+    m.add_class::<PyInputBuffer>()?;
+    m.add_class::<PyEntityScheduler>()?;
+    m.add_class::<PySnapshotStore>()?;
+    m.add_class::<PySnapshotBuffer>()?;
+    m.add_class::<PyPositionEncoder>()?;
+    m.add_class::<PyPositionDecoder>()?;
 
     m.add_class::<PyCircle>()?;
     m.add_class::<PyRectangle>()?;
@@ -1,318 +1,1375 @@
-use netcode::{FieldKind, MessageSchema, NET_SCHEMA};
-use pyo3::exceptions::PyTypeError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use netcode::{
+    net_schema, set_net_schema, train_dictionary, BufferConfig, Channel, ChannelConfig, Codec,
+    CodecError, CompressionConfig, EntityScheduler, FieldKind, FieldSchema, FragmentConfig,
+    Fragmenter, FrameCompressor, FrameRef, FrameSchema, FrameSection, FrameWriter, InlineGroup,
+    InputBuffer, InputConfig, InputStatus, InputTiming, Message, MessageRef, MessageSchema,
+    NetSchema, PositionConfig, PositionDecoder, PositionEncoder, PositionStream, PositionStreams,
+    PositionUpdate, Reassembler, SchedulerConfig, SchemaError, SectionKind, SnapshotBuffer,
+    SnapshotStore, Value, ValueRef, ViewRect, WireFormat,
+};
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyOverflowError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyBytes, PyBytesMethods, PyDict, PyDictMethods, PyList, PyListMethods};
+use pyo3::types::{
+    PyByteArray, PyByteArrayMethods, PyBytes, PyBytesMethods, PyDict, PyDictMethods, PyFloat,
+    PyInt, PyList, PyListMethods, PyMemoryView, PySlice, PyString,
+};
 use pyo3::{Bound, IntoPyObjectExt};
 
-const FRAME_VERSION: u8 = 1;
+/// Getters registered per message with `NetCodec.register_getters`: field
+/// or inline group names paired with an attribute name or a callable taking
+/// the object.
+type Getters = HashMap<String, Vec<(String, Py<PyAny>)>>;
 
 #[pyclass(name = "NetCodec")]
-pub struct NetCodec;
+pub struct NetCodec {
+    schema: Arc<NetSchema>,
+    format: WireFormat,
+    protocol: Option<u8>,
+    getters: Getters,
+}
+
+impl NetCodec {
+    fn create(schema: Arc<NetSchema>, compact: bool, protocol: Option<u8>) -> PyResult<Self> {
+        let codec = NetCodec {
+            schema,
+            format: wire_format(compact),
+            protocol,
+            getters: Getters::new(),
+        };
+        codec.codec()?;
+        Ok(codec)
+    }
+
+    fn codec(&self) -> PyResult<Codec<'_>> {
+        let codec = Codec::with_format(&self.schema, self.format);
+        match self.protocol {
+            Some(version) => codec.with_protocol(version).map_err(codec_error),
+            None => Ok(codec),
+        }
+    }
+
+    fn set_schema(&mut self, schema: NetSchema) -> PyResult<()> {
+        if let Some(version) = self
+            .protocol
+            .filter(|version| schema.protocol(*version).is_none())
+        {
+            return Err(codec_error(CodecError::UnsupportedFrameVersion(version)));
+        }
+        self.schema = Arc::new(schema);
+        Ok(())
+    }
+}
 
 #[pymethods]
 impl NetCodec {
+    /// `protocol_version` selects an older protocol from the schema's
+    /// `legacy_frames` to encode with; frames of any protocol are decoded.
     #[new]
-    pub fn new() -> Self {
-        NetCodec
+    #[pyo3(signature = (compact=false, schema_path=None, protocol_version=None))]
+    pub fn new(
+        compact: bool,
+        schema_path: Option<&str>,
+        protocol_version: Option<u8>,
+    ) -> PyResult<Self> {
+        let schema = match schema_path {
+            Some(path) => Arc::new(NetSchema::load(path).map_err(schema_error)?),
+            None => net_schema().map_err(schema_error)?,
+        };
+        Self::create(schema, compact, protocol_version)
     }
 
-    pub fn encode_frame(&self, py: Python, payload: &Bound<'_, PyDict>) -> PyResult<Py<PyBytes>> {
-        let mut buffer = Vec::with_capacity(2048);
+    #[staticmethod]
+    #[pyo3(signature = (raw_json, compact=false, protocol_version=None))]
+    pub fn from_json(
+        raw_json: &str,
+        compact: bool,
+        protocol_version: Option<u8>,
+    ) -> PyResult<Self> {
+        let schema = netcode::validate_json(raw_json).map_err(schema_error)?;
+        Self::create(Arc::new(schema), compact, protocol_version)
+    }
 
-        let sequence = get_u32(payload, "sequence")?.unwrap_or(0);
+    /// Replaces the schema used by codecs created without `schema_path`
+    /// from now on, and by `DiffFieldSet`.
+    #[staticmethod]
+    pub fn set_default_schema(schema_path: &str) -> PyResult<()> {
+        set_net_schema(NetSchema::load(schema_path).map_err(schema_error)?);
+        Ok(())
+    }
 
-        let complete = get_bool(payload, "complete")?;
-        let complete_global = get_bool(payload, "complete_global")?;
-        let reset = get_bool(payload, "reset")?;
+    #[getter]
+    pub fn compact(&self) -> bool {
+        self.format == WireFormat::Compact
+    }
 
-        let self_id = get_u32(payload, "self_id")?;
-        let tick_rate = get_f32(payload, "tick_rate")?;
-        let pong = get_u32(payload, "pong")?;
+    #[getter]
+    pub fn protocol_version(&self) -> PyResult<u8> {
+        Ok(self.codec()?.protocol())
+    }
 
-        let area = get_message_bytes(py, payload, "area", "Area")?;
-        let map = get_message_bytes(py, payload, "map", "Map")?;
-        let chat = get_message_bytes(py, payload, "chat", "Chat")?;
-        let settings = get_message_bytes(py, payload, "settings", "Settings")?;
-        let mod_tools_response =
-            get_message_bytes(py, payload, "mod_tools_response", "ModToolsResponse")?;
-        let quest_data = get_message_bytes(py, payload, "quest_data", "QuestData")?;
+    /// Protocol versions this codec can encode with, newest first.
+    #[getter]
+    pub fn supported_protocol_versions(&self) -> Vec<u32> {
+        // Widened so pyo3 returns a list rather than bytes.
+        self.schema
+            .frame_layouts()
+            .map(|frame| u32::from(frame.version))
+            .collect()
+    }
 
-        let x_entities = get_bytes(payload, "x_entities")?;
-        let y_entities = get_bytes(payload, "y_entities")?;
-        let xy_entities = get_bytes(payload, "xy_entities")?;
-        let xy_radius_entities = get_bytes(payload, "xy_radius_entities")?;
+    pub fn load_schema(&mut self, schema_path: &str) -> PyResult<()> {
+        self.set_schema(NetSchema::load(schema_path).map_err(schema_error)?)
+    }
 
-        let mut flags1 = 0u8;
-        if complete {
-            flags1 |= 1 << 0;
-        }
-        if complete_global {
-            flags1 |= 1 << 1;
-        }
-        if reset {
-            flags1 |= 1 << 2;
-        }
-        if self_id.is_some() {
-            flags1 |= 1 << 3;
-        }
-        if tick_rate.is_some() {
-            flags1 |= 1 << 4;
-        }
-        if pong.is_some() {
-            flags1 |= 1 << 5;
-        }
-        if area.is_some() {
-            flags1 |= 1 << 6;
-        }
-        if map.is_some() {
-            flags1 |= 1 << 7;
-        }
+    pub fn load_schema_json(&mut self, raw_json: &str) -> PyResult<()> {
+        self.set_schema(netcode::validate_json(raw_json).map_err(schema_error)?)
+    }
 
-        let mut flags2 = 0u8;
-        if chat.is_some() {
-            flags2 |= 1 << 0;
-        }
-        if settings.is_some() {
-            flags2 |= 1 << 1;
-        }
-        if mod_tools_response.is_some() {
-            flags2 |= 1 << 2;
-        }
-        if quest_data.is_some() {
-            flags2 |= 1 << 3;
-        }
-        if x_entities.is_some() {
-            flags2 |= 1 << 4;
-        }
-        if y_entities.is_some() {
-            flags2 |= 1 << 5;
-        }
-        if xy_entities.is_some() {
-            flags2 |= 1 << 6;
-        }
-        if xy_radius_entities.is_some() {
-            flags2 |= 1 << 7;
+    pub fn encode_frame(&self, py: Python, payload: &Bound<'_, PyDict>) -> PyResult<Py<PyBytes>> {
+        let codec = self.codec()?;
+        let frame = frame_from_py(&codec, &self.getters, payload)?;
+        let mut buffer = Vec::with_capacity(2048);
+        codec
+            .encode_frame(&frame, &mut buffer)
+            .map_err(codec_error)?;
+        Ok(PyBytes::new(py, &buffer).unbind())
+    }
+
+    /// With `zero_copy`, bytes fields are returned as memoryviews into
+    /// `bytes` rather than copies.
+    #[pyo3(signature = (bytes, nest_inline=false, zero_copy=false))]
+    pub fn decode_frame(
+        &self,
+        py: Python,
+        bytes: &Bound<'_, PyBytes>,
+        nest_inline: bool,
+        zero_copy: bool,
+    ) -> PyResult<Py<PyDict>> {
+        let codec = self.codec()?.with_inline_nesting(nest_inline);
+        if zero_copy {
+            let frame = codec
+                .decode_frame_ref(bytes.as_bytes())
+                .map_err(codec_error)?;
+            return Ok(frame_ref_to_py(&frame, nest_inline, &BufferView::new(bytes)?)?.unbind());
         }
+        let frame = codec.decode_frame(bytes.as_bytes()).map_err(codec_error)?;
+        Ok(message_to_py(py, &frame)?.unbind())
+    }
 
-        buffer.push(FRAME_VERSION);
-        buffer.push(flags1);
-        buffer.push(flags2);
-        write_u32(&mut buffer, sequence);
+    /// Encodes a client input frame; `payload` is read like the payload of
+    /// `encode_frame`, against the schema's input layout.
+    pub fn encode_input(&self, py: Python, payload: &Bound<'_, PyDict>) -> PyResult<Py<PyBytes>> {
+        let codec = self.codec()?;
+        let input = layout_from_py(&codec, &self.getters, codec.input_layout(), payload)?;
+        let mut buffer = Vec::with_capacity(64);
+        codec
+            .encode_input(&input, &mut buffer)
+            .map_err(codec_error)?;
+        Ok(PyBytes::new(py, &buffer).unbind())
+    }
 
-        if let Some(value) = self_id {
-            write_u32(&mut buffer, value);
+    #[pyo3(signature = (bytes, nest_inline=false))]
+    pub fn decode_input(
+        &self,
+        py: Python,
+        bytes: &[u8],
+        nest_inline: bool,
+    ) -> PyResult<Py<PyDict>> {
+        let codec = self.codec()?.with_inline_nesting(nest_inline);
+        let input = codec.decode_input(bytes).map_err(codec_error)?;
+        Ok(message_to_py(py, &input)?.unbind())
+    }
+
+    /// Registers how to read `name` messages from objects, so they can be
+    /// passed to `encode_message` and `encode_frame` instead of dicts.
+    /// `getters` maps field or inline group names to an attribute name or a
+    /// callable taking the object; getters returning None leave the field
+    /// out.
+    pub fn register_getters(&mut self, name: &str, getters: &Bound<'_, PyDict>) -> PyResult<()> {
+        let schema = self.codec()?.message_schema(name).map_err(codec_error)?;
+        let mut spec = Vec::with_capacity(getters.len());
+        for (key, getter) in getters.iter() {
+            let key: String = key.extract()?;
+            if !schema.fields_by_name.contains_key(&key) && schema.inline_group(&key).is_none() {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown field {key} in getters for {name}"
+                )));
+            }
+            if !getter.is_instance_of::<PyString>() && !getter.is_callable() {
+                return Err(PyTypeError::new_err(format!(
+                    "Getter for {key} must be an attribute name or a callable"
+                )));
+            }
+            spec.push((key, getter.unbind()));
         }
-        if let Some(value) = tick_rate {
-            write_f32(&mut buffer, value);
+        self.getters.insert(name.to_string(), spec);
+        Ok(())
+    }
+
+    /// `payload` is a dict, or an object read through the getters
+    /// registered for `name`.
+    pub fn encode_message(
+        &self,
+        py: Python,
+        name: &str,
+        payload: &Bound<'_, PyAny>,
+    ) -> PyResult<Py<PyBytes>> {
+        let codec = self.codec()?;
+        let schema = codec.message_schema(name).map_err(codec_error)?;
+        let message = message_value_from_py(&codec, &self.getters, schema, payload)?;
+        let mut buffer = Vec::with_capacity(256);
+        codec
+            .write_message(schema, &message, &mut buffer)
+            .map_err(codec_error)?;
+        Ok(PyBytes::new(py, &buffer).unbind())
+    }
+
+    #[pyo3(signature = (name, bytes, nest_inline=false, zero_copy=false))]
+    pub fn decode_message(
+        &self,
+        py: Python,
+        name: &str,
+        bytes: &Bound<'_, PyBytes>,
+        nest_inline: bool,
+        zero_copy: bool,
+    ) -> PyResult<Py<PyDict>> {
+        let codec = self.codec()?.with_inline_nesting(nest_inline);
+        if zero_copy {
+            let message = codec
+                .decode_message_ref(name, bytes.as_bytes())
+                .map_err(codec_error)?;
+            return Ok(
+                message_ref_to_py(&message, nest_inline, &BufferView::new(bytes)?)?.unbind(),
+            );
         }
-        if let Some(value) = pong {
-            write_u32(&mut buffer, value);
+        let message = codec
+            .decode_message(name, bytes.as_bytes())
+            .map_err(codec_error)?;
+        Ok(message_to_py(py, &message)?.unbind())
+    }
+}
+
+/// Writes each client's frame around sections shared by every client; see
+/// `netcode::FrameWriter`. Payloads are read like `NetCodec.encode_frame`
+/// payloads, with the codec's schema and getters.
+#[pyclass(name = "FrameWriter")]
+pub struct PyFrameWriter {
+    codec: Py<NetCodec>,
+    writer: FrameWriter,
+}
+
+#[pymethods]
+impl PyFrameWriter {
+    #[new]
+    pub fn new(codec: Py<NetCodec>) -> Self {
+        PyFrameWriter {
+            codec,
+            writer: FrameWriter::new(),
         }
+    }
+
+    pub fn set_shared(&mut self, py: Python, payload: &Bound<'_, PyDict>) -> PyResult<()> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let frame = frame_from_py(&codec, &net_codec.getters, payload)?;
+        self.writer.set_shared(&codec, &frame).map_err(codec_error)
+    }
 
-        if let Some(value) = area {
-            write_bytes(&mut buffer, &value);
+    pub fn clear_shared(&mut self) {
+        self.writer.clear_shared();
+    }
+
+    pub fn write(&mut self, py: Python, payload: &Bound<'_, PyDict>) -> PyResult<Py<PyBytes>> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let client = frame_from_py(&codec, &net_codec.getters, payload)?;
+        let frame = self.writer.write(&codec, &client).map_err(codec_error)?;
+        Ok(PyBytes::new(py, frame).unbind())
+    }
+
+    /// Writes the frame to the start of `out`, a writable buffer, and
+    /// returns its length. A `bytearray` is grown to fit.
+    pub fn write_into(
+        &mut self,
+        py: Python,
+        payload: &Bound<'_, PyDict>,
+        out: &Bound<'_, PyAny>,
+    ) -> PyResult<usize> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let client = frame_from_py(&codec, &net_codec.getters, payload)?;
+        let frame = self.writer.write(&codec, &client).map_err(codec_error)?;
+        if let Ok(array) = out.cast::<PyByteArray>() {
+            if array.len() < frame.len() {
+                array.resize(frame.len())?;
+            }
         }
-        if let Some(value) = map {
-            write_bytes(&mut buffer, &value);
+        let buffer = PyBuffer::<u8>::get(out)?;
+        let cells = buffer
+            .as_mut_slice(py)
+            .ok_or_else(|| PyTypeError::new_err("Expected a writable contiguous buffer"))?;
+        if cells.len() < frame.len() {
+            return Err(PyValueError::new_err(format!(
+                "Buffer of {} bytes is too small for a {}-byte frame",
+                cells.len(),
+                frame.len()
+            )));
         }
-        if let Some(value) = chat {
-            write_bytes(&mut buffer, &value);
+        for (cell, byte) in cells.iter().zip(frame) {
+            cell.set(*byte);
         }
-        if let Some(value) = settings {
-            write_bytes(&mut buffer, &value);
+        Ok(frame.len())
+    }
+}
+
+/// Delta-encodes entity lists per client; see `netcode::SnapshotStore`.
+/// Entities are read and written with the codec's schema, format and
+/// getters.
+#[pyclass(name = "SnapshotStore")]
+pub struct PySnapshotStore {
+    codec: Py<NetCodec>,
+    store: SnapshotStore,
+}
+
+#[pymethods]
+impl PySnapshotStore {
+    #[new]
+    #[pyo3(signature = (codec, message="Entity", history=32))]
+    pub fn new(codec: Py<NetCodec>, message: &str, history: usize) -> Self {
+        PySnapshotStore {
+            codec,
+            store: SnapshotStore::new(message, history),
         }
-        if let Some(value) = mod_tools_response {
-            write_bytes(&mut buffer, &value);
+    }
+
+    pub fn ack(&mut self, client_id: u32, sequence: u32) {
+        self.store.ack(client_id, sequence);
+    }
+
+    pub fn acked(&self, client_id: u32) -> Option<u32> {
+        self.store.acked(client_id)
+    }
+
+    pub fn remove_client(&mut self, client_id: u32) {
+        self.store.remove_client(client_id);
+    }
+
+    pub fn encode_entities(
+        &mut self,
+        py: Python,
+        client_id: u32,
+        sequence: u32,
+        entities: &Bound<'_, PyList>,
+    ) -> PyResult<Py<PyBytes>> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let schema = codec
+            .message_schema(self.store.message())
+            .map_err(codec_error)?;
+        let entities = entities
+            .iter()
+            .map(|item| message_value_from_py(&codec, &net_codec.getters, schema, &item))
+            .collect::<PyResult<Vec<_>>>()?;
+        let mut buffer = Vec::with_capacity(1024);
+        self.store
+            .encode_entities(&codec, client_id, sequence, &entities, &mut buffer)
+            .map_err(codec_error)?;
+        Ok(PyBytes::new(py, &buffer).unbind())
+    }
+
+    #[pyo3(signature = (client_id, sequence, bytes, nest_inline=false))]
+    pub fn decode_entities(
+        &mut self,
+        py: Python,
+        client_id: u32,
+        sequence: u32,
+        bytes: &Bound<'_, PyBytes>,
+        nest_inline: bool,
+    ) -> PyResult<Py<PyList>> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?.with_inline_nesting(nest_inline);
+        let entities = self
+            .store
+            .decode_entities(&codec, client_id, sequence, bytes.as_bytes())
+            .map_err(codec_error)?;
+        let list = PyList::empty(py);
+        for entity in &entities {
+            list.append(message_to_py(py, entity)?)?;
         }
-        if let Some(value) = quest_data {
-            write_bytes(&mut buffer, &value);
+        Ok(list.unbind())
+    }
+}
+
+#[pyclass(name = "SnapshotBuffer")]
+pub struct PySnapshotBuffer {
+    buffer: SnapshotBuffer,
+}
+
+#[pymethods]
+impl PySnapshotBuffer {
+    #[new]
+    #[pyo3(signature = (tick_rate=20.0, jitter_window=0.1, max_extrapolation=0.25))]
+    pub fn new(tick_rate: f32, jitter_window: f64, max_extrapolation: f64) -> PyResult<Self> {
+        let buffer = SnapshotBuffer::new(BufferConfig {
+            tick_rate,
+            jitter_window,
+            max_extrapolation,
+        })
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PySnapshotBuffer { buffer })
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[getter]
+    pub fn latest_sequence(&self) -> Option<u32> {
+        self.buffer.latest_sequence()
+    }
+
+    #[getter]
+    pub fn latest_time(&self) -> Option<f64> {
+        self.buffer.latest_time()
+    }
+
+    pub fn render_time(&self) -> Option<f64> {
+        self.buffer.render_time()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    pub fn push_frame(&mut self, frame: &Bound<'_, PyDict>) -> PyResult<bool> {
+        Ok(self.buffer.push_frame(&snapshot_frame_from_py(frame)?))
+    }
+
+    pub fn sample(&self, py: Python, time: f64) -> PyResult<Py<PyList>> {
+        let list = PyList::empty(py);
+        for entity in self.buffer.sample(time) {
+            let dict = PyDict::new(py);
+            dict.set_item("id", entity.id)?;
+            dict.set_item("x", entity.x)?;
+            dict.set_item("y", entity.y)?;
+            if let Some(radius) = entity.radius {
+                dict.set_item("radius", radius)?;
+            }
+            list.append(dict)?;
         }
-        if let Some(value) = x_entities {
-            write_bytes(&mut buffer, &value);
+        Ok(list.unbind())
+    }
+}
+
+type PositionTuple = (u32, f32, f32, f32);
+
+#[pyclass(name = "PositionEncoder")]
+pub struct PyPositionEncoder {
+    encoder: PositionEncoder,
+}
+
+#[pymethods]
+impl PyPositionEncoder {
+    #[new]
+    #[pyo3(signature = (position_scale=16.0, radius_scale=16.0))]
+    pub fn new(position_scale: f32, radius_scale: f32) -> PyResult<Self> {
+        Ok(PyPositionEncoder {
+            encoder: PositionEncoder::new(position_config(position_scale, radius_scale)?),
+        })
+    }
+
+    /// Encodes `(id, x, y, radius)` updates into the position sections of a
+    /// frame; the returned dict only holds the non-empty ones and can be
+    /// merged into an `encode_frame` payload.
+    pub fn encode(&mut self, py: Python, updates: Vec<PositionTuple>) -> PyResult<Py<PyDict>> {
+        let updates: Vec<PositionUpdate> = updates
+            .into_iter()
+            .map(|(id, x, y, radius)| PositionUpdate { id, x, y, radius })
+            .collect();
+        let mut sections = Message::new();
+        self.encoder.encode(&updates).write_to_frame(&mut sections);
+        Ok(message_to_py(py, &sections)?.unbind())
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.encoder.remove(id);
+    }
+
+    pub fn reset(&mut self) {
+        self.encoder.reset();
+    }
+}
+
+#[pyclass(name = "PositionDecoder")]
+pub struct PyPositionDecoder {
+    decoder: PositionDecoder,
+}
+
+#[pymethods]
+impl PyPositionDecoder {
+    #[new]
+    #[pyo3(signature = (position_scale=16.0, radius_scale=16.0))]
+    pub fn new(position_scale: f32, radius_scale: f32) -> PyResult<Self> {
+        Ok(PyPositionDecoder {
+            decoder: PositionDecoder::new(position_config(position_scale, radius_scale)?),
+        })
+    }
+
+    /// Applies the position sections of a decoded frame and returns the
+    /// updated `(id, x, y, radius)` tuples, sorted by id.
+    pub fn decode(&mut self, frame: &Bound<'_, PyDict>) -> PyResult<Vec<PositionTuple>> {
+        let mut streams = PositionStreams::default();
+        for stream in PositionStream::ALL {
+            if let Some(bytes) = get_bytes(frame, stream.section())? {
+                *streams.get_mut(stream) = bytes;
+            }
         }
-        if let Some(value) = y_entities {
-            write_bytes(&mut buffer, &value);
+        let updates = self.decoder.decode(&streams).map_err(codec_error)?;
+        Ok(updates.iter().map(position_tuple).collect())
+    }
+
+    pub fn get(&self, id: u32) -> Option<PositionTuple> {
+        self.decoder.get(id).as_ref().map(position_tuple)
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.decoder.remove(id);
+    }
+
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+fn position_config(position_scale: f32, radius_scale: f32) -> PyResult<PositionConfig> {
+    for scale in [position_scale, radius_scale] {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "Position scales must be positive numbers, got {scale}"
+            )));
         }
-        if let Some(value) = xy_entities {
-            write_bytes(&mut buffer, &value);
+    }
+    Ok(PositionConfig {
+        position_scale,
+        radius_scale,
+    })
+}
+
+fn position_tuple(update: &PositionUpdate) -> PositionTuple {
+    (update.id, update.x, update.y, update.radius)
+}
+
+/// Compresses encoded frames for the socket and decompresses received ones;
+/// see `netcode::FrameCompressor`. Both ends need the same dictionary.
+#[pyclass(name = "FrameCompressor")]
+pub struct PyFrameCompressor {
+    compressor: FrameCompressor,
+}
+
+#[pymethods]
+impl PyFrameCompressor {
+    #[new]
+    #[pyo3(signature = (dictionary=None, level=3, min_size=64, max_frame_size=1 << 20))]
+    pub fn new(
+        dictionary: Option<&[u8]>,
+        level: i32,
+        min_size: usize,
+        max_frame_size: usize,
+    ) -> PyResult<Self> {
+        let config = CompressionConfig {
+            level,
+            min_size,
+            max_frame_size,
+        };
+        let compressor = FrameCompressor::with_dictionary(dictionary.unwrap_or_default(), config)
+            .map_err(codec_error)?;
+        Ok(PyFrameCompressor { compressor })
+    }
+
+    pub fn compress(&mut self, py: Python, frame: &[u8]) -> PyResult<Py<PyBytes>> {
+        let mut buffer = Vec::new();
+        self.compressor
+            .compress(frame, &mut buffer)
+            .map_err(codec_error)?;
+        Ok(PyBytes::new(py, &buffer).unbind())
+    }
+
+    /// Returns the frame ready for `NetCodec.decode_frame`, whether or not
+    /// it was compressed.
+    pub fn decompress(&mut self, py: Python, bytes: &[u8]) -> PyResult<Py<PyBytes>> {
+        let frame = self.compressor.decompress(bytes).map_err(codec_error)?;
+        Ok(PyBytes::new(py, frame).unbind())
+    }
+
+    /// Trains a dictionary from a list of uncompressed frames; the
+    /// `netcode-dict` tool does the same from a capture file.
+    #[staticmethod]
+    #[pyo3(signature = (frames, max_size=16384))]
+    pub fn train(py: Python, frames: Vec<Vec<u8>>, max_size: usize) -> PyResult<Py<PyBytes>> {
+        let dictionary = train_dictionary(&frames, max_size).map_err(codec_error)?;
+        Ok(PyBytes::new(py, &dictionary).unbind())
+    }
+}
+
+/// Splits encoded frames into packets that fit the transport's MTU; see
+/// `netcode::Fragmenter`.
+#[pyclass(name = "Fragmenter")]
+pub struct PyFragmenter {
+    fragmenter: Fragmenter,
+}
+
+#[pymethods]
+impl PyFragmenter {
+    #[new]
+    #[pyo3(signature = (max_packet_size=1200))]
+    pub fn new(max_packet_size: usize) -> Self {
+        let config = FragmentConfig {
+            max_packet_size,
+            ..FragmentConfig::default()
+        };
+        PyFragmenter {
+            fragmenter: Fragmenter::new(config),
         }
-        if let Some(value) = xy_radius_entities {
-            write_bytes(&mut buffer, &value);
+    }
+
+    pub fn split(&mut self, py: Python, frame: &[u8]) -> PyResult<Vec<Py<PyBytes>>> {
+        let packets = self.fragmenter.split(frame).map_err(codec_error)?;
+        Ok(packets
+            .iter()
+            .map(|packet| PyBytes::new(py, packet).unbind())
+            .collect())
+    }
+}
+
+/// Rebuilds frames from `Fragmenter` packets; see `netcode::Reassembler`.
+/// Times are in seconds.
+#[pyclass(name = "Reassembler")]
+pub struct PyReassembler {
+    reassembler: Reassembler,
+}
+
+#[pymethods]
+impl PyReassembler {
+    #[new]
+    #[pyo3(signature = (timeout=1.0, max_frame_size=1 << 20, max_packet_size=1200, max_frames=64))]
+    pub fn new(
+        timeout: f64,
+        max_frame_size: usize,
+        max_packet_size: usize,
+        max_frames: usize,
+    ) -> Self {
+        let config = FragmentConfig {
+            max_packet_size,
+            timeout,
+            max_frame_size,
+            max_frames,
+        };
+        PyReassembler {
+            reassembler: Reassembler::new(config),
         }
+    }
 
-        let entities = get_list(payload, "entities")?;
-        let global_entities = get_list(payload, "global_entities")?;
-        let debug_objects = get_list(payload, "debug_objects")?;
-        let entity_schema = get_schema("Entity")?;
-        let debug_object_schema = get_schema("ServerDebugObject")?;
-        encode_entity_list(py, entity_schema, entities, &mut buffer)?;
-        encode_entity_list(py, entity_schema, global_entities, &mut buffer)?;
-        encode_entity_list(py, debug_object_schema, debug_objects, &mut buffer)?;
+    /// Returns the frame once its last fragment arrives, otherwise None.
+    pub fn insert(&mut self, py: Python, packet: &[u8], now: f64) -> PyResult<Option<Py<PyBytes>>> {
+        let frame = self.reassembler.insert(packet, now).map_err(codec_error)?;
+        Ok(frame.map(|frame| PyBytes::new(py, &frame).unbind()))
+    }
 
-        Ok(PyBytes::new(py, &buffer).unbind())
+    pub fn expire(&mut self, now: f64) -> usize {
+        self.reassembler.expire(now)
     }
 
-    pub fn encode_message(
-        &self,
+    pub fn pending(&self) -> usize {
+        self.reassembler.pending()
+    }
+
+    pub fn clear(&mut self) {
+        self.reassembler.clear();
+    }
+}
+
+type ReceivedTuple<'py> = (
+    Option<Bound<'py, PyDict>>,
+    Vec<(String, Bound<'py, PyDict>)>,
+    Vec<String>,
+);
+
+/// One end of a connection over an unreliable transport; see
+/// `netcode::Channel`. Frames are read like `NetCodec.encode_frame`, and
+/// their `reliable_sections` are resent until acked. Times are in seconds.
+#[pyclass(name = "Channel")]
+pub struct PyChannel {
+    codec: Py<NetCodec>,
+    channel: Channel,
+}
+
+#[pymethods]
+impl PyChannel {
+    #[new]
+    #[pyo3(signature = (
+        codec,
+        reliable_sections=None,
+        initial_rtt=0.2,
+        min_resend=0.05,
+        max_reliable_bytes=1024
+    ))]
+    pub fn new(
+        codec: Py<NetCodec>,
+        reliable_sections: Option<Vec<String>>,
+        initial_rtt: f64,
+        min_resend: f64,
+        max_reliable_bytes: usize,
+    ) -> Self {
+        let defaults = ChannelConfig::default();
+        let config = ChannelConfig {
+            reliable_sections: reliable_sections.unwrap_or(defaults.reliable_sections),
+            initial_rtt,
+            min_resend,
+            max_reliable_bytes,
+        };
+        PyChannel {
+            codec,
+            channel: Channel::new(config),
+        }
+    }
+
+    pub fn send(
+        &mut self,
         py: Python,
-        name: &str,
         payload: &Bound<'_, PyDict>,
+        now: f64,
     ) -> PyResult<Py<PyBytes>> {
-        let schema = get_schema(name)?;
-        let mut buffer = Vec::with_capacity(256);
-        encode_message(py, schema, payload, &mut buffer)?;
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let frame = frame_from_py(&codec, &net_codec.getters, payload)?;
+        let mut buffer = Vec::with_capacity(2048);
+        self.channel
+            .send_frame(&codec, &frame, now, &mut buffer)
+            .map_err(codec_error)?;
         Ok(PyBytes::new(py, &buffer).unbind())
     }
 
-    pub fn decode_message(
-        &self,
+    /// Returns the frame, or None if the packet is stale or the frame failed
+    /// to decode, the reliable sections now deliverable as `(name, dict)`
+    /// pairs in the order they were sent, and why the frame or any section
+    /// failed to decode. A packet that cannot be parsed raises instead.
+    #[pyo3(signature = (packet, now, nest_inline=false))]
+    pub fn receive<'py>(
+        &mut self,
+        py: Python<'py>,
+        packet: &[u8],
+        now: f64,
+        nest_inline: bool,
+    ) -> PyResult<ReceivedTuple<'py>> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?.with_inline_nesting(nest_inline);
+        let received = self
+            .channel
+            .receive_frame(&codec, packet, now)
+            .map_err(codec_error)?;
+        let frame = received
+            .frame
+            .map(|frame| message_to_py(py, &frame))
+            .transpose()?;
+        let reliable = received
+            .reliable
+            .into_iter()
+            .map(|(name, message)| Ok((name, message_to_py(py, &message)?)))
+            .collect::<PyResult<_>>()?;
+        let errors = received.errors.iter().map(ToString::to_string).collect();
+        Ok((frame, reliable, errors))
+    }
+
+    /// The smoothed round-trip time, or None until a packet is acked.
+    #[getter]
+    pub fn rtt(&self) -> Option<f64> {
+        self.channel.rtt()
+    }
+
+    #[getter]
+    pub fn packet_loss(&self) -> f64 {
+        self.channel.packet_loss()
+    }
+
+    /// The number of reliable sections not acked yet.
+    #[getter]
+    pub fn unacked(&self) -> usize {
+        self.channel.unacked()
+    }
+
+    #[getter]
+    pub fn local_sequence(&self) -> u16 {
+        self.channel.local_sequence()
+    }
+
+    #[getter]
+    pub fn remote_sequence(&self) -> Option<u16> {
+        self.channel.remote_sequence()
+    }
+}
+
+/// Buffers one client's inputs by client tick; see `netcode::InputBuffer`.
+/// Inputs are pushed as encoded input frames or as dicts.
+#[pyclass(name = "InputBuffer")]
+pub struct PyInputBuffer {
+    codec: Py<NetCodec>,
+    buffer: InputBuffer,
+}
+
+#[pymethods]
+impl PyInputBuffer {
+    #[new]
+    #[pyo3(signature = (codec, capacity=32, target_lead=2, tolerance=1))]
+    pub fn new(codec: Py<NetCodec>, capacity: usize, target_lead: u32, tolerance: u32) -> Self {
+        PyInputBuffer {
+            codec,
+            buffer: InputBuffer::new(InputConfig {
+                capacity,
+                target_lead,
+                tolerance,
+            }),
+        }
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[getter]
+    pub fn latest_tick(&self) -> Option<u32> {
+        self.buffer.latest_tick()
+    }
+
+    #[getter]
+    pub fn consumed_tick(&self) -> Option<u32> {
+        self.buffer.consumed_tick()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Returns "buffered", "stale", "duplicate" or "full".
+    pub fn push(&mut self, py: Python, input: &Bound<'_, PyAny>) -> PyResult<&'static str> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let input = if let Ok(bytes) = input.cast::<PyBytes>() {
+            codec.decode_input(bytes.as_bytes()).map_err(codec_error)?
+        } else {
+            let layout = codec.input_layout();
+            layout_from_py(&codec, &net_codec.getters, layout, input.cast::<PyDict>()?)?
+        };
+        let status = self.buffer.push(input).map_err(codec_error)?;
+        Ok(match status {
+            InputStatus::Buffered => "buffered",
+            InputStatus::Stale => "stale",
+            InputStatus::Duplicate => "duplicate",
+            InputStatus::Full => "full",
+        })
+    }
+
+    /// Returns the input for `tick` as a dict, or None if it has not
+    /// arrived.
+    pub fn pop(&mut self, py: Python, tick: u32) -> PyResult<Option<Py<PyDict>>> {
+        self.buffer
+            .pop(tick)
+            .map(|input| Ok(message_to_py(py, &input)?.unbind()))
+            .transpose()
+    }
+
+    /// Ticks the client should slow down by, negative when it should speed
+    /// up, 0 when on time, or None before any input arrived.
+    pub fn timing(&self, tick: u32) -> Option<i64> {
+        self.buffer.timing(tick).map(|timing| match timing {
+            InputTiming::OnTime => 0,
+            InputTiming::Ahead(ticks) => i64::from(ticks),
+            InputTiming::Behind(ticks) => -i64::from(ticks),
+        })
+    }
+}
+
+/// Keeps one client's entity updates under a byte budget; see
+/// `netcode::EntityScheduler`. Frames are read like `NetCodec.encode_frame`.
+#[pyclass(name = "EntityScheduler")]
+pub struct PyEntityScheduler {
+    codec: Py<NetCodec>,
+    scheduler: EntityScheduler,
+}
+
+#[pymethods]
+impl PyEntityScheduler {
+    #[new]
+    #[pyo3(signature = (codec, default_priority=1.0, outside_view=0.0))]
+    pub fn new(codec: Py<NetCodec>, default_priority: f32, outside_view: f32) -> Self {
+        PyEntityScheduler {
+            codec,
+            scheduler: EntityScheduler::new(SchedulerConfig {
+                default_priority,
+                outside_view,
+            }),
+        }
+    }
+
+    pub fn set_priority(&mut self, id: u32, priority: f32) {
+        self.scheduler.set_priority(id, priority);
+    }
+
+    pub fn accumulator(&self, id: u32) -> Option<f32> {
+        self.scheduler.accumulator(id)
+    }
+
+    /// Forgets entity `id`, whose priority and accumulator are otherwise
+    /// kept while it is missing from frames.
+    pub fn remove(&mut self, id: u32) {
+        self.scheduler.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.scheduler.clear();
+    }
+
+    /// Removes the entities that do not fit in `budget` bytes from the
+    /// `entities` and `global_entities` lists of `payload`, given the
+    /// client's view as `(x, y, width, height)`. Returns the ids of the
+    /// deferred entities. Their updates are dropped, not queued, so send
+    /// their full state the next time they are sent.
+    pub fn select(
+        &mut self,
         py: Python,
-        name: &str,
-        bytes: &Bound<'_, PyBytes>,
-    ) -> PyResult<Py<PyDict>> {
-        let schema = get_schema(name)?;
-        let mut cursor = Cursor::new(bytes.as_bytes());
-        let dict = decode_message(py, schema, &mut cursor)?;
-        Ok(dict.into())
+        payload: &Bound<'_, PyDict>,
+        view: (f32, f32, f32, f32),
+        budget: usize,
+    ) -> PyResult<Vec<u32>> {
+        let net_codec = self.codec.borrow(py);
+        let codec = net_codec.codec()?;
+        let frame = frame_from_py(&codec, &net_codec.getters, payload)?;
+        let (x, y, width, height) = view;
+        let view = ViewRect {
+            x,
+            y,
+            width,
+            height,
+        };
+        let selection = self
+            .scheduler
+            .select(&codec, &frame, &view, budget)
+            .map_err(codec_error)?;
+        for (key, kept) in [
+            ("entities", &selection.entities),
+            ("global_entities", &selection.global_entities),
+        ] {
+            let Some(items) = get_list(payload, key)? else {
+                continue;
+            };
+            let items = kept
+                .iter()
+                .map(|&index| items.get_item(index))
+                .collect::<PyResult<Vec<_>>>()?;
+            payload.set_item(key, PyList::new(py, items)?)?;
+        }
+        Ok(selection.deferred)
     }
 }
 
-fn encode_entity_list<'py>(
-    py: Python<'py>,
-    schema: &'py MessageSchema,
-    list: Option<Bound<'py, PyList>>,
-    buffer: &mut Vec<u8>,
-) -> PyResult<()> {
-    let entities = match list {
-        Some(list) => list,
-        None => {
-            write_u32(buffer, 0);
-            return Ok(());
+/// Converts the parts of a decoded frame `SnapshotBuffer::push_frame` reads.
+fn snapshot_frame_from_py(frame: &Bound<'_, PyDict>) -> PyResult<Message> {
+    let mut message = Message::new();
+    if let Some(sequence) = get_u32(frame, "sequence")? {
+        message.insert("sequence", sequence);
+    }
+    if let Some(tick_rate) = get_f32(frame, "tick_rate")? {
+        message.insert("tick_rate", tick_rate);
+    }
+    if get_bool(frame, "reset")? {
+        message.insert("reset", true);
+    }
+    for key in ["entities", "global_entities"] {
+        let Some(list) = get_list(frame, key)? else {
+            continue;
+        };
+        let mut entities = Vec::with_capacity(list.len());
+        for item in list.iter() {
+            let item = item.cast::<PyDict>()?;
+            let mut entity = Message::new();
+            if let Some(id) = get_u32(item, "id")? {
+                entity.insert("id", id);
+            }
+            for key in ["x", "y", "radius"] {
+                if let Some(value) = get_f32(item, key)? {
+                    entity.insert(key, value);
+                }
+            }
+            entities.push(Value::Message(entity));
         }
-    };
+        message.insert(key, entities);
+    }
+    Ok(message)
+}
+
+pub(crate) fn codec_error(err: CodecError) -> PyErr {
+    PyTypeError::new_err(err.to_string())
+}
 
-    write_u32(buffer, entities.len() as u32);
-    for item in entities.iter() {
-        let dict = item.cast::<PyDict>()?;
-        encode_message(py, schema, dict, buffer)?;
+pub(crate) fn schema_error(errors: Vec<SchemaError>) -> PyErr {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    PyValueError::new_err(messages.join("; "))
+}
+
+fn wire_format(compact: bool) -> WireFormat {
+    if compact {
+        WireFormat::Compact
+    } else {
+        WireFormat::Fixed
     }
-    Ok(())
 }
 
-fn encode_message<'py>(
-    py: Python<'py>,
-    schema: &'py MessageSchema,
-    dict: &Bound<'py, PyDict>,
-    buffer: &mut Vec<u8>,
-) -> PyResult<()> {
-    let mut entries: Vec<(u16, &netcode::FieldSchema, Bound<'py, PyAny>)> = Vec::new();
+fn frame_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    payload: &Bound<'_, PyDict>,
+) -> PyResult<Message> {
+    layout_from_py(codec, getters, codec.frame_layout(), payload)
+}
+
+fn layout_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    layout: &FrameSchema,
+    payload: &Bound<'_, PyDict>,
+) -> PyResult<Message> {
+    let mut frame = Message::new();
+    for section in &layout.sections {
+        let key = section.name.as_str();
+        let value = match section.kind {
+            SectionKind::Flag => get_bool(payload, key)?.then_some(Value::Bool(true)),
+            SectionKind::UInt32 => get_u32(payload, key)?.map(Value::from),
+            SectionKind::Float => get_f32(payload, key)?.map(Value::from),
+            SectionKind::Bytes => get_bytes(payload, key)?.map(Value::from),
+            SectionKind::Message if section.is_repeated => match get_list(payload, key)? {
+                Some(list) => {
+                    let schema = codec.frame_section_schema(section).map_err(codec_error)?;
+                    let items = list
+                        .iter()
+                        .map(|item| {
+                            message_value_from_py(codec, getters, schema, &item).map(Value::Message)
+                        })
+                        .collect::<PyResult<Vec<_>>>()?;
+                    Some(Value::List(items))
+                }
+                None => None,
+            },
+            SectionKind::Message => get_message(codec, getters, payload, section)?,
+        };
+        if let Some(value) = value {
+            frame.insert(key, value);
+        }
+    }
+    Ok(frame)
+}
 
+/// Converts a dict, or an object with getters registered for `schema`.
+fn message_value_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    schema: &MessageSchema,
+    value: &Bound<'_, PyAny>,
+) -> PyResult<Message> {
+    if let Ok(dict) = value.cast::<PyDict>() {
+        return message_from_py(codec, getters, schema, dict);
+    }
+    let Some(spec) = getters.get(&schema.name) else {
+        return Err(PyTypeError::new_err(format!(
+            "Expected a dict or an object with registered getters for {}",
+            schema.name
+        )));
+    };
+    let mut message = Message::with_capacity(spec.len());
+    for (key, getter) in spec {
+        let getter = getter.bind(value.py());
+        let field_value = match getter.cast::<PyString>() {
+            Ok(name) => value.getattr(name)?,
+            Err(_) => getter.call1((value,))?,
+        };
+        if field_value.is_none() {
+            continue;
+        }
+        if let Some(group) = schema.inline_group(key) {
+            let inline = field_value.cast::<PyDict>()?;
+            message.insert(
+                key.as_str(),
+                inline_from_py(codec, getters, schema, group, inline)?,
+            );
+            continue;
+        }
+        if let Some(field) = schema.fields_by_name.get(key) {
+            message.insert(
+                key.as_str(),
+                field_value_from_py(codec, getters, field, &field_value)?,
+            );
+        }
+    }
+    Ok(message)
+}
+
+pub(crate) fn message_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    schema: &MessageSchema,
+    dict: &Bound<'_, PyDict>,
+) -> PyResult<Message> {
+    let mut message = Message::with_capacity(dict.len());
     for (key, value) in dict.iter() {
         if value.is_none() {
             continue;
         }
         let key_str: String = key.extract()?;
-        if key_str == "hero" {
-            let hero = value.cast::<PyDict>()?;
-            append_hero_fields(schema, hero, &mut entries)?;
+        if let Some(group) = schema.inline_group(&key_str) {
+            let inline = value.cast::<PyDict>()?;
+            message.insert(
+                key_str,
+                inline_from_py(codec, getters, schema, group, inline)?,
+            );
             continue;
         }
         if let Some(field) = schema.fields_by_name.get(&key_str) {
-            entries.push((field.number, field, value));
+            message.insert(key_str, field_value_from_py(codec, getters, field, &value)?);
         }
     }
-
-    entries.sort_by_key(|(number, _, _)| *number);
-    write_u16(buffer, entries.len() as u16);
-
-    for (number, field, value) in entries {
-        write_u16(buffer, number);
-        encode_field_value(py, field, &value, buffer)?;
-    }
-    Ok(())
+    Ok(message)
 }
 
-fn append_hero_fields<'py>(
-    schema: &'py MessageSchema,
-    hero: &Bound<'py, PyDict>,
-    entries: &mut Vec<(u16, &'py netcode::FieldSchema, Bound<'py, PyAny>)>,
-) -> PyResult<()> {
-    for (key, value) in hero.iter() {
+fn inline_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    schema: &MessageSchema,
+    group: &InlineGroup,
+    inline: &Bound<'_, PyDict>,
+) -> PyResult<Message> {
+    let mut message = Message::with_capacity(inline.len());
+    for (key, value) in inline.iter() {
         if value.is_none() {
             continue;
         }
         let key_str: String = key.extract()?;
+        if !group.accepts(&key_str) {
+            continue;
+        }
         if let Some(field) = schema.fields_by_name.get(&key_str) {
-            entries.push((field.number, field, value));
+            message.insert(key_str, field_value_from_py(codec, getters, field, &value)?);
         }
     }
-    Ok(())
+    Ok(message)
 }
 
-fn encode_field_value<'py>(
-    py: Python<'py>,
-    field: &netcode::FieldSchema,
-    value: &Bound<'py, PyAny>,
-    buffer: &mut Vec<u8>,
-) -> PyResult<()> {
+fn field_value_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    field: &FieldSchema,
+    value: &Bound<'_, PyAny>,
+) -> PyResult<Value> {
     if field.is_repeated {
         let list = value.cast::<PyList>()?;
-        write_u16(buffer, list.len() as u16);
-        for item in list.iter() {
-            encode_single_value(py, field, &item, buffer)?;
-        }
-        return Ok(());
+        let items = list
+            .iter()
+            .map(|item| single_value_from_py(codec, getters, field, &item))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(Value::List(items));
     }
 
-    encode_single_value(py, field, value, buffer)
+    single_value_from_py(codec, getters, field, value)
 }
 
-fn encode_single_value<'py>(
-    py: Python<'py>,
-    field: &netcode::FieldSchema,
-    value: &Bound<'py, PyAny>,
-    buffer: &mut Vec<u8>,
-) -> PyResult<()> {
+fn single_value_from_py(
+    codec: &Codec,
+    getters: &Getters,
+    field: &FieldSchema,
+    value: &Bound<'_, PyAny>,
+) -> PyResult<Value> {
+    let name = field.name.as_str();
     match field.kind {
-        FieldKind::Int32 | FieldKind::Enum => {
-            let value = extract_i32(value, field.name.as_str())?;
-            write_i32(buffer, value);
+        FieldKind::Int32 | FieldKind::Enum => Ok(Value::Int32(extract_i32(value, name)?)),
+        FieldKind::UInt32 => Ok(Value::UInt32(extract_u32(value, name)?)),
+        FieldKind::Int64 => Ok(Value::Int64(extract_i64(value, name)?)),
+        FieldKind::UInt64 => Ok(Value::UInt64(extract_u64(value, name)?)),
+        FieldKind::Float => Ok(Value::Float(extract_f32(value, name)?)),
+        FieldKind::Double => Ok(Value::Double(extract_f64(value, name)?)),
+        FieldKind::Bool => Ok(Value::Bool(extract_bool(value, name)?)),
+        FieldKind::String => Ok(Value::String(value.extract()?)),
+        FieldKind::Bytes => Ok(Value::Bytes(value.cast::<PyBytes>()?.as_bytes().to_vec())),
+        FieldKind::Message => {
+            let message_schema = codec.field_message_schema(field).map_err(codec_error)?;
+            let message = message_value_from_py(codec, getters, message_schema, value)?;
+            Ok(Value::Message(message))
+        }
+        FieldKind::Map => {
+            let map = field
+                .map
+                .as_deref()
+                .ok_or_else(|| PyTypeError::new_err(format!("Missing map schema for {name}")))?;
+            let dict = value.cast::<PyDict>()?;
+            let mut entries = Vec::with_capacity(dict.len());
+            for (key, value) in dict.iter() {
+                entries.push((
+                    single_value_from_py(codec, getters, &map.key, &key)?,
+                    single_value_from_py(codec, getters, &map.value, &value)?,
+                ));
+            }
+            Ok(Value::Map(entries))
         }
-        FieldKind::UInt32 => {
-            let value = extract_u32(value, field.name.as_str())?;
-            write_u32(buffer, value);
+    }
+}
+
+pub(crate) fn message_to_py<'py>(
+    py: Python<'py>,
+    message: &Message,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for (key, value) in message.iter() {
+        dict.set_item(key, value_to_py(py, value)?)?;
+    }
+    Ok(dict)
+}
+
+fn value_to_py(py: Python, value: &Value) -> PyResult<Py<PyAny>> {
+    match value {
+        Value::Int32(value) => value.into_py_any(py),
+        Value::UInt32(value) => value.into_py_any(py),
+        Value::Int64(value) => value.into_py_any(py),
+        Value::UInt64(value) => value.into_py_any(py),
+        Value::Float(value) => value.into_py_any(py),
+        Value::Double(value) => value.into_py_any(py),
+        Value::Bool(value) => value.into_py_any(py),
+        Value::String(value) => value.into_py_any(py),
+        Value::Bytes(value) => Ok(PyBytes::new(py, value).unbind().into()),
+        Value::Message(value) => Ok(message_to_py(py, value)?.unbind().into()),
+        Value::List(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(value_to_py(py, item)?)?;
+            }
+            Ok(list.unbind().into())
         }
-        FieldKind::Float => {
-            let value = extract_f32(value, field.name.as_str())?;
-            write_f32(buffer, value);
+        Value::Map(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(value_to_py(py, key)?, value_to_py(py, value)?)?;
+            }
+            Ok(dict.unbind().into())
         }
-        FieldKind::Bool => {
-            let value = extract_bool(value, field.name.as_str())?;
-            buffer.push(if value { 1 } else { 0 });
+    }
+}
+
+/// Slices memoryviews out of the buffer being decoded, so bytes fields
+/// share its memory.
+struct BufferView<'py> {
+    view: Bound<'py, PyMemoryView>,
+    start: usize,
+}
+
+impl<'py> BufferView<'py> {
+    fn new(bytes: &Bound<'py, PyBytes>) -> PyResult<Self> {
+        Ok(Self {
+            view: PyMemoryView::from(bytes.as_any())?,
+            start: bytes.as_bytes().as_ptr() as usize,
+        })
+    }
+
+    fn py(&self) -> Python<'py> {
+        self.view.py()
+    }
+
+    /// `bytes` must have been borrowed from the viewed buffer.
+    fn slice(&self, bytes: &[u8]) -> PyResult<Py<PyAny>> {
+        let start = bytes.as_ptr() as usize - self.start;
+        let range = PySlice::new(self.py(), start as isize, (start + bytes.len()) as isize, 1);
+        Ok(self.view.get_item(range)?.unbind())
+    }
+}
+
+fn frame_ref_to_py<'py>(
+    frame: &FrameRef,
+    nest_inline: bool,
+    buffer: &BufferView<'py>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(buffer.py());
+    for (key, value) in frame.iter() {
+        dict.set_item(key, value_ref_to_py(value, nest_inline, buffer)?)?;
+    }
+    Ok(dict)
+}
+
+fn message_ref_to_py<'py>(
+    message: &MessageRef,
+    nest_inline: bool,
+    buffer: &BufferView<'py>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let py = buffer.py();
+    let schema = message.schema();
+    let dict = PyDict::new(py);
+    let mut groups: Vec<Option<Bound<PyDict>>> = vec![None; schema.inline_groups.len()];
+    for entry in message.iter() {
+        let (key, value) = entry.map_err(codec_error)?;
+        let value = value_ref_to_py(&value, nest_inline, buffer)?;
+        let group = schema
+            .inline_groups
+            .iter()
+            .position(|group| nest_inline && group.nests(key));
+        match group {
+            Some(index) => groups[index]
+                .get_or_insert_with(|| PyDict::new(py))
+                .set_item(key, value)?,
+            None => dict.set_item(key, value)?,
         }
-        FieldKind::String => {
-            let value: String = value.extract()?;
-            write_bytes(buffer, value.as_bytes());
+    }
+    for (group, fields) in schema.inline_groups.iter().zip(groups) {
+        if let Some(fields) = fields {
+            dict.set_item(&group.name, fields)?;
         }
-        FieldKind::Bytes => {
-            let bytes = value.cast::<PyBytes>()?;
-            write_bytes(buffer, bytes.as_bytes());
+    }
+    Ok(dict)
+}
+
+fn value_ref_to_py(
+    value: &ValueRef,
+    nest_inline: bool,
+    buffer: &BufferView,
+) -> PyResult<Py<PyAny>> {
+    let py = buffer.py();
+    match value {
+        ValueRef::Int32(value) => value.into_py_any(py),
+        ValueRef::UInt32(value) => value.into_py_any(py),
+        ValueRef::Int64(value) => value.into_py_any(py),
+        ValueRef::UInt64(value) => value.into_py_any(py),
+        ValueRef::Float(value) => value.into_py_any(py),
+        ValueRef::Double(value) => value.into_py_any(py),
+        ValueRef::Bool(value) => value.into_py_any(py),
+        ValueRef::String(value) => value.into_py_any(py),
+        ValueRef::Bytes(value) => buffer.slice(value),
+        ValueRef::Message(value) => Ok(message_ref_to_py(value, nest_inline, buffer)?
+            .unbind()
+            .into()),
+        ValueRef::List(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(value_ref_to_py(item, nest_inline, buffer)?)?;
+            }
+            Ok(list.unbind().into())
         }
-        FieldKind::Message => {
-            let dict = value.cast::<PyDict>()?;
-            let message_schema = match field.type_name.as_deref() {
-                Some(name) => get_schema(name)?,
-                other => {
-                    return Err(PyTypeError::new_err(format!(
-                        "Unsupported message type for {}: {other:?}",
-                        field.name
-                    )))
-                }
-            };
-            encode_message(py, message_schema, dict, buffer)?;
+        ValueRef::Map(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(
+                    value_ref_to_py(key, nest_inline, buffer)?,
+                    value_ref_to_py(value, nest_inline, buffer)?,
+                )?;
+            }
+            Ok(dict.unbind().into())
         }
     }
-    Ok(())
 }
 
 fn extract_i32(value: &Bound<'_, PyAny>, name: &str) -> PyResult<i32> {
@@ -340,6 +1397,35 @@ fn extract_u32(value: &Bound<'_, PyAny>, name: &str) -> PyResult<u32> {
     Err(PyTypeError::new_err(format!("Expected uint32 for {name}")))
 }
 
+// Unlike the 32-bit extractors, out-of-range ints and floats raise
+// OverflowError instead of falling through to a TypeError.
+fn extract_i64(value: &Bound<'_, PyAny>, name: &str) -> PyResult<i64> {
+    if value.is_instance_of::<PyInt>() {
+        return value
+            .extract::<i64>()
+            .map_err(|_| PyOverflowError::new_err(format!("Value out of range for int64 {name}")));
+    }
+    if let Ok(v) = value.cast::<PyFloat>() {
+        let v = v.value();
+        if !(-(2f64.powi(63))..2f64.powi(63)).contains(&v) {
+            return Err(PyOverflowError::new_err(format!(
+                "Value out of range for int64 {name}"
+            )));
+        }
+        return Ok(v as i64);
+    }
+    Err(PyTypeError::new_err(format!("Expected int64 for {name}")))
+}
+
+fn extract_u64(value: &Bound<'_, PyAny>, name: &str) -> PyResult<u64> {
+    if value.is_instance_of::<PyInt>() {
+        return value.extract::<u64>().map_err(|_| {
+            PyOverflowError::new_err(format!("Value out of range for uint64 {name}"))
+        });
+    }
+    Err(PyTypeError::new_err(format!("Expected uint64 for {name}")))
+}
+
 fn extract_f32(value: &Bound<'_, PyAny>, name: &str) -> PyResult<f32> {
     if let Ok(v) = value.extract::<f32>() {
         return Ok(v);
@@ -350,6 +1436,17 @@ fn extract_f32(value: &Bound<'_, PyAny>, name: &str) -> PyResult<f32> {
     Err(PyTypeError::new_err(format!("Expected float for {name}")))
 }
 
+fn extract_f64(value: &Bound<'_, PyAny>, name: &str) -> PyResult<f64> {
+    if let Ok(v) = value.cast::<PyFloat>() {
+        return Ok(v.value());
+    }
+    if value.is_instance_of::<PyInt>() {
+        // float(int) raises OverflowError for ints beyond the double range.
+        return value.extract::<f64>();
+    }
+    Err(PyTypeError::new_err(format!("Expected double for {name}")))
+}
+
 fn extract_bool(value: &Bound<'_, PyAny>, name: &str) -> PyResult<bool> {
     if let Ok(v) = value.extract::<bool>() {
         return Ok(v);
@@ -368,28 +1465,6 @@ fn extract_bool(value: &Bound<'_, PyAny>, name: &str) -> PyResult<bool> {
     )))
 }
 
-fn write_u16(buffer: &mut Vec<u8>, value: u16) {
-    buffer.extend_from_slice(&value.to_le_bytes());
-}
-
-fn write_u32(buffer: &mut Vec<u8>, value: u32) {
-    buffer.extend_from_slice(&value.to_le_bytes());
-}
-
-fn write_i32(buffer: &mut Vec<u8>, value: i32) {
-    buffer.extend_from_slice(&value.to_le_bytes());
-}
-
-fn write_f32(buffer: &mut Vec<u8>, value: f32) {
-    buffer.extend_from_slice(&value.to_le_bytes());
-}
-
-fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
-    let len = bytes.len() as u32;
-    write_u32(buffer, len);
-    buffer.extend_from_slice(bytes);
-}
-
 fn get_u32(payload: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<u32>> {
     match payload.get_item(key)? {
         Some(value) if !value.is_none() => value.extract::<u32>().map(Some),
@@ -421,22 +1496,20 @@ fn get_bytes(payload: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<Vec<u8>>
     }
 }
 
-fn get_message_bytes(
-    py: Python,
+fn get_message(
+    codec: &Codec,
+    getters: &Getters,
     payload: &Bound<'_, PyDict>,
-    key: &str,
-    message_name: &str,
-) -> PyResult<Option<Vec<u8>>> {
-    match payload.get_item(key)? {
+    section: &FrameSection,
+) -> PyResult<Option<Value>> {
+    match payload.get_item(&section.name)? {
         Some(value) if !value.is_none() => {
             if let Ok(bytes) = value.cast::<PyBytes>() {
-                return Ok(Some(bytes.as_bytes().to_vec()));
+                return Ok(Some(Value::Bytes(bytes.as_bytes().to_vec())));
             }
-            let dict = value.cast::<PyDict>()?;
-            let schema = get_schema(message_name)?;
-            let mut buffer = Vec::with_capacity(128);
-            encode_message(py, schema, dict, &mut buffer)?;
-            Ok(Some(buffer))
+            let schema = codec.frame_section_schema(section).map_err(codec_error)?;
+            let message = message_value_from_py(codec, getters, schema, &value)?;
+            Ok(Some(Value::Message(message)))
         }
         _ => Ok(None),
     }
@@ -448,138 +1521,3 @@ fn get_list<'py>(payload: &Bound<'py, PyDict>, key: &str) -> PyResult<Option<Bou
         _ => Ok(None),
     }
 }
-
-fn get_schema(name: &str) -> PyResult<&'static MessageSchema> {
-    NET_SCHEMA
-        .messages
-        .get(name)
-        .ok_or_else(|| PyTypeError::new_err(format!("Unknown message schema: {name}")))
-}
-
-struct Cursor<'a> {
-    data: &'a [u8],
-    offset: usize,
-}
-
-impl<'a> Cursor<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Self { data, offset: 0 }
-    }
-
-    fn read_u8(&mut self) -> PyResult<u8> {
-        if self.offset >= self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let value = self.data[self.offset];
-        self.offset += 1;
-        Ok(value)
-    }
-
-    fn read_u16(&mut self) -> PyResult<u16> {
-        if self.offset + 2 > self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let value = u16::from_le_bytes(self.data[self.offset..self.offset + 2].try_into().unwrap());
-        self.offset += 2;
-        Ok(value)
-    }
-
-    fn read_u32(&mut self) -> PyResult<u32> {
-        if self.offset + 4 > self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let value = u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap());
-        self.offset += 4;
-        Ok(value)
-    }
-
-    fn read_i32(&mut self) -> PyResult<i32> {
-        if self.offset + 4 > self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let value = i32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap());
-        self.offset += 4;
-        Ok(value)
-    }
-
-    fn read_f32(&mut self) -> PyResult<f32> {
-        if self.offset + 4 > self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let value = f32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap());
-        self.offset += 4;
-        Ok(value)
-    }
-
-    fn read_bytes(&mut self) -> PyResult<&'a [u8]> {
-        let len = self.read_u32()? as usize;
-        if self.offset + len > self.data.len() {
-            return Err(PyTypeError::new_err("Unexpected end of buffer"));
-        }
-        let start = self.offset;
-        let end = start + len;
-        self.offset = end;
-        Ok(&self.data[start..end])
-    }
-}
-
-fn decode_message(py: Python, schema: &MessageSchema, cursor: &mut Cursor) -> PyResult<Py<PyDict>> {
-    let field_count = cursor.read_u16()? as usize;
-    let dict = PyDict::new(py);
-    for _ in 0..field_count {
-        let number = cursor.read_u16()?;
-        let field = schema
-            .fields_by_number
-            .get(&number)
-            .ok_or_else(|| PyTypeError::new_err(format!("Unknown field number: {number}")))?;
-        if field.is_repeated {
-            let count = cursor.read_u16()? as usize;
-            let list = PyList::empty(py);
-            for _ in 0..count {
-                let value = decode_single_value(py, field, cursor)?;
-                list.append(value)?;
-            }
-            dict.set_item(field.name.as_str(), list)?;
-        } else {
-            let value = decode_single_value(py, field, cursor)?;
-            dict.set_item(field.name.as_str(), value)?;
-        }
-    }
-    Ok(dict.into())
-}
-
-fn decode_single_value(
-    py: Python,
-    field: &netcode::FieldSchema,
-    cursor: &mut Cursor,
-) -> PyResult<Py<PyAny>> {
-    match field.kind {
-        FieldKind::Int32 | FieldKind::Enum => Ok(cursor.read_i32()?.into_py_any(py)?),
-        FieldKind::UInt32 => Ok(cursor.read_u32()?.into_py_any(py)?),
-        FieldKind::Float => Ok(cursor.read_f32()?.into_py_any(py)?),
-        FieldKind::Bool => Ok((cursor.read_u8()? == 1).into_py_any(py)?),
-        FieldKind::String => {
-            let bytes = cursor.read_bytes()?;
-            let value = String::from_utf8(bytes.to_vec())
-                .map_err(|_| PyTypeError::new_err("Invalid UTF-8 string"))?;
-            Ok(value.into_py_any(py)?)
-        }
-        FieldKind::Bytes => {
-            let bytes = cursor.read_bytes()?;
-            Ok(PyBytes::new(py, bytes).unbind().into())
-        }
-        FieldKind::Message => {
-            let message_schema = match field.type_name.as_deref() {
-                Some(name) => get_schema(name)?,
-                None => {
-                    return Err(PyTypeError::new_err(format!(
-                        "Missing message type for {}",
-                        field.name
-                    )))
-                }
-            };
-            let value = decode_message(py, message_schema, cursor)?;
-            Ok(value.into())
-        }
-    }
-}
@@ -1,7 +1,7 @@
-use netcode::{FieldKind, NET_SCHEMA};
+use netcode::{net_schema, FieldKind};
 use serialization::*;
 
-use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::{PyOverflowError, PyTypeError};
 use pyo3::prelude::*;
 use pyo3::pyclass;
 use pyo3::pymethods;
@@ -10,6 +10,8 @@ use pyo3::IntoPyObjectExt;
 use smallvec::SmallVec;
 use std::collections::HashMap;
 
+use crate::netcode::schema_error;
+
 #[pyclass(name = "DiffFieldSet", unsendable)]
 pub struct DiffFieldSetWrapper {
     diff_field_set: DiffFieldSet,
@@ -62,7 +64,8 @@ impl DiffFieldSetWrapper {
             )));
         }
 
-        let schema = NET_SCHEMA.messages.get(message_name).ok_or_else(|| {
+        let net_schema = net_schema().map_err(schema_error)?;
+        let schema = net_schema.messages.get(message_name).ok_or_else(|| {
             PyTypeError::new_err(format!("Unknown message schema: {message_name}"))
         })?;
 
@@ -104,10 +107,11 @@ impl DiffFieldSetWrapper {
         profile_name: &str,
         field_defaults: Option<&Bound<'_, PyAny>>,
     ) -> PyResult<Self> {
-        let profile = NET_SCHEMA.profiles.get(profile_name).ok_or_else(|| {
+        let net_schema = net_schema().map_err(schema_error)?;
+        let profile = net_schema.profiles.get(profile_name).ok_or_else(|| {
             PyTypeError::new_err(format!("Unknown profile schema: {profile_name}"))
         })?;
-        let schema = NET_SCHEMA.messages.get(&profile.message).ok_or_else(|| {
+        let schema = net_schema.messages.get(&profile.message).ok_or_else(|| {
             PyTypeError::new_err(format!(
                 "Unknown message schema '{}' for profile {}",
                 profile.message, profile_name
@@ -185,7 +189,9 @@ impl DiffFieldSetWrapper {
 
     #[staticmethod]
     pub fn has_profile(profile_name: &str) -> bool {
-        NET_SCHEMA.profiles.contains_key(profile_name)
+        net_schema()
+            .map(|schema| schema.profiles.contains_key(profile_name))
+            .unwrap_or(false)
     }
 
     pub fn update(&mut self, updates: &Bound<'_, PyList>) -> PyResult<()> {
@@ -333,6 +339,9 @@ fn convert_to_py_list(
             FieldValue::Float(val) => val.into_py_any(py)?,
             FieldValue::Bool(val) => val.into_py_any(py)?,
             FieldValue::String(val) => val.into_py_any(py)?,
+            FieldValue::Int64(val) => val.into_py_any(py)?,
+            FieldValue::UInt64(val) => val.into_py_any(py)?,
+            FieldValue::Double(val) => val.into_py_any(py)?,
             FieldValue::None => py.None(),
         };
         py_list.append((index, py_value))?;
@@ -358,10 +367,13 @@ fn convert_to_py_dict(
 fn field_kind_to_type(kind: FieldKind) -> Option<FieldType> {
     match kind {
         FieldKind::Int32 | FieldKind::UInt32 | FieldKind::Enum => Some(FieldType::Int),
+        FieldKind::Int64 => Some(FieldType::Int64),
+        FieldKind::UInt64 => Some(FieldType::UInt64),
         FieldKind::Float => Some(FieldType::Float),
+        FieldKind::Double => Some(FieldType::Double),
         FieldKind::Bool => Some(FieldType::Bool),
         FieldKind::String => Some(FieldType::String),
-        FieldKind::Bytes | FieldKind::Message => None,
+        FieldKind::Bytes | FieldKind::Message | FieldKind::Map => None,
     }
 }
 
@@ -413,6 +425,57 @@ fn get_rust_value(
                 }
             }
         }
+        FieldType::Int64 => {
+            if let Ok(int_value) = value.cast_exact::<PyInt>() {
+                return Ok(FieldValue::Int64(int_value.extract::<i64>()?));
+            }
+            match value.extract::<i64>() {
+                Ok(val) => Ok(FieldValue::Int64(val)),
+                Err(err) if err.is_instance_of::<PyOverflowError>(value.py()) => Err(err),
+                Err(_) => {
+                    let value_type = value.get_type().name()?.to_string_lossy().into_owned();
+                    let value_repr = value.repr()?.to_string_lossy().into_owned();
+                    Err(PyTypeError::new_err(format!(
+                        "Expected an int64 value for {}, got {value_type} value {value_repr}",
+                        label()
+                    )))
+                }
+            }
+        }
+        FieldType::UInt64 => {
+            if let Ok(int_value) = value.cast_exact::<PyInt>() {
+                return Ok(FieldValue::UInt64(int_value.extract::<u64>()?));
+            }
+            match value.extract::<u64>() {
+                Ok(val) => Ok(FieldValue::UInt64(val)),
+                Err(err) if err.is_instance_of::<PyOverflowError>(value.py()) => Err(err),
+                Err(_) => {
+                    let value_type = value.get_type().name()?.to_string_lossy().into_owned();
+                    let value_repr = value.repr()?.to_string_lossy().into_owned();
+                    Err(PyTypeError::new_err(format!(
+                        "Expected a uint64 value for {}, got {value_type} value {value_repr}",
+                        label()
+                    )))
+                }
+            }
+        }
+        FieldType::Double => {
+            if let Ok(float_value) = value.cast_exact::<PyFloat>() {
+                return Ok(FieldValue::Double(float_value.value()));
+            }
+            match value.extract::<f64>() {
+                Ok(val) => Ok(FieldValue::Double(val)),
+                Err(err) if err.is_instance_of::<PyOverflowError>(value.py()) => Err(err),
+                Err(_) => {
+                    let value_type = value.get_type().name()?.to_string_lossy().into_owned();
+                    let value_repr = value.repr()?.to_string_lossy().into_owned();
+                    Err(PyTypeError::new_err(format!(
+                        "Expected a double value for {}, got {value_type} value {value_repr}",
+                        label()
+                    )))
+                }
+            }
+        }
         FieldType::Bool => {
             if let Ok(bool_value) = value.cast_exact::<PyBool>() {
                 return Ok(FieldValue::Bool(bool_value.is_true()));
@@ -492,6 +555,9 @@ fn set_py_dict_value(
         FieldValue::Float(val) => val.into_py_any(py)?,
         FieldValue::Bool(val) => val.into_py_any(py)?,
         FieldValue::String(val) => val.into_py_any(py)?,
+        FieldValue::Int64(val) => val.into_py_any(py)?,
+        FieldValue::UInt64(val) => val.into_py_any(py)?,
+        FieldValue::Double(val) => val.into_py_any(py)?,
         FieldValue::None => py.None(),
     };
     dict.set_item(name.as_str(), py_value)?;
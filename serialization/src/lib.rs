@@ -6,6 +6,9 @@ pub enum FieldValue {
     Float(f32),
     Bool(bool),
     String(String),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
     None,
 }
 
@@ -17,6 +20,9 @@ impl Clone for FieldValue {
             FieldValue::Float(value) => FieldValue::Float(*value),
             FieldValue::Bool(value) => FieldValue::Bool(*value),
             FieldValue::String(value) => FieldValue::String(value.clone()),
+            FieldValue::Int64(value) => FieldValue::Int64(*value),
+            FieldValue::UInt64(value) => FieldValue::UInt64(*value),
+            FieldValue::Double(value) => FieldValue::Double(*value),
             FieldValue::None => FieldValue::None,
         }
     }
@@ -27,6 +33,9 @@ pub enum FieldType {
     Float = 1,
     Bool = 2,
     String = 3,
+    Int64 = 4,
+    UInt64 = 5,
+    Double = 6,
 }
 
 impl FieldType {
@@ -36,6 +45,9 @@ impl FieldType {
             1 => Ok(FieldType::Float),
             2 => Ok(FieldType::Bool),
             3 => Ok(FieldType::String),
+            4 => Ok(FieldType::Int64),
+            5 => Ok(FieldType::UInt64),
+            6 => Ok(FieldType::Double),
             _ => Err(format!("Invalid field type: {}", value)),
         }
     }
@@ -100,3 +100,42 @@ fn test_diff_field_set() {
         ),])
     );
 }
+
+#[test]
+fn test_diff_field_set_wide_types() {
+    let field_types = SmallVec::from(vec![FieldType::Int64, FieldType::UInt64, FieldType::Double]);
+    let field_defaults = SmallVec::from(vec![
+        FieldValue::Int64(0),
+        FieldValue::UInt64(0),
+        FieldValue::Double(0.0),
+    ]);
+
+    let mut diff_field_set = DiffFieldSet::new(field_types, field_defaults);
+    diff_field_set.update(SmallVec::from(vec![
+        FieldValue::Int64(-5_000_000_000),
+        FieldValue::UInt64(0),
+        FieldValue::Double(0.1),
+    ]));
+
+    assert!(diff_field_set.has_changed());
+    assert_eq!(
+        diff_field_set.get_all(),
+        SmallVec::<[(usize, FieldValue); 16]>::from(vec![
+            (0, FieldValue::Int64(-5_000_000_000)),
+            (2, FieldValue::Double(0.1)),
+        ])
+    );
+
+    diff_field_set.update(SmallVec::from(vec![
+        FieldValue::Int64(-5_000_000_000),
+        FieldValue::UInt64(u64::MAX),
+        FieldValue::Double(0.1),
+    ]));
+
+    assert_eq!(
+        diff_field_set.get_diff(),
+        SmallVec::<[(usize, FieldValue); 16]>::from(vec![(1, FieldValue::UInt64(u64::MAX))])
+    );
+    assert!(FieldType::from_int(6).is_ok());
+    assert!(FieldType::from_int(7).is_err());
+}